@@ -5,6 +5,7 @@ use libbpf_rs::skel::{OpenSkel, Skel, SkelBuilder};
 use libbpf_rs::{Map, MapCore, Object, ProgramMut, RingBufferBuilder};
 use shared::clap::Parser;
 use shared::log::{self, error};
+use shared::nats_publisher::{Headers, NatsPublisher};
 use shared::nats_subjects::Subject;
 use shared::prost::Message;
 use shared::protobuf::ebpf_extractor::ctypes::{
@@ -18,10 +19,11 @@ use shared::protobuf::ebpf_extractor::{
 use shared::protobuf::event::event::PeerObserverEvent;
 use shared::protobuf::event::Event;
 use shared::simple_logger;
-use shared::{async_nats, clap, nats_util, tokio};
+use shared::{clap, nats_util, tokio};
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::mem::MaybeUninit;
+use std::sync::Arc;
 use std::time::Duration;
 use std::time::SystemTime;
 
@@ -270,10 +272,15 @@ async fn run() -> Result<(), RuntimeError> {
     let skel: tracing::TracingSkel = open_skel.load()?;
     let obj = skel.object();
 
-    let nc = nats_util::prepare_connection(&args.nats)?
-        .connect(&args.nats.address)
+    let nats_client = nats_util::prepare_connection(&args.nats)?
+        .connect(nats_util::server_addrs(&args.nats)?)
         .await?;
     log::info!("Connected to NATS server at {}", &args.nats.address);
+    let nc = Arc::new(NatsPublisher::new(
+        nats_client,
+        false,
+        nats_util::pending_buffer(&args.nats),
+    ));
 
     // Update the ebpf-extractor docs in the README.md when editing the active_tracepoints.
     let mut active_tracepoints = vec![];
@@ -424,7 +431,7 @@ async fn run() -> Result<(), RuntimeError> {
     }
 }
 
-fn handle_net_conn_closed(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_net_conn_closed(data: &[u8], nc: &Arc<NatsPublisher>) -> i32 {
     let closed = ClosedConnection::from_bytes(data);
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Connection(connection::ConnectionEvent {
@@ -440,7 +447,12 @@ fn handle_net_conn_closed(data: &[u8], nc: &async_nats::Client) -> i32 {
     let nc = nc.clone();
     tokio::spawn(async move {
         if let Err(e) = nc
-            .publish(Subject::NetConn.to_string(), proto.encode_to_vec().into())
+            .publish(
+                &Subject::NetConn.to_string(),
+                &Headers::new(),
+                proto.encode_to_vec(),
+                &(),
+            )
             .await
         {
             error!(
@@ -452,7 +464,7 @@ fn handle_net_conn_closed(data: &[u8], nc: &async_nats::Client) -> i32 {
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_net_conn_outbound(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_net_conn_outbound(data: &[u8], nc: &Arc<NatsPublisher>) -> i32 {
     let outbound = OutboundConnection::from_bytes(data);
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Connection(connection::ConnectionEvent {
@@ -470,7 +482,12 @@ fn handle_net_conn_outbound(data: &[u8], nc: &async_nats::Client) -> i32 {
     let nc = nc.clone();
     tokio::spawn(async move {
         if let Err(e) = nc
-            .publish(Subject::NetConn.to_string(), proto.encode_to_vec().into())
+            .publish(
+                &Subject::NetConn.to_string(),
+                &Headers::new(),
+                proto.encode_to_vec(),
+                &(),
+            )
             .await
         {
             error!(
@@ -482,7 +499,7 @@ fn handle_net_conn_outbound(data: &[u8], nc: &async_nats::Client) -> i32 {
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_net_conn_inbound(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_net_conn_inbound(data: &[u8], nc: &Arc<NatsPublisher>) -> i32 {
     let inbound = InboundConnection::from_bytes(data);
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Connection(connection::ConnectionEvent {
@@ -499,7 +516,12 @@ fn handle_net_conn_inbound(data: &[u8], nc: &async_nats::Client) -> i32 {
     let nc = nc.clone();
     tokio::spawn(async move {
         if let Err(e) = nc
-            .publish(Subject::NetConn.to_string(), proto.encode_to_vec().into())
+            .publish(
+                &Subject::NetConn.to_string(),
+                &Headers::new(),
+                proto.encode_to_vec(),
+                &(),
+            )
             .await
         {
             error!(
@@ -511,7 +533,7 @@ fn handle_net_conn_inbound(data: &[u8], nc: &async_nats::Client) -> i32 {
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_net_conn_inbound_evicted(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_net_conn_inbound_evicted(data: &[u8], nc: &Arc<NatsPublisher>) -> i32 {
     let evicted = ClosedConnection::from_bytes(data);
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Connection(connection::ConnectionEvent {
@@ -530,7 +552,12 @@ fn handle_net_conn_inbound_evicted(data: &[u8], nc: &async_nats::Client) -> i32
     let nc = nc.clone();
     tokio::spawn(async move {
         if let Err(e) = nc
-            .publish(Subject::NetConn.to_string(), proto.encode_to_vec().into())
+            .publish(
+                &Subject::NetConn.to_string(),
+                &Headers::new(),
+                proto.encode_to_vec(),
+                &(),
+            )
             .await
         {
             error!(
@@ -542,7 +569,7 @@ fn handle_net_conn_inbound_evicted(data: &[u8], nc: &async_nats::Client) -> i32
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_net_conn_misbehaving(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_net_conn_misbehaving(data: &[u8], nc: &Arc<NatsPublisher>) -> i32 {
     let misbehaving = MisbehavingConnection::from_bytes(data);
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Connection(connection::ConnectionEvent {
@@ -561,7 +588,12 @@ fn handle_net_conn_misbehaving(data: &[u8], nc: &async_nats::Client) -> i32 {
     let nc = nc.clone();
     tokio::spawn(async move {
         if let Err(e) = nc
-            .publish(Subject::NetConn.to_string(), proto.encode_to_vec().into())
+            .publish(
+                &Subject::NetConn.to_string(),
+                &Headers::new(),
+                proto.encode_to_vec(),
+                &(),
+            )
             .await
         {
             error!(
@@ -573,7 +605,7 @@ fn handle_net_conn_misbehaving(data: &[u8], nc: &async_nats::Client) -> i32 {
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_net_message(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_net_message(data: &[u8], nc: &Arc<NatsPublisher>) -> i32 {
     let message = P2PMessage::from_bytes(data);
     let protobuf_message = match message.decode_to_protobuf_network_message() {
         Ok(msg) => msg,
@@ -597,7 +629,12 @@ fn handle_net_message(data: &[u8], nc: &async_nats::Client) -> i32 {
     let nc = nc.clone();
     tokio::spawn(async move {
         if let Err(e) = nc
-            .publish(Subject::NetMsg.to_string(), proto.encode_to_vec().into())
+            .publish(
+                &Subject::NetMsg.to_string(),
+                &Headers::new(),
+                proto.encode_to_vec(),
+                &(),
+            )
             .await
         {
             error!("could not publish message in 'handle_net_message': {}", e);
@@ -606,7 +643,7 @@ fn handle_net_message(data: &[u8], nc: &async_nats::Client) -> i32 {
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_addrman_new(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_addrman_new(data: &[u8], nc: &Arc<NatsPublisher>) -> i32 {
     let new = AddrmanInsertNew::from_bytes(data);
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Addrman(addrman::AddrmanEvent {
@@ -622,7 +659,12 @@ fn handle_addrman_new(data: &[u8], nc: &async_nats::Client) -> i32 {
     let nc = nc.clone();
     tokio::spawn(async move {
         if let Err(e) = nc
-            .publish(Subject::Addrman.to_string(), proto.encode_to_vec().into())
+            .publish(
+                &Subject::Addrman.to_string(),
+                &Headers::new(),
+                proto.encode_to_vec(),
+                &(),
+            )
             .await
         {
             error!("could not publish message in 'handle_addrman_new': {}", e);
@@ -631,7 +673,7 @@ fn handle_addrman_new(data: &[u8], nc: &async_nats::Client) -> i32 {
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_addrman_tried(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_addrman_tried(data: &[u8], nc: &Arc<NatsPublisher>) -> i32 {
     let tried = AddrmanInsertTried::from_bytes(data);
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Addrman(addrman::AddrmanEvent {
@@ -647,7 +689,12 @@ fn handle_addrman_tried(data: &[u8], nc: &async_nats::Client) -> i32 {
     let nc = nc.clone();
     tokio::spawn(async move {
         if let Err(e) = nc
-            .publish(Subject::Addrman.to_string(), proto.encode_to_vec().into())
+            .publish(
+                &Subject::Addrman.to_string(),
+                &Headers::new(),
+                proto.encode_to_vec(),
+                &(),
+            )
             .await
         {
             error!("could not publish message in 'handle_addrman_tried': {}", e);
@@ -656,7 +703,7 @@ fn handle_addrman_tried(data: &[u8], nc: &async_nats::Client) -> i32 {
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_mempool_added(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_mempool_added(data: &[u8], nc: &Arc<NatsPublisher>) -> i32 {
     let added = MempoolAdded::from_bytes(data);
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Mempool(mempool::MempoolEvent {
@@ -672,7 +719,12 @@ fn handle_mempool_added(data: &[u8], nc: &async_nats::Client) -> i32 {
     let nc = nc.clone();
     tokio::spawn(async move {
         if let Err(e) = nc
-            .publish(Subject::Mempool.to_string(), proto.encode_to_vec().into())
+            .publish(
+                &Subject::Mempool.to_string(),
+                &Headers::new(),
+                proto.encode_to_vec(),
+                &(),
+            )
             .await
         {
             error!("could not publish message in 'handle_mempool_added': {}", e);
@@ -681,7 +733,7 @@ fn handle_mempool_added(data: &[u8], nc: &async_nats::Client) -> i32 {
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_mempool_removed(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_mempool_removed(data: &[u8], nc: &Arc<NatsPublisher>) -> i32 {
     let removed = MempoolRemoved::from_bytes(data);
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Mempool(mempool::MempoolEvent {
@@ -697,7 +749,12 @@ fn handle_mempool_removed(data: &[u8], nc: &async_nats::Client) -> i32 {
     let nc = nc.clone();
     tokio::spawn(async move {
         if let Err(e) = nc
-            .publish(Subject::Mempool.to_string(), proto.encode_to_vec().into())
+            .publish(
+                &Subject::Mempool.to_string(),
+                &Headers::new(),
+                proto.encode_to_vec(),
+                &(),
+            )
             .await
         {
             error!(
@@ -709,7 +766,7 @@ fn handle_mempool_removed(data: &[u8], nc: &async_nats::Client) -> i32 {
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_mempool_replaced(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_mempool_replaced(data: &[u8], nc: &Arc<NatsPublisher>) -> i32 {
     let replaced = MempoolReplaced::from_bytes(data);
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Mempool(mempool::MempoolEvent {
@@ -725,7 +782,12 @@ fn handle_mempool_replaced(data: &[u8], nc: &async_nats::Client) -> i32 {
     let nc = nc.clone();
     tokio::spawn(async move {
         if let Err(e) = nc
-            .publish(Subject::Mempool.to_string(), proto.encode_to_vec().into())
+            .publish(
+                &Subject::Mempool.to_string(),
+                &Headers::new(),
+                proto.encode_to_vec(),
+                &(),
+            )
             .await
         {
             error!(
@@ -737,7 +799,7 @@ fn handle_mempool_replaced(data: &[u8], nc: &async_nats::Client) -> i32 {
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_mempool_rejected(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_mempool_rejected(data: &[u8], nc: &Arc<NatsPublisher>) -> i32 {
     let rejected = MempoolRejected::from_bytes(data);
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Mempool(mempool::MempoolEvent {
@@ -753,7 +815,12 @@ fn handle_mempool_rejected(data: &[u8], nc: &async_nats::Client) -> i32 {
     let nc = nc.clone();
     tokio::spawn(async move {
         if let Err(e) = nc
-            .publish(Subject::Mempool.to_string(), proto.encode_to_vec().into())
+            .publish(
+                &Subject::Mempool.to_string(),
+                &Headers::new(),
+                proto.encode_to_vec(),
+                &(),
+            )
             .await
         {
             error!(
@@ -765,7 +832,7 @@ fn handle_mempool_rejected(data: &[u8], nc: &async_nats::Client) -> i32 {
     RINGBUFF_CALLBACK_OK
 }
 
-fn handle_validation_block_connected(data: &[u8], nc: &async_nats::Client) -> i32 {
+fn handle_validation_block_connected(data: &[u8], nc: &Arc<NatsPublisher>) -> i32 {
     let connected = ValidationBlockConnected::from_bytes(data);
     let proto = match Event::new(PeerObserverEvent::EbpfExtractor(Ebpf {
         ebpf_event: Some(ebpf::EbpfEvent::Validation(validation::ValidationEvent {
@@ -784,8 +851,10 @@ fn handle_validation_block_connected(data: &[u8], nc: &async_nats::Client) -> i3
     tokio::spawn(async move {
         if let Err(e) = nc
             .publish(
-                Subject::Validation.to_string(),
-                proto.encode_to_vec().into(),
+                &Subject::Validation.to_string(),
+                &Headers::new(),
+                proto.encode_to_vec(),
+                &(),
             )
             .await
         {
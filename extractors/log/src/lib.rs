@@ -1,9 +1,10 @@
 use error::RuntimeError;
-use shared::async_nats::{self};
 use shared::clap;
 use shared::clap::Parser;
 use shared::log;
-use shared::log_matchers::parse_log_event;
+use shared::log_matchers::{is_log_line, parse_log_event};
+use shared::metricserver;
+use shared::nats_publisher::{Headers, NatsPublisher};
 use shared::nats_subjects::Subject;
 use shared::nats_util::{self, NatsArgs};
 use shared::prost::Message;
@@ -16,21 +17,27 @@ use shared::tokio::{
     sync::watch,
     time,
 };
+use std::path::PathBuf;
 
 mod error;
+mod metrics;
+mod tail;
+
+pub use metrics::Metrics;
+use tail::LogFileTailer;
 
 // from libc crate
 pub const O_NONBLOCK: i32 = 2048;
 
-/// The peer-observer log-extractor reads lines from a pipe to a Bitcoin node
-/// debug.log pipe (named pipe / FIFO) and publishes parsed lines as events
-/// into a NATS pub-sub queue.
+/// The peer-observer log-extractor reads lines from a Bitcoin node debug.log
+/// pipe (named pipe / FIFO) or file and publishes parsed lines as events into
+/// a NATS pub-sub queue.
 #[derive(Parser, Debug)]
 #[clap(group(
-    clap::ArgGroup::new("pipe")
+    clap::ArgGroup::new("input")
         .required(true)
         .multiple(false)
-        .args(&["bitcoind_pipe"]),
+        .args(&["bitcoind_pipe", "bitcoind_log_file"]),
 ))]
 #[command(version, about, long_about = None)]
 pub struct Args {
@@ -40,7 +47,22 @@ pub struct Args {
 
     /// Path to the bitcoind log pipe (named pipe / FIFO).
     #[arg(short, long)]
-    pub bitcoind_pipe: String,
+    pub bitcoind_pipe: Option<String>,
+
+    /// Path to the bitcoind debug.log file. New lines are read as they are
+    /// appended to the file. The file is reopened when it's rotated.
+    #[arg(long)]
+    pub bitcoind_log_file: Option<String>,
+
+    /// Read the `--bitcoind-log-file` from the start instead of only reading
+    /// lines appended after the extractor started.
+    #[arg(long, requires = "bitcoind_log_file")]
+    pub from_start: bool,
+
+    /// If set, the extractor serves Prometheus metrics about itself on this
+    /// address (e.g. 127.0.0.1:8285).
+    #[arg(long)]
+    pub metrics_address: Option<String>,
 
     /// The log level the extractor should run with. Valid log levels are "trace",
     /// "debug", "info", "warn", "error". See https://docs.rs/log/latest/log/enum.Level.html.
@@ -52,33 +74,55 @@ impl Args {
     pub fn new(nats: NatsArgs, bitcoind_pipe: String, log_level: log::Level) -> Args {
         Self {
             nats,
-            bitcoind_pipe,
+            bitcoind_pipe: Some(bitcoind_pipe),
+            bitcoind_log_file: None,
+            from_start: false,
+            metrics_address: None,
             log_level,
         }
     }
 }
 
-pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(), RuntimeError> {
+pub async fn run(args: Args, shutdown_rx: watch::Receiver<bool>) -> Result<(), RuntimeError> {
+    let metrics = Metrics::new();
+    if let Some(address) = &args.metrics_address {
+        metricserver::start(address, Some(metrics.registry.clone()))?;
+    }
+
     let nats_client = nats_util::prepare_connection(&args.nats)?
-        .connect(&args.nats.address)
+        .connect(nats_util::server_addrs(&args.nats)?)
         .await?;
     log::info!("Connected to NATS server at {}", &args.nats.address);
+    let publisher = NatsPublisher::new(nats_client, false, nats_util::pending_buffer(&args.nats));
 
-    log::info!("Opening bitcoind log pipe at {}...", &args.bitcoind_pipe);
-    let file = open_pipe(&args.bitcoind_pipe, shutdown_rx.clone()).await?;
-    log::info!("Opened bitcoind log pipe at {}", &args.bitcoind_pipe);
+    match (&args.bitcoind_pipe, &args.bitcoind_log_file) {
+        (_, Some(log_file)) => {
+            read_log_file(&publisher, log_file, args.from_start, &metrics, shutdown_rx).await
+        }
+        (Some(pipe), None) => read_pipe(&publisher, pipe, &metrics, shutdown_rx).await,
+        // clap requires one of them
+        (None, None) => unreachable!("neither a bitcoind pipe nor log file was set"),
+    }
+}
+
+async fn read_pipe(
+    publisher: &NatsPublisher,
+    pipe: &str,
+    metrics: &Metrics,
+    mut shutdown_rx: watch::Receiver<bool>,
+) -> Result<(), RuntimeError> {
+    log::info!("Opening bitcoind log pipe at {}...", pipe);
+    let file = open_pipe(pipe, shutdown_rx.clone()).await?;
+    log::info!("Opened bitcoind log pipe at {}", pipe);
     let reader = BufReader::new(file);
     let mut lines = reader.lines();
 
-    log::info!(
-        "Started reading lines from bitcoind log pipe at {}",
-        &args.bitcoind_pipe
-    );
+    log::info!("Started reading lines from bitcoind log pipe at {}", pipe);
     loop {
         tokio::select! {
             line = lines.next_line() => {
                 match line {
-                    Ok(Some(line)) => process_log(&nats_client, &line).await,
+                    Ok(Some(line)) => process_log(publisher, &line, metrics).await,
                     Ok(None) => {
                         // Since we use O_NONBLOCK, we need to wait here for a
                         // bit to avoid spinning here if we don't have anything
@@ -97,18 +141,42 @@ pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(
                 }
             },
             res = shutdown_rx.changed() => {
-                match res {
-                    Ok(_) => {
-                        if *shutdown_rx.borrow() {
-                            log::info!("log-extractor received shutdown signal.");
-                            break;
-                        }
-                    }
-                    Err(_) => {
-                        // all senders dropped -> treat as shutdown
-                        log::warn!("The shutdown notification sender was dropped. Shutting down.");
-                        break;
-                    }
+                if shutdown_requested(res, &shutdown_rx) {
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn read_log_file(
+    publisher: &NatsPublisher,
+    log_file: &str,
+    from_start: bool,
+    metrics: &Metrics,
+    mut shutdown_rx: watch::Receiver<bool>,
+) -> Result<(), RuntimeError> {
+    wait_until_exists(log_file, &shutdown_rx).await?;
+    let mut tailer = LogFileTailer::open(PathBuf::from(log_file), from_start).await?;
+    log::info!(
+        "Started reading lines from bitcoind log file at {} (from start: {})",
+        log_file,
+        from_start
+    );
+
+    loop {
+        // Reading from a file doesn't block until new lines are written, so
+        // the available lines are read before checking for a shutdown.
+        while let Some(line) = tailer.next_line().await? {
+            process_log(publisher, &line, metrics).await;
+        }
+        tokio::select! {
+            _ = time::sleep(time::Duration::from_millis(25)) => (),
+            res = shutdown_rx.changed() => {
+                if shutdown_requested(res, &shutdown_rx) {
+                    break;
                 }
             }
         }
@@ -117,14 +185,42 @@ pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(
     Ok(())
 }
 
-async fn process_log(nats_client: &async_nats::Client, line: &str) {
+/// Returns whether the extractor should shut down after the shutdown
+/// notification changed with the result `res`.
+fn shutdown_requested(
+    res: Result<(), watch::error::RecvError>,
+    shutdown_rx: &watch::Receiver<bool>,
+) -> bool {
+    match res {
+        Ok(_) => {
+            if *shutdown_rx.borrow() {
+                log::info!("log-extractor received shutdown signal.");
+                return true;
+            }
+            false
+        }
+        Err(_) => {
+            // all senders dropped -> treat as shutdown
+            log::warn!("The shutdown notification sender was dropped. Shutting down.");
+            true
+        }
+    }
+}
+
+async fn process_log(publisher: &NatsPublisher, line: &str, metrics: &Metrics) {
     log::trace!("Read log line: {}", line);
+    metrics.lines_processed_total.inc();
+    if !is_log_line(line) {
+        metrics.parse_errors_total.inc();
+    }
     match Event::new(PeerObserverEvent::LogExtractor(parse_log_event(line))) {
         Ok(proto) => {
-            if let Err(e) = nats_client
+            if let Err(e) = publisher
                 .publish(
-                    Subject::LogExtractor.to_string(),
-                    proto.encode_to_vec().into(),
+                    &Subject::LogExtractor.to_string(),
+                    &Headers::new(),
+                    proto.encode_to_vec(),
+                    &metrics.publish_counters(),
                 )
                 .await
             {
@@ -140,11 +236,28 @@ async fn process_log(nats_client: &async_nats::Client, line: &str) {
 }
 
 async fn open_pipe(path: &str, shutdown_rx: watch::Receiver<bool>) -> Result<File, std::io::Error> {
-    // Fail after MAX_RETRIES if the pipe doesn't exist yet.
+    wait_until_exists(path, &shutdown_rx).await?;
+
+    OpenOptions::new()
+        .read(true)
+        .write(false)
+        // We need to use O_NONBLOCK here, otherwise a pipe without a writer
+        // will block the tokio async routine on next_line() and we can't
+        // e.g. CTRL+C anymore.
+        .custom_flags(O_NONBLOCK)
+        .open(path)
+        .await
+}
+
+async fn wait_until_exists(
+    path: &str,
+    shutdown_rx: &watch::Receiver<bool>,
+) -> Result<(), std::io::Error> {
+    // Fail after MAX_RETRIES if the pipe or file doesn't exist yet.
     const MAX_RETRIES: i32 = 30;
     for retries in 0..=MAX_RETRIES {
         if *shutdown_rx.borrow() {
-            log::info!("open_pipe received shutdown signal.");
+            log::info!("Received shutdown signal while waiting for {}.", path);
             return Err(std::io::Error::new(
                 std::io::ErrorKind::Interrupted,
                 "shutdown signal received",
@@ -153,7 +266,7 @@ async fn open_pipe(path: &str, shutdown_rx: watch::Receiver<bool>) -> Result<Fil
 
         if !std::path::Path::new(path).exists() {
             log::warn!(
-                "Pipe {} does not exist yet, retrying in 1s (retry: {}/{})",
+                "{} does not exist yet, retrying in 1s (retry: {}/{})",
                 path,
                 retries,
                 MAX_RETRIES
@@ -163,14 +276,5 @@ async fn open_pipe(path: &str, shutdown_rx: watch::Receiver<bool>) -> Result<Fil
             break;
         }
     }
-
-    OpenOptions::new()
-        .read(true)
-        .write(false)
-        // We need to use O_NONBLOCK here, otherwise a pipe without a writer
-        // will block the tokio async routine on next_line() and we can't
-        // e.g. CTRL+C anymore.
-        .custom_flags(O_NONBLOCK)
-        .open(path)
-        .await
+    Ok(())
 }
@@ -0,0 +1,77 @@
+use shared::nats_publisher::PublishCounters;
+use shared::prometheus::{IntCounter, Opts, Registry, register_int_counter_with_registry};
+
+pub const NAMESPACE: &str = "logextractor";
+
+/// Prometheus metrics about the log-extractor itself.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    pub registry: Registry,
+
+    pub lines_processed_total: IntCounter,
+    pub parse_errors_total: IntCounter,
+    pub nats_publish_errors: IntCounter,
+    pub nats_dropped_total: IntCounter,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some(NAMESPACE.to_string()), None)
+            .expect("Could not setup prometheus metric registry");
+
+        let lines_processed_total = register_int_counter_with_registry!(
+            Opts::new(
+                "lines_processed_total",
+                "Number of log lines read by the extractor."
+            ),
+            registry
+        )
+        .expect("Could not create metric 'lines_processed_total'");
+        let parse_errors_total = register_int_counter_with_registry!(
+            Opts::new(
+                "parse_errors_total",
+                "Number of read lines that aren't formatted like a debug.log line."
+            ),
+            registry
+        )
+        .expect("Could not create metric 'parse_errors_total'");
+        let nats_publish_errors = register_int_counter_with_registry!(
+            Opts::new(
+                "nats_publish_errors",
+                "Number of events that could not be published to NATS."
+            ),
+            registry
+        )
+        .expect("Could not create metric 'nats_publish_errors'");
+        let nats_dropped_total = register_int_counter_with_registry!(
+            Opts::new(
+                "nats_dropped_total",
+                "Number of events dropped as the buffer for events published while disconnected from the NATS server was full. These are also counted in nats_publish_errors."
+            ),
+            registry
+        )
+        .expect("Could not create metric 'nats_dropped_total'");
+
+        Self {
+            registry,
+            lines_processed_total,
+            parse_errors_total,
+            nats_publish_errors,
+            nats_dropped_total,
+        }
+    }
+
+    /// The counters the NATS publisher increments for the published events.
+    pub fn publish_counters(&self) -> PublishCounters {
+        PublishCounters {
+            errors: self.nats_publish_errors.clone(),
+            dropped: self.nats_dropped_total.clone(),
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
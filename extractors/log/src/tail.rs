@@ -0,0 +1,195 @@
+use shared::log;
+use shared::tokio::fs::{self, File};
+use shared::tokio::io::{AsyncBufReadExt, AsyncSeekExt, BufReader};
+use std::io::{self, SeekFrom};
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+
+/// Reads the lines appended to a log file, similar to `tail -F`. The file is
+/// reopened when it's rotated (i.e. replaced by a file with a different inode)
+/// or truncated.
+pub struct LogFileTailer {
+    path: PathBuf,
+    reader: BufReader<File>,
+    inode: u64,
+    // Number of bytes read from the current file.
+    position: u64,
+    // A line that wasn't completely written yet when it was read.
+    partial_line: String,
+}
+
+impl LogFileTailer {
+    /// Opens the log file at `path`. If `from_start` is set, the lines already
+    /// in the file are read too. Otherwise, only newly appended lines are read.
+    pub async fn open(path: PathBuf, from_start: bool) -> io::Result<Self> {
+        let mut file = File::open(&path).await?;
+        let inode = file.metadata().await?.ino();
+        let position = if from_start {
+            0
+        } else {
+            file.seek(SeekFrom::End(0)).await?
+        };
+        Ok(Self {
+            path,
+            reader: BufReader::new(file),
+            inode,
+            position,
+            partial_line: String::new(),
+        })
+    }
+
+    /// Returns the next complete line without the line break, or `None` if no
+    /// new line was written to the file yet.
+    pub async fn next_line(&mut self) -> io::Result<Option<String>> {
+        if let Some(line) = self.read_line().await? {
+            return Ok(Some(line));
+        }
+        if self.reopen_if_rotated().await? {
+            return self.read_line().await;
+        }
+        Ok(None)
+    }
+
+    async fn read_line(&mut self) -> io::Result<Option<String>> {
+        let read = self.reader.read_line(&mut self.partial_line).await?;
+        self.position += read as u64;
+        if !self.partial_line.ends_with('\n') {
+            // Either nothing or only a part of a line was written yet. The
+            // rest of the line is appended on the next read.
+            return Ok(None);
+        }
+        let line = std::mem::take(&mut self.partial_line);
+        Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+
+    /// Reopens the log file from the start if it was rotated or truncated.
+    /// Returns whether the file was reopened.
+    async fn reopen_if_rotated(&mut self) -> io::Result<bool> {
+        let metadata = match fs::metadata(&self.path).await {
+            Ok(metadata) => metadata,
+            // The file was moved away and the new one wasn't created yet.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        if metadata.ino() == self.inode && metadata.len() >= self.position {
+            return Ok(false);
+        }
+        if metadata.ino() != self.inode {
+            log::info!(
+                "Log file {} was rotated. Reopening it.",
+                self.path.display()
+            );
+        } else {
+            log::info!(
+                "Log file {} was truncated. Reading it from the start.",
+                self.path.display()
+            );
+        }
+        *self = Self::open(self.path.clone(), true).await?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::tokio;
+    use std::io::Write;
+    use std::path::Path;
+
+    const FIXTURE: &str = include_str!("fixtures/debug.log");
+
+    fn fixture_copy(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "log-extractor-test-{}-{}.log",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, FIXTURE).unwrap();
+        path
+    }
+
+    fn append(path: &Path, content: &str) {
+        let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+    }
+
+    async fn read_available(tailer: &mut LogFileTailer) -> Vec<String> {
+        let mut lines = vec![];
+        while let Some(line) = tailer.next_line().await.unwrap() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    #[tokio::test]
+    async fn test_from_start() {
+        let path = fixture_copy("from-start");
+        let mut tailer = LogFileTailer::open(path.clone(), true).await.unwrap();
+
+        let lines = read_available(&mut tailer).await;
+        assert_eq!(lines, FIXTURE.lines().collect::<Vec<_>>());
+
+        append(&path, "2025-10-28T02:20:15Z new line\n");
+        assert_eq!(
+            read_available(&mut tailer).await,
+            vec!["2025-10-28T02:20:15Z new line"]
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tail() {
+        let path = fixture_copy("tail");
+        let mut tailer = LogFileTailer::open(path.clone(), false).await.unwrap();
+        assert!(read_available(&mut tailer).await.is_empty());
+
+        // a line is only returned once it's completely written
+        append(&path, "2025-10-28T02:20:15Z partial");
+        assert!(read_available(&mut tailer).await.is_empty());
+        append(&path, " line\n");
+        assert_eq!(
+            read_available(&mut tailer).await,
+            vec!["2025-10-28T02:20:15Z partial line"]
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rotation() {
+        let path = fixture_copy("rotation");
+        let rotated = path.with_extension("log.1");
+        let mut tailer = LogFileTailer::open(path.clone(), false).await.unwrap();
+
+        append(&path, "2025-10-28T02:20:15Z before rotation\n");
+        std::fs::rename(&path, &rotated).unwrap();
+        assert_eq!(
+            read_available(&mut tailer).await,
+            vec!["2025-10-28T02:20:15Z before rotation"]
+        );
+        // the new file doesn't exist yet
+        assert!(read_available(&mut tailer).await.is_empty());
+
+        std::fs::write(&path, "2025-10-28T02:20:16Z after rotation\n").unwrap();
+        assert_eq!(
+            read_available(&mut tailer).await,
+            vec!["2025-10-28T02:20:16Z after rotation"]
+        );
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(rotated).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_truncation() {
+        let path = fixture_copy("truncation");
+        let mut tailer = LogFileTailer::open(path.clone(), true).await.unwrap();
+        read_available(&mut tailer).await;
+
+        std::fs::write(&path, "2025-10-28T02:20:16Z after truncation\n").unwrap();
+        assert_eq!(
+            read_available(&mut tailer).await,
+            vec!["2025-10-28T02:20:16Z after truncation"]
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -57,9 +57,7 @@ fn make_test_args(nats_port: u16, bitcoind_pipe: String) -> Args {
     Args::new(
         NatsArgs {
             address: format!("127.0.0.1:{}", nats_port),
-            username: None,
-            password: None,
-            password_file: None,
+            ..Default::default()
         },
         bitcoind_pipe,
         Level::Trace,
@@ -542,3 +540,52 @@ async fn test_integration_logextractor_unknown_with_all_metadata() {
     )
     .await;
 }
+
+#[tokio::test]
+async fn test_integration_logextractor_log_file_from_start() {
+    println!("test that we receive the events of a log file read from the start");
+    setup();
+    let nats_server = NatsServerForTesting::new(&[]).await;
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+    let nc = async_nats::connect(format!("127.0.0.1:{}", nats_server.port))
+        .await
+        .unwrap();
+    let mut sub = nc.subscribe("*").await.unwrap();
+
+    let mut args = make_test_args(nats_server.port, String::new());
+    args.bitcoind_pipe = None;
+    args.bitcoind_log_file =
+        Some(concat!(env!("CARGO_MANIFEST_DIR"), "/src/fixtures/debug.log").to_string());
+    args.from_start = true;
+    let log_extractor_handle = tokio::spawn(async move {
+        log_extractor::run(args, shutdown_rx)
+            .await
+            .expect("log extractor failed");
+    });
+
+    let (mut update_tip, mut misbehaving) = (false, false);
+    while !(update_tip && misbehaving) {
+        let msg = sub.next().await.unwrap();
+        let unwrapped = Event::decode(msg.payload).unwrap();
+        let Some(PeerObserverEvent::LogExtractor(l)) = unwrapped.peer_observer_event else {
+            continue;
+        };
+        match l.log_event {
+            Some(log::LogEvent::UpdateTipLog(tip)) => {
+                assert_eq!(tip.block_height, 437);
+                assert_eq!(tip.tx_count, 438);
+                update_tip = true;
+            }
+            Some(log::LogEvent::MisbehavingLog(m)) => {
+                assert_eq!(m.peer_id, 5);
+                assert_eq!(m.message, "invalid header received");
+                misbehaving = true;
+            }
+            _ => (),
+        }
+    }
+
+    shutdown_tx.send(true).unwrap();
+    log_extractor_handle.await.unwrap();
+}
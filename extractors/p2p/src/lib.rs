@@ -1,5 +1,4 @@
 use shared::{
-    async_nats,
     bitcoin::{
         Network as BitcoinNetwork,
         consensus::{Decodable, Encodable},
@@ -12,6 +11,7 @@ use shared::{
     },
     clap::{self, Parser, ValueEnum},
     log,
+    nats_publisher::{Headers, NatsPublisher},
     nats_subjects::Subject,
     nats_util,
     nats_util::NatsArgs,
@@ -33,6 +33,7 @@ use shared::{
 
 use std::{
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
 
@@ -173,9 +174,16 @@ pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(
     }
 
     let nats_client = nats_util::prepare_connection(&args.nats)?
-        .connect(&args.nats.address)
+        .connect(nats_util::server_addrs(&args.nats)?)
         .await?;
     log::info!("Connected to NATS server at {}", &args.nats.address);
+    // The connections share the buffer for events published while
+    // disconnected from the NATS server.
+    let publisher = Arc::new(NatsPublisher::new(
+        nats_client,
+        false,
+        nats_util::pending_buffer(&args.nats),
+    ));
 
     log::debug!("Starting TCP listener on {}..", args.p2p_address);
     let listener = TcpListener::bind(args.p2p_address.clone()).await?;
@@ -188,8 +196,8 @@ pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(
                 if let Ok(connection) = res {
                     let (socket, addr) = connection;
                     log::info!("accepted a new connection from: {}", addr);
-                    let nats_client_clone = nats_client.clone();
-                    shared::tokio::task::spawn(handle_connection(socket, network, args.clone(), nats_client_clone));
+                    let publisher_clone = publisher.clone();
+                    shared::tokio::task::spawn(handle_connection(socket, network, args.clone(), publisher_clone));
 
                 } else {
                     log::warn!("Could not accept connection on socket: {:?}", res);
@@ -219,7 +227,7 @@ async fn handle_connection(
     mut stream: TcpStream,
     network: BitcoinNetwork,
     args: Args,
-    nats_client: async_nats::Client,
+    publisher: Arc<NatsPublisher>,
 ) {
     let addr: &str = match stream.peer_addr() {
         Ok(addr) => &addr.to_string(),
@@ -286,7 +294,7 @@ async fn handle_connection(
                                     .as_nanos() as u64;
                                 let duration = now - nonce;
                                 log::debug!(target: addr, "processing the ping message took: {}ns", now - nonce);
-                                publish_ping_measurement_event(duration, &nats_client).await;
+                                publish_ping_measurement_event(duration, &publisher).await;
                             }
                             NetworkMessage::AddrV2(addrs) => {
                                 log::debug!(target: addr, "received addrv2: {:?}", addrs);
@@ -294,7 +302,7 @@ async fn handle_connection(
                                     .iter()
                                     .map(|addr_entry| addr_entry.clone().into())
                                     .collect();
-                                publish_addr_announcement_event(addresses, &nats_client).await;
+                                publish_addr_announcement_event(addresses, &publisher).await;
                             }
                             NetworkMessage::Inv(inventory) => {
                                 log::debug!(target: addr, "received inv: {:?}", inventory);
@@ -303,13 +311,13 @@ async fn handle_connection(
                                         .iter()
                                         .map(|i| (*i).into())
                                         .collect();
-                                    publish_inventory_announcement_event(items, &nats_client).await;
+                                    publish_inventory_announcement_event(items, &publisher).await;
                                 }
                             }
                             NetworkMessage::FeeFilter(feefilter) => {
                                 log::debug!(target: addr, "received feefilter: {}", feefilter);
                                 if !args.disable_feefilter {
-                                    publish_feefilter_announcement_event(*feefilter, &nats_client).await;
+                                    publish_feefilter_announcement_event(*feefilter, &publisher).await;
                                 }
                             }
                             NetworkMessage::Alert(_) => {
@@ -335,7 +343,7 @@ async fn handle_connection(
 
 async fn publish_addr_announcement_event(
     addresses: Vec<bitcoin_primitives::Address>,
-    nats_client: &async_nats::Client,
+    publisher: &NatsPublisher,
 ) {
     let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
         p2p_event: Some(p2p_extractor::p2p::P2pEvent::AddressAnnouncement(
@@ -345,10 +353,12 @@ async fn publish_addr_announcement_event(
 
     match proto_result {
         Ok(proto) => {
-            if let Err(e) = nats_client
+            if let Err(e) = publisher
                 .publish(
-                    Subject::P2PExtractor.to_string(),
-                    proto.encode_to_vec().into(),
+                    &Subject::P2PExtractor.to_string(),
+                    &Headers::new(),
+                    proto.encode_to_vec(),
+                    &(),
                 )
                 .await
             {
@@ -365,7 +375,7 @@ async fn publish_addr_announcement_event(
 
 async fn publish_inventory_announcement_event(
     inventory: Vec<bitcoin_primitives::InventoryItem>,
-    nats_client: &async_nats::Client,
+    publisher: &NatsPublisher,
 ) {
     let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
         p2p_event: Some(p2p_extractor::p2p::P2pEvent::InventoryAnnouncement(
@@ -375,10 +385,12 @@ async fn publish_inventory_announcement_event(
 
     match proto_result {
         Ok(proto) => {
-            if let Err(e) = nats_client
+            if let Err(e) = publisher
                 .publish(
-                    Subject::P2PExtractor.to_string(),
-                    proto.encode_to_vec().into(),
+                    &Subject::P2PExtractor.to_string(),
+                    &Headers::new(),
+                    proto.encode_to_vec(),
+                    &(),
                 )
                 .await
             {
@@ -393,7 +405,7 @@ async fn publish_inventory_announcement_event(
     }
 }
 
-async fn publish_feefilter_announcement_event(feefilter: i64, nats_client: &async_nats::Client) {
+async fn publish_feefilter_announcement_event(feefilter: i64, publisher: &NatsPublisher) {
     let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
         p2p_event: Some(p2p_extractor::p2p::P2pEvent::FeefilterAnnouncement(
             feefilter,
@@ -402,10 +414,12 @@ async fn publish_feefilter_announcement_event(feefilter: i64, nats_client: &asyn
 
     match proto_result {
         Ok(proto) => {
-            if let Err(e) = nats_client
+            if let Err(e) = publisher
                 .publish(
-                    Subject::P2PExtractor.to_string(),
-                    proto.encode_to_vec().into(),
+                    &Subject::P2PExtractor.to_string(),
+                    &Headers::new(),
+                    proto.encode_to_vec(),
+                    &(),
                 )
                 .await
             {
@@ -420,7 +434,7 @@ async fn publish_feefilter_announcement_event(feefilter: i64, nats_client: &asyn
     }
 }
 
-async fn publish_ping_measurement_event(duration: u64, nats_client: &async_nats::Client) {
+async fn publish_ping_measurement_event(duration: u64, publisher: &NatsPublisher) {
     let proto_result = Event::new(PeerObserverEvent::P2pExtractor(p2p_extractor::P2p {
         p2p_event: Some(p2p_extractor::p2p::P2pEvent::PingDuration(
             p2p_extractor::PingDuration { duration },
@@ -429,10 +443,12 @@ async fn publish_ping_measurement_event(duration: u64, nats_client: &async_nats:
 
     match proto_result {
         Ok(proto) => {
-            if let Err(e) = nats_client
+            if let Err(e) = publisher
                 .publish(
-                    Subject::P2PExtractor.to_string(),
-                    proto.encode_to_vec().into(),
+                    &Subject::P2PExtractor.to_string(),
+                    &Headers::new(),
+                    proto.encode_to_vec(),
+                    &(),
                 )
                 .await
             {
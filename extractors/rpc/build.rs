@@ -0,0 +1,42 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    // The git commit and the rustc version are set as labels of the
+    // build_info metric.
+    let git_sha = output("git", &["rev-parse", "--short=12", "HEAD"]);
+    println!(
+        "cargo:rustc-env=RPC_EXTRACTOR_GIT_SHA={}",
+        git_sha.as_deref().unwrap_or("unknown")
+    );
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    // e.g. "rustc 1.85.0 (4d91de4e4 2025-02-17)"
+    let rustc_version = output(&rustc, &["--version"])
+        .and_then(|version| version.split_whitespace().nth(1).map(str::to_string));
+    println!(
+        "cargo:rustc-env=RPC_EXTRACTOR_RUSTC_VERSION={}",
+        rustc_version.as_deref().unwrap_or("unknown")
+    );
+
+    // Cargo always reruns the build script if a path doesn't exist, e.g. when
+    // building from a source archive without the git repository.
+    for path in ["../../.git/HEAD", "../../.git/refs/heads"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+}
+
+/// Returns the trimmed stdout of the `program`, or `None` if it couldn't be
+/// run or failed.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    Some(stdout.trim().to_string()).filter(|s| !s.is_empty())
+}
@@ -0,0 +1,387 @@
+use crate::error::FetchOrPublishError;
+use crate::http;
+use crate::{CallOptions, RpcMethod};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use rustls::ClientConfig;
+use shared::corepc_client::client_sync::Auth;
+use shared::corepc_client::types::v17::{
+    EstimateSmartFee, GetChainTips, GetMemoryInfoStats, GetNetTotals, GetRawMempool, ListBanned,
+};
+use shared::corepc_client::types::v19::GetChainTxStats;
+use shared::corepc_client::types::v21::GetIndexInfo;
+use shared::corepc_client::types::v22::GetNodeAddresses;
+use shared::corepc_client::types::v23::GetDeploymentInfo;
+use shared::corepc_client::types::v24::GetRawMempoolVerbose;
+use shared::corepc_client::types::v26::{
+    GetAddrManInfo, GetMempoolInfo, GetPeerInfo, GetTxOutSetInfo,
+};
+use shared::corepc_client::types::v28::GetNetworkInfo;
+use shared::corepc_client::types::v29::{GetBlockchainInfo, GetMiningInfo};
+use shared::protobuf::rpc_extractor::rpc::RpcEvent;
+use shared::protobuf::rpc_extractor::{FeeEstimate, FeeEstimates};
+use shared::serde_json::{self, Value, json};
+use std::fs;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The credentials used to authenticate batched RPC requests. The cookie file
+/// is read on each request, so a restarted node's new cookie is picked up.
+#[derive(Debug, Clone)]
+pub(crate) enum Credentials {
+    None,
+    UserPass(String, String),
+    CookieFile(String),
+}
+
+impl From<&Auth> for Credentials {
+    fn from(auth: &Auth) -> Self {
+        match auth {
+            Auth::None => Credentials::None,
+            Auth::UserPass(user, password) => Credentials::UserPass(user.clone(), password.clone()),
+            Auth::CookieFile(path) => Credentials::CookieFile(path.to_string_lossy().to_string()),
+        }
+    }
+}
+
+impl Credentials {
+    /// Returns the value of the HTTP `Authorization` header, if any.
+    fn authorization(&self) -> io::Result<Option<String>> {
+        let user_pass = match self {
+            Credentials::None => return Ok(None),
+            Credentials::UserPass(user, password) => format!("{}:{}", user, password),
+            Credentials::CookieFile(path) => fs::read_to_string(path)?.trim().to_string(),
+        };
+        Ok(Some(format!("Basic {}", BASE64.encode(user_pass))))
+    }
+}
+
+/// Queries the `methods` of the node at `host` in a single JSON-RPC batch
+/// request and converts the results into RPC events. Errors for individual
+/// methods, for example a JSON-RPC error returned for one of the requests in
+/// the batch, are returned along with the method. If the whole batch request
+/// fails, an error is returned instead. With a `tls` configuration, the
+/// request is sent over HTTPS.
+pub(crate) fn call(
+    host: &str,
+    credentials: &Credentials,
+    methods: &[RpcMethod],
+    options: &CallOptions,
+    timeout: Duration,
+    tls: Option<&Arc<ClientConfig>>,
+) -> Result<Vec<(RpcMethod, Result<RpcEvent, FetchOrPublishError>)>, FetchOrPublishError> {
+    let mut batch = vec![];
+    let mut counts = vec![];
+    for method in methods {
+        let requests = requests(*method, options);
+        counts.push(requests.len());
+        for (name, params) in requests {
+            batch.push(json!({
+                "jsonrpc": "2.0",
+                "id": batch.len(),
+                "method": name,
+                "params": params,
+            }));
+        }
+    }
+    let count = batch.len();
+
+    let authorization = credentials
+        .authorization()
+        .map_err(FetchOrPublishError::CookieFile)?;
+    let body = http::post(
+        host,
+        "/",
+        authorization.as_deref(),
+        &Value::Array(batch).to_string(),
+        timeout,
+        tls,
+    )
+    .map_err(FetchOrPublishError::Http)?;
+    let mut responses = parse_responses(&body, count)?.into_iter();
+
+    Ok(methods
+        .iter()
+        .zip(counts)
+        .map(|(method, count)| {
+            let results: Result<Vec<Value>, FetchOrPublishError> =
+                responses.by_ref().take(count).collect();
+            let event = results.and_then(|results| rpc_event(*method, results, options));
+            (*method, event)
+        })
+        .collect())
+}
+
+/// Queries a single `method` like [call]. Used for nodes the corepc client
+/// can't query, for example behind an HTTPS proxy.
+pub(crate) fn call_single(
+    host: &str,
+    credentials: &Credentials,
+    method: RpcMethod,
+    options: &CallOptions,
+    timeout: Duration,
+    tls: Option<&Arc<ClientConfig>>,
+) -> Result<RpcEvent, FetchOrPublishError> {
+    match call(host, credentials, &[method], options, timeout, tls)?.pop() {
+        Some((_, result)) => result,
+        None => Err(FetchOrPublishError::Batch(format!(
+            "missing response for '{}'",
+            method
+        ))),
+    }
+}
+
+/// Calls the JSON-RPC method with the `name` and `params`, which isn't one
+/// of the queried [RpcMethod]s, and returns its result.
+pub(crate) fn call_method(
+    host: &str,
+    credentials: &Credentials,
+    name: &str,
+    params: Vec<Value>,
+    timeout: Duration,
+    tls: Option<&Arc<ClientConfig>>,
+) -> Result<Value, FetchOrPublishError> {
+    let request = json!([{
+        "jsonrpc": "2.0",
+        "id": 0,
+        "method": name,
+        "params": params,
+    }]);
+    let authorization = credentials
+        .authorization()
+        .map_err(FetchOrPublishError::CookieFile)?;
+    let body = http::post(
+        host,
+        "/",
+        authorization.as_deref(),
+        &request.to_string(),
+        timeout,
+        tls,
+    )
+    .map_err(FetchOrPublishError::Http)?;
+    parse_responses(&body, 1)?
+        .pop()
+        .expect("one result per request")
+}
+
+/// Returns the JSON-RPC method names and parameters of the requests needed to
+/// query the `method`. `estimatesmartfee` is requested once per target.
+fn requests(method: RpcMethod, options: &CallOptions) -> Vec<(&'static str, Vec<Value>)> {
+    match method {
+        RpcMethod::GetRawMempool => vec![(
+            method.as_str(),
+            vec![Value::from(!options.getrawmempool_txids_only)],
+        )],
+        RpcMethod::GetNodeAddresses => vec![(
+            method.as_str(),
+            vec![Value::from(options.getnodeaddresses_count)],
+        )],
+        RpcMethod::EstimateSmartFee => options
+            .fee_estimate_targets
+            .iter()
+            .map(|target| (method.as_str(), vec![Value::from(*target)]))
+            .collect(),
+        _ => vec![(method.as_str(), vec![])],
+    }
+}
+
+/// Parses the JSON-RPC batch response `body` with the `count` expected
+/// responses into the result or error of each request, ordered by id.
+fn parse_responses(
+    body: &str,
+    count: usize,
+) -> Result<Vec<Result<Value, FetchOrPublishError>>, FetchOrPublishError> {
+    let responses: Vec<Value> = serde_json::from_str(body)?;
+    let mut results: Vec<Result<Value, FetchOrPublishError>> = (0..count)
+        .map(|id| {
+            Err(FetchOrPublishError::Batch(format!(
+                "missing response for request {}",
+                id
+            )))
+        })
+        .collect();
+    for mut response in responses {
+        let Some(id) = response["id"].as_u64().map(|id| id as usize) else {
+            continue;
+        };
+        if id >= count {
+            continue;
+        }
+        results[id] = match response["error"].take() {
+            Value::Null => Ok(response["result"].take()),
+            error => Err(FetchOrPublishError::JsonRpc {
+                code: error["code"].as_i64().unwrap_or_default(),
+                message: error["message"]
+                    .as_str()
+                    .unwrap_or("unknown error")
+                    .to_string(),
+            }),
+        };
+    }
+    Ok(results)
+}
+
+/// Converts the `results` of the requests of the `method` into an RPC event.
+fn rpc_event(
+    method: RpcMethod,
+    results: Vec<Value>,
+    options: &CallOptions,
+) -> Result<RpcEvent, FetchOrPublishError> {
+    if method == RpcMethod::EstimateSmartFee {
+        let mut estimates = vec![];
+        for (target, result) in options.fee_estimate_targets.iter().zip(results) {
+            let estimate: EstimateSmartFee = serde_json::from_value(result)?;
+            estimates.push(FeeEstimate::new(*target as u32, estimate));
+        }
+        return Ok(RpcEvent::FeeEstimates(FeeEstimates { estimates }));
+    }
+
+    let result = results.into_iter().next().unwrap_or_default();
+    Ok(match method {
+        RpcMethod::GetPeerInfo => {
+            RpcEvent::PeerInfos(serde_json::from_value::<GetPeerInfo>(result)?.into())
+        }
+        RpcMethod::GetMempoolInfo => {
+            RpcEvent::MempoolInfo(serde_json::from_value::<GetMempoolInfo>(result)?.into())
+        }
+        RpcMethod::Uptime => RpcEvent::Uptime(serde_json::from_value(result)?),
+        RpcMethod::GetNetTotals => {
+            RpcEvent::NetTotals(serde_json::from_value::<GetNetTotals>(result)?.into())
+        }
+        RpcMethod::GetMemoryInfo => {
+            RpcEvent::MemoryInfo(serde_json::from_value::<GetMemoryInfoStats>(result)?.into())
+        }
+        RpcMethod::GetAddrmanInfo => {
+            RpcEvent::AddrmanInfo(serde_json::from_value::<GetAddrManInfo>(result)?.into())
+        }
+        RpcMethod::GetChainTxStats => {
+            RpcEvent::ChainTxStats(serde_json::from_value::<GetChainTxStats>(result)?.into())
+        }
+        RpcMethod::GetNetworkInfo => {
+            RpcEvent::NetworkInfo(serde_json::from_value::<GetNetworkInfo>(result)?.into())
+        }
+        RpcMethod::GetBlockchainInfo => {
+            RpcEvent::BlockchainInfo(serde_json::from_value::<GetBlockchainInfo>(result)?.into())
+        }
+        RpcMethod::GetBlockCount => RpcEvent::BlockCount(serde_json::from_value(result)?),
+        RpcMethod::GetMiningInfo => {
+            RpcEvent::MiningInfo(serde_json::from_value::<GetMiningInfo>(result)?.into())
+        }
+        RpcMethod::GetRawMempool => RpcEvent::RawMempool(if options.getrawmempool_txids_only {
+            serde_json::from_value::<GetRawMempool>(result)?.into()
+        } else {
+            serde_json::from_value::<GetRawMempoolVerbose>(result)?.into()
+        }),
+        RpcMethod::GetChainTips => {
+            RpcEvent::ChainTips(serde_json::from_value::<GetChainTips>(result)?.into())
+        }
+        RpcMethod::GetNodeAddresses => {
+            RpcEvent::NodeAddresses(serde_json::from_value::<GetNodeAddresses>(result)?.into())
+        }
+        RpcMethod::ListBanned => {
+            RpcEvent::Banned(serde_json::from_value::<ListBanned>(result)?.into())
+        }
+        RpcMethod::GetIndexInfo => {
+            RpcEvent::IndexInfo(serde_json::from_value::<GetIndexInfo>(result)?.into())
+        }
+        RpcMethod::GetDeploymentInfo => {
+            RpcEvent::DeploymentInfo(serde_json::from_value::<GetDeploymentInfo>(result)?.into())
+        }
+        RpcMethod::GetTxOutSetInfo => {
+            RpcEvent::TxOutSetInfo(serde_json::from_value::<GetTxOutSetInfo>(result)?.into())
+        }
+        RpcMethod::EstimateSmartFee => unreachable!("handled above"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> CallOptions {
+        CallOptions {
+            getrawmempool_txids_only: false,
+            getnodeaddresses_count: 10,
+            fee_estimate_targets: vec![1, 6],
+        }
+    }
+
+    #[test]
+    fn test_authorization() {
+        assert_eq!(Credentials::None.authorization().unwrap(), None);
+        let credentials = Credentials::UserPass("user".to_string(), "password".to_string());
+        assert_eq!(
+            credentials.authorization().unwrap(),
+            Some("Basic dXNlcjpwYXNzd29yZA==".to_string())
+        );
+        let missing = Credentials::CookieFile("/does/not/exist/.cookie".to_string());
+        assert!(missing.authorization().is_err());
+    }
+
+    #[test]
+    fn test_requests() {
+        let options = options();
+        assert_eq!(
+            requests(RpcMethod::Uptime, &options),
+            vec![("uptime", vec![])]
+        );
+        assert_eq!(
+            requests(RpcMethod::GetRawMempool, &options),
+            vec![("getrawmempool", vec![Value::from(true)])]
+        );
+        assert_eq!(
+            requests(RpcMethod::GetNodeAddresses, &options),
+            vec![("getnodeaddresses", vec![Value::from(10)])]
+        );
+        assert_eq!(
+            requests(RpcMethod::EstimateSmartFee, &options),
+            vec![
+                ("estimatesmartfee", vec![Value::from(1)]),
+                ("estimatesmartfee", vec![Value::from(6)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_responses() {
+        // responses can arrive in any order and can be errors
+        let body = r#"[
+            {"jsonrpc": "2.0", "id": 1, "result": 42},
+            {"jsonrpc": "2.0", "id": 0, "error": {"code": -32601, "message": "Method not found"}}
+        ]"#;
+        let responses = parse_responses(body, 3).unwrap();
+        match &responses[0] {
+            Err(FetchOrPublishError::JsonRpc { code, message }) => {
+                assert_eq!(*code, -32601);
+                assert_eq!(message, "Method not found");
+            }
+            result => panic!("expected a JSON-RPC error, got {:?}", result),
+        }
+        assert_eq!(responses[1].as_ref().unwrap(), &Value::from(42));
+        assert!(matches!(responses[2], Err(FetchOrPublishError::Batch(_))));
+
+        assert!(parse_responses("{}", 1).is_err());
+    }
+
+    #[test]
+    fn test_rpc_event() {
+        let options = options();
+        match rpc_event(RpcMethod::Uptime, vec![Value::from(120)], &options) {
+            Ok(RpcEvent::Uptime(uptime)) => assert_eq!(uptime, 120),
+            result => panic!("expected an uptime event, got {:?}", result),
+        }
+        match rpc_event(
+            RpcMethod::GetBlockCount,
+            vec![Value::from(800_000)],
+            &options,
+        ) {
+            Ok(RpcEvent::BlockCount(count)) => assert_eq!(count, 800_000),
+            result => panic!("expected a block count event, got {:?}", result),
+        }
+        assert!(matches!(
+            rpc_event(RpcMethod::GetBlockCount, vec![Value::from("abc")], &options),
+            Err(FetchOrPublishError::Serialization(_))
+        ));
+    }
+}
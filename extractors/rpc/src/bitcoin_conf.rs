@@ -0,0 +1,362 @@
+use crate::error::ConfigError;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const BITCOIN_CONF: &str = "bitcoin.conf";
+const COOKIE_FILE: &str = ".cookie";
+const DEFAULT_RPC_HOST: &str = "127.0.0.1";
+// Options that Bitcoin Core only applies to mainnet if they are set outside
+// of a network section.
+const NETWORK_ONLY_OPTIONS: [&str; 2] = ["rpcport", "rpcbind"];
+
+/// A chain Bitcoin Core can run on, with the section of its options in the
+/// bitcoin.conf, its data directory, and its default RPC port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Chain {
+    Main,
+    Testnet3,
+    Testnet4,
+    Signet,
+    Regtest,
+}
+
+impl Chain {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "main" => Some(Chain::Main),
+            "test" => Some(Chain::Testnet3),
+            "testnet4" => Some(Chain::Testnet4),
+            "signet" => Some(Chain::Signet),
+            "regtest" => Some(Chain::Regtest),
+            _ => None,
+        }
+    }
+
+    fn section(&self) -> &'static str {
+        match self {
+            Chain::Main => "main",
+            Chain::Testnet3 => "test",
+            Chain::Testnet4 => "testnet4",
+            Chain::Signet => "signet",
+            Chain::Regtest => "regtest",
+        }
+    }
+
+    /// The subdirectory of the data directory the chain's files, e.g. the
+    /// cookie file, are in.
+    fn data_subdir(&self) -> &'static str {
+        match self {
+            Chain::Main => "",
+            Chain::Testnet3 => "testnet3",
+            Chain::Testnet4 => "testnet4",
+            Chain::Signet => "signet",
+            Chain::Regtest => "regtest",
+        }
+    }
+
+    fn default_rpc_port(&self) -> u16 {
+        match self {
+            Chain::Main => 8332,
+            Chain::Testnet3 => 18332,
+            Chain::Testnet4 => 48332,
+            Chain::Signet => 38332,
+            Chain::Regtest => 18443,
+        }
+    }
+}
+
+/// The RPC endpoint and cookie file of a node as derived from its
+/// bitcoin.conf. See `--bitcoin-conf`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RpcSettings {
+    pub host: String,
+    pub cookie_file: String,
+}
+
+/// The options of a bitcoin.conf file. Each option is stored with the
+/// network section it was set in, if any.
+#[derive(Debug, Default)]
+struct BitcoinConf {
+    options: Vec<(Option<String>, String, String)>,
+}
+
+impl BitcoinConf {
+    /// Parses the `content` of a bitcoin.conf file. Options can be set in a
+    /// `[section]` or prefixed with the section, e.g. `regtest.rpcport=18443`.
+    fn parse(content: &str) -> Result<Self, ConfigError> {
+        let mut conf = BitcoinConf::default();
+        let mut section: Option<String> = None;
+        for (number, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = Some(name.trim().to_string());
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(ConfigError::BitcoinConf(format!(
+                    "expected <option>=<value> on line {}",
+                    number + 1
+                )));
+            };
+            let (option_section, key) = match key.trim().split_once('.') {
+                Some((prefix, key)) => (Some(prefix.to_string()), key),
+                None => (section.clone(), key.trim()),
+            };
+            conf.options
+                .push((option_section, key.to_string(), value.trim().to_string()));
+        }
+        Ok(conf)
+    }
+
+    /// Returns the first value of the option `key` set outside of a section.
+    fn top_level(&self, key: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(section, k, _)| section.is_none() && k == key)
+            .map(|(_, _, value)| value.as_str())
+    }
+
+    /// Returns the first value of the option `key` for the `chain`. Options
+    /// in the chain's section take precedence over options outside of a
+    /// section, which are ignored for the network-only options unless the
+    /// chain is mainnet.
+    fn get(&self, chain: Chain, key: &str) -> Option<&str> {
+        let in_section = self
+            .options
+            .iter()
+            .find(|(section, k, _)| section.as_deref() == Some(chain.section()) && k == key)
+            .map(|(_, _, value)| value.as_str());
+        if in_section.is_some() || (chain != Chain::Main && NETWORK_ONLY_OPTIONS.contains(&key)) {
+            return in_section;
+        }
+        self.top_level(key)
+    }
+
+    /// Returns the chain selected with `chain=<name>` or one of the
+    /// `regtest=1`, `testnet=1`, `testnet4=1`, and `signet=1` options.
+    fn chain(&self) -> Result<Chain, ConfigError> {
+        if let Some(name) = self.top_level("chain") {
+            return Chain::from_name(name)
+                .ok_or_else(|| ConfigError::BitcoinConf(format!("unknown chain '{}'", name)));
+        }
+        let enabled = |key: &str| self.top_level(key).is_some_and(|value| value != "0");
+        Ok(if enabled("regtest") {
+            Chain::Regtest
+        } else if enabled("testnet4") {
+            Chain::Testnet4
+        } else if enabled("testnet") {
+            Chain::Testnet3
+        } else if enabled("signet") {
+            Chain::Signet
+        } else {
+            Chain::Main
+        })
+    }
+}
+
+/// Splits an address like `127.0.0.1:8332`, `[::1]:8332`, or `::1` into the
+/// host, with IPv6 addresses enclosed in brackets, and the port, if any.
+fn split_host_port(address: &str) -> Result<(String, Option<u16>), ConfigError> {
+    let invalid = || ConfigError::BitcoinConf(format!("invalid address '{}'", address));
+    let (host, port) = if let Some(rest) = address.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']').ok_or_else(invalid)?;
+        (format!("[{}]", host), rest.strip_prefix(':'))
+    } else if address.matches(':').count() > 1 {
+        (format!("[{}]", address), None)
+    } else {
+        match address.split_once(':') {
+            Some((host, port)) => (host.to_string(), Some(port)),
+            None => (address.to_string(), None),
+        }
+    };
+    let port = port
+        .map(|port| port.parse::<u16>().map_err(|_| invalid()))
+        .transpose()?;
+    Ok((host, port))
+}
+
+/// Returns the host to connect to for an `rpcbind` address. A node bound to
+/// all interfaces is connected to on localhost.
+fn connect_host(bind_host: String) -> String {
+    match bind_host.as_str() {
+        "0.0.0.0" => DEFAULT_RPC_HOST.to_string(),
+        "[::]" => "[::1]".to_string(),
+        _ => bind_host,
+    }
+}
+
+/// Derives the RPC settings from the bitcoin.conf `content` of a node with
+/// the `datadir`. The host is taken from `rpcconnect` or the first `rpcbind`
+/// and the port from the address, `rpcport`, or the chain's default. The
+/// cookie file is `rpccookiefile` or `.cookie` in the chain's data
+/// directory.
+fn rpc_settings_from_conf(content: &str, datadir: &Path) -> Result<RpcSettings, ConfigError> {
+    let conf = BitcoinConf::parse(content)?;
+    let chain = conf.chain()?;
+
+    let (host, address_port) = match conf
+        .get(chain, "rpcconnect")
+        .or_else(|| conf.get(chain, "rpcbind"))
+    {
+        Some(address) => {
+            let (host, port) = split_host_port(address)?;
+            (connect_host(host), port)
+        }
+        None => (DEFAULT_RPC_HOST.to_string(), None),
+    };
+    let port = match (address_port, conf.get(chain, "rpcport")) {
+        (Some(port), _) => port,
+        (None, Some(port)) => port
+            .parse::<u16>()
+            .map_err(|_| ConfigError::BitcoinConf(format!("invalid rpcport '{}'", port)))?,
+        (None, None) => chain.default_rpc_port(),
+    };
+
+    let datadir = match conf.top_level("datadir") {
+        Some(datadir) => PathBuf::from(datadir),
+        None => datadir.to_path_buf(),
+    };
+    // a relative cookie file is in the chain's data directory
+    let cookie_file = datadir
+        .join(chain.data_subdir())
+        .join(conf.get(chain, "rpccookiefile").unwrap_or(COOKIE_FILE));
+
+    Ok(RpcSettings {
+        host: format!("{}:{}", host, port),
+        cookie_file: cookie_file.display().to_string(),
+    })
+}
+
+/// Derives the RPC settings from the bitcoin.conf at the `path`. If the
+/// `path` is a directory, it's the data directory of the node and its
+/// bitcoin.conf is used, if there is one. Otherwise, the data directory is
+/// the directory of the bitcoin.conf, unless it sets a `datadir`.
+pub(crate) fn rpc_settings(path: &Path) -> Result<RpcSettings, ConfigError> {
+    let io_error = |e: io::Error| ConfigError::Io(path.display().to_string(), e);
+    let (content, datadir) = if path.is_dir() {
+        let content = match fs::read_to_string(path.join(BITCOIN_CONF)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(io_error(e)),
+        };
+        (content, path.to_path_buf())
+    } else {
+        let content = fs::read_to_string(path).map_err(io_error)?;
+        let datadir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        (content, datadir)
+    };
+    rpc_settings_from_conf(&content, &datadir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(content: &str) -> RpcSettings {
+        rpc_settings_from_conf(content, Path::new("/data")).unwrap()
+    }
+
+    fn expected(host: &str, cookie_file: &str) -> RpcSettings {
+        RpcSettings {
+            host: host.to_string(),
+            cookie_file: cookie_file.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_default_chains() {
+        assert_eq!(settings(""), expected("127.0.0.1:8332", "/data/.cookie"));
+        assert_eq!(
+            settings("testnet=1\n"),
+            expected("127.0.0.1:18332", "/data/testnet3/.cookie")
+        );
+        assert_eq!(
+            settings("chain=testnet4\n"),
+            expected("127.0.0.1:48332", "/data/testnet4/.cookie")
+        );
+        assert_eq!(
+            settings("signet=1\n"),
+            expected("127.0.0.1:38332", "/data/signet/.cookie")
+        );
+        assert_eq!(
+            settings("regtest=1\n"),
+            expected("127.0.0.1:18443", "/data/regtest/.cookie")
+        );
+        assert_eq!(settings("regtest=0\n").host, "127.0.0.1:8332");
+        assert!(rpc_settings_from_conf("chain=unknown\n", Path::new("/data")).is_err());
+    }
+
+    #[test]
+    fn test_sections() {
+        let conf = "\
+# comment
+regtest=1
+rpcport=9000 # only for mainnet
+rpcbind=0.0.0.0
+
+[main]
+rpcport=9001
+
+[regtest]
+rpcport=9002
+";
+        assert_eq!(settings(conf).host, "127.0.0.1:9002");
+        assert_eq!(
+            settings(&conf.replace("regtest=1", "")).host,
+            "127.0.0.1:9001"
+        );
+        assert_eq!(
+            settings("regtest=1\nrpcport=9000\n").host,
+            "127.0.0.1:18443"
+        );
+        assert_eq!(
+            settings("chain=test\ntest.rpcport=9003\n").host,
+            "127.0.0.1:9003"
+        );
+    }
+
+    #[test]
+    fn test_rpcbind_and_rpcconnect() {
+        assert_eq!(settings("rpcbind=10.0.0.1\n").host, "10.0.0.1:8332");
+        assert_eq!(
+            settings("rpcbind=10.0.0.1:9000\nrpcport=9001\n").host,
+            "10.0.0.1:9000"
+        );
+        assert_eq!(settings("rpcbind=::1\n").host, "[::1]:8332");
+        assert_eq!(settings("rpcbind=[::]:9000\n").host, "[::1]:9000");
+        assert_eq!(
+            settings("rpcconnect=10.0.0.2\nrpcbind=10.0.0.1\n").host,
+            "10.0.0.2:8332"
+        );
+        assert!(rpc_settings_from_conf("rpcbind=10.0.0.1:port\n", Path::new("/data")).is_err());
+        assert!(rpc_settings_from_conf("rpcport=port\n", Path::new("/data")).is_err());
+    }
+
+    #[test]
+    fn test_cookie_file() {
+        assert_eq!(
+            settings("datadir=/bitcoin\nsignet=1\n").cookie_file,
+            "/bitcoin/signet/.cookie"
+        );
+        assert_eq!(
+            settings("rpccookiefile=rpc.cookie\n").cookie_file,
+            "/data/rpc.cookie"
+        );
+        assert_eq!(
+            settings("regtest=1\n[regtest]\nrpccookiefile=/run/bitcoin/.cookie\n").cookie_file,
+            "/run/bitcoin/.cookie"
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_line() {
+        assert!(matches!(
+            BitcoinConf::parse("regtest\n"),
+            Err(ConfigError::BitcoinConf(_))
+        ));
+    }
+}
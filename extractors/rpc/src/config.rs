@@ -0,0 +1,283 @@
+use crate::bitcoin_conf;
+use crate::error::ConfigError;
+use crate::{Args, RpcMethod};
+use shared::clap::{CommandFactory, Parser};
+use shared::toml;
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+
+/// Command line option to load a TOML config file.
+const CONFIG_OPTION: &str = "--config";
+/// Option to derive the RPC host and cookie file from a bitcoin.conf.
+const BITCOIN_CONF_OPTION: &str = "--bitcoin-conf";
+const RPC_USER_OPTION: &str = "--rpc-user";
+
+/// Parses the `Args` from the command line arguments `argv`. If a
+/// `--config <path>` is passed, the options in the TOML config file are
+/// loaded first, so that options passed on the command line override them.
+///
+/// The keys in the config file are the long option names without the leading
+/// `--`, for example `query-interval = 20` or `disable-getpeerinfo = true`.
+/// Options that can be passed multiple times take an array.
+///
+/// With `--bitcoin-conf <path>`, on the command line or in the config file,
+/// the `--rpc-host` and, unless an `--rpc-user` is passed, the
+/// `--rpc-cookie-file` are derived from the bitcoin.conf and passed before
+/// all other options.
+pub fn parse_args<I, T>(argv: I) -> Result<Args, ConfigError>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let argv: Vec<OsString> = argv.into_iter().map(Into::into).collect();
+    let mut merged: Vec<OsString> = argv.iter().take(1).cloned().collect();
+    if let Some(path) = option_value(&argv, CONFIG_OPTION) {
+        let content = fs::read_to_string(&path).map_err(|e| ConfigError::Io(path.clone(), e))?;
+        merged.extend(config_to_args(&content)?.into_iter().map(OsString::from));
+    }
+    merged.extend(argv.into_iter().skip(1));
+    if let Some(path) = option_value(&merged, BITCOIN_CONF_OPTION) {
+        let settings = bitcoin_conf::rpc_settings(Path::new(&path))?;
+        let mut derived = vec!["--rpc-host".to_string(), settings.host];
+        let user_auth = merged
+            .iter()
+            .skip(1)
+            .any(|arg| arg.to_string_lossy().split('=').next() == Some(RPC_USER_OPTION));
+        if !user_auth {
+            derived.extend(["--rpc-cookie-file".to_string(), settings.cookie_file]);
+        }
+        merged.splice(1..1, derived.into_iter().map(OsString::from));
+    }
+    Ok(Args::try_parse_from(merged)?)
+}
+
+/// Re-reads the config file at `path` and returns the `args` with the
+/// `disable-*` and `enable-gettxoutsetinfo` flags from the config file
+/// applied. Flags that aren't set in the config file are left unchanged.
+pub fn reload_disabled(args: &Args, path: &str) -> Result<Args, ConfigError> {
+    let content = fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_string(), e))?;
+    // reject unknown keys and invalid values like when loading the config
+    config_to_args(&content)?;
+    let table: toml::Table = toml::from_str(&content)?;
+
+    let mut reloaded = args.clone();
+    for method in RpcMethod::ALL {
+        let (key, inverted) = match method {
+            RpcMethod::GetTxOutSetInfo => (format!("enable-{}", method), true),
+            _ => (format!("disable-{}", method), false),
+        };
+        match table.get(&key) {
+            Some(toml::Value::Boolean(flag)) => reloaded.set_disabled(method, *flag != inverted),
+            Some(_) => return Err(ConfigError::InvalidValue(key)),
+            None => (),
+        }
+    }
+    Ok(reloaded)
+}
+
+/// Returns the value passed with `<option> <value>` or `<option>=<value>`,
+/// e.g. the path of `--config <path>`.
+fn option_value(argv: &[OsString], option: &str) -> Option<String> {
+    let mut args = argv.iter().skip(1).map(|arg| arg.to_string_lossy());
+    while let Some(arg) = args.next() {
+        if arg == option {
+            return args.next().map(|value| value.to_string());
+        }
+        if let Some(value) = arg
+            .strip_prefix(option)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Converts the TOML config file `content` into command line arguments.
+/// Unknown keys are rejected.
+fn config_to_args(content: &str) -> Result<Vec<String>, ConfigError> {
+    let table: toml::Table = toml::from_str(content)?;
+    let command = Args::command();
+    let mut args = vec![];
+    for (key, value) in table {
+        let known = command
+            .get_arguments()
+            .any(|arg| arg.get_long() == Some(key.as_str()));
+        if !known || key == "config" {
+            return Err(ConfigError::UnknownKey(key));
+        }
+        let option = format!("--{}", key);
+        match value {
+            toml::Value::Boolean(true) => args.push(option),
+            toml::Value::Boolean(false) => (),
+            toml::Value::Array(values) => {
+                for value in values {
+                    args.push(option.clone());
+                    args.push(config_value(&key, value)?);
+                }
+            }
+            value => {
+                args.push(option);
+                args.push(config_value(&key, value)?);
+            }
+        }
+    }
+    Ok(args)
+}
+
+/// Converts a single config value of the `key` into a command line value.
+fn config_value(key: &str, value: toml::Value) -> Result<String, ConfigError> {
+    match value {
+        toml::Value::String(s) => Ok(s),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        _ => Err(ConfigError::InvalidValue(key.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/fixtures/config.toml");
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/fixtures");
+    const BITCOIN_CONF_FIXTURE: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/src/fixtures/bitcoin.conf");
+
+    #[test]
+    fn test_parse_args_from_config() {
+        let args = parse_args(["rpc-extractor", "--config", FIXTURE]).unwrap();
+        assert_eq!(args.rpc_host, "127.0.0.1:18443");
+        assert_eq!(
+            args.rpc_cookie_file,
+            Some("/tmp/regtest/.cookie".to_string())
+        );
+        assert_eq!(args.query_interval, 20);
+        assert!(args.disable_getpeerinfo);
+        assert!(args.disable_getrawmempool);
+        assert!(!args.disable_uptime);
+        assert_eq!(args.fee_estimate_targets, vec![2, 144]);
+        assert_eq!(
+            args.rpc_intervals,
+            vec![(RpcMethod::GetBlockchainInfo, 30), (RpcMethod::Uptime, 60)]
+        );
+        assert_eq!(args.nats.address, "127.0.0.1:4333");
+    }
+
+    #[test]
+    fn test_parse_args_cli_overrides_config() {
+        let args = parse_args([
+            "rpc-extractor",
+            "--query-interval",
+            "5",
+            "--config",
+            FIXTURE,
+            "--rpc-host=127.0.0.1:8332",
+        ])
+        .unwrap();
+        assert_eq!(args.query_interval, 5);
+        assert_eq!(args.rpc_host, "127.0.0.1:8332");
+        assert!(args.disable_getpeerinfo);
+    }
+
+    #[test]
+    fn test_parse_args_from_bitcoin_conf() {
+        let args = parse_args(["rpc-extractor", "--bitcoin-conf", BITCOIN_CONF_FIXTURE]).unwrap();
+        assert_eq!(args.rpc_host, "127.0.0.1:18555");
+        assert_eq!(
+            args.rpc_cookie_file,
+            Some(format!("{}/regtest/.cookie", FIXTURES))
+        );
+        assert_eq!(args.bitcoin_conf, Some(BITCOIN_CONF_FIXTURE.to_string()));
+
+        // the data directory is read the same way
+        let from_datadir = parse_args(["rpc-extractor", "--bitcoin-conf", FIXTURES]).unwrap();
+        assert_eq!(from_datadir.rpc_host, args.rpc_host);
+        assert_eq!(from_datadir.rpc_cookie_file, args.rpc_cookie_file);
+    }
+
+    #[test]
+    fn test_parse_args_cli_overrides_bitcoin_conf() {
+        let args = parse_args([
+            "rpc-extractor",
+            &format!("--bitcoin-conf={}", BITCOIN_CONF_FIXTURE),
+            "--rpc-host",
+            "127.0.0.1:8332",
+        ])
+        .unwrap();
+        assert_eq!(args.rpc_host, "127.0.0.1:8332");
+        assert_eq!(
+            args.rpc_cookie_file,
+            Some(format!("{}/regtest/.cookie", FIXTURES))
+        );
+
+        let args = parse_args([
+            "rpc-extractor",
+            "--bitcoin-conf",
+            BITCOIN_CONF_FIXTURE,
+            "--rpc-user",
+            "user",
+            "--rpc-password",
+            "password",
+        ])
+        .unwrap();
+        assert_eq!(args.rpc_host, "127.0.0.1:18555");
+        assert_eq!(args.rpc_cookie_file, None);
+        assert_eq!(args.rpc_user, Some("user".to_string()));
+
+        assert!(matches!(
+            parse_args([
+                "rpc-extractor",
+                "--bitcoin-conf",
+                "/nonexistent/bitcoin.conf"
+            ]),
+            Err(ConfigError::Io(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_config_to_args_unknown_key() {
+        match config_to_args("query-interval = 20\ndisable-everything = true\n") {
+            Err(ConfigError::UnknownKey(key)) => assert_eq!(key, "disable-everything"),
+            result => panic!("expected an unknown key error, got {:?}", result),
+        }
+        assert!(matches!(
+            config_to_args("config = \"other.toml\"\n"),
+            Err(ConfigError::UnknownKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_config_to_args_invalid_value() {
+        assert!(matches!(
+            config_to_args("[query-interval]\nseconds = 20\n"),
+            Err(ConfigError::InvalidValue(_))
+        ));
+        assert!(matches!(
+            config_to_args("rpc-host = { host = \"127.0.0.1\" }\n"),
+            Err(ConfigError::InvalidValue(_))
+        ));
+    }
+
+    #[test]
+    fn test_reload_disabled() {
+        let mut args = parse_args(["rpc-extractor", "--config", FIXTURE]).unwrap();
+        args.disable_getblockcount = true;
+        args.enable_gettxoutsetinfo = true;
+
+        let reloaded = reload_disabled(&args, FIXTURE).unwrap();
+        // set in the config file
+        assert!(reloaded.disable_getpeerinfo);
+        assert!(!reloaded.disable_uptime);
+        // not set in the config file
+        assert!(reloaded.disable_getblockcount);
+        assert!(reloaded.enable_gettxoutsetinfo);
+
+        assert!(matches!(
+            reload_disabled(&args, "/nonexistent/config.toml"),
+            Err(ConfigError::Io(_, _))
+        ));
+    }
+}
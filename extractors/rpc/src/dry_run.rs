@@ -0,0 +1,37 @@
+use crate::error::PublishError;
+use crate::file_sink::json_line;
+use crate::publisher::{Headers, Publisher};
+use shared::futures::future::BoxFuture;
+use shared::log;
+use shared::nats_publisher::ErrorCounter;
+
+/// Logs the events at debug level instead of publishing them. Used with
+/// `--dry-run`, so that no NATS server is needed.
+#[derive(Debug, Default)]
+pub struct DryRunPublisher;
+
+impl Publisher for DryRunPublisher {
+    fn publish<'a>(
+        &'a self,
+        subject: &'a str,
+        headers: Headers,
+        payload: Vec<u8>,
+        _errors: &'a dyn ErrorCounter,
+    ) -> BoxFuture<'a, Result<(), PublishError>> {
+        Box::pin(async move {
+            if log::log_enabled!(log::Level::Debug) {
+                let line = json_line(subject, &headers, &payload)?;
+                log::debug!(
+                    "Dry run, not publishing on '{}': {}",
+                    subject,
+                    String::from_utf8_lossy(&line)
+                );
+            }
+            Ok(())
+        })
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), PublishError>> {
+        Box::pin(async { Ok(()) })
+    }
+}
@@ -1,17 +1,134 @@
+use crate::http::StatusError;
 use shared::async_nats;
 use shared::async_nats::ConnectErrorKind;
+use shared::clap;
 use shared::corepc_client::client_sync::Error as RPCError;
+use shared::jsonrpc;
+use shared::jsonrpc::http::bitreq_http::{self, HttpError};
 use shared::log::SetLoggerError;
+use shared::nats_publisher::NatsPublishError;
+use shared::serde_json;
+use shared::tokio::task::JoinError;
+use shared::toml;
 use std::error;
 use std::fmt;
 use std::io;
-use std::time::SystemTimeError;
+use std::sync::Arc;
+use std::time::{Duration, SystemTimeError};
 
 #[derive(Debug)]
 pub enum FetchOrPublishError {
     Rpc(RPCError),
     SystemTime(SystemTimeError),
-    NatsPublish(async_nats::error::Error<async_nats::client::PublishErrorKind>),
+    Publish(PublishError),
+    Timeout(Duration),
+    Join(JoinError),
+    Serialization(serde_json::Error),
+    /// The HTTP request of the extractor's own JSON-RPC client failed.
+    Http(io::Error),
+    /// The cookie file for the extractor's own JSON-RPC client couldn't be
+    /// read.
+    CookieFile(io::Error),
+    /// The node answered a request of the extractor's own JSON-RPC client
+    /// with a JSON-RPC error.
+    JsonRpc {
+        code: i64,
+        message: String,
+    },
+    /// The whole batch request failed with this error, which is shared by
+    /// all methods in the batch.
+    BatchFailed(Arc<FetchOrPublishError>),
+    Batch(String),
+}
+
+impl FetchOrPublishError {
+    /// Classifies the error of a failed RPC call for the `error_kind` label:
+    /// `auth`, `timeout`, `connection`, `decode`, `rpc_error`, or `other`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FetchOrPublishError::Timeout(_) => "timeout",
+            FetchOrPublishError::Serialization(_) => "decode",
+            FetchOrPublishError::Rpc(e) => rpc_error_kind(e),
+            FetchOrPublishError::Http(e) => io_error_kind(e),
+            FetchOrPublishError::CookieFile(_) => "auth",
+            FetchOrPublishError::JsonRpc { .. } => "rpc_error",
+            FetchOrPublishError::BatchFailed(e) => e.kind(),
+            FetchOrPublishError::SystemTime(_)
+            | FetchOrPublishError::Publish(_)
+            | FetchOrPublishError::Join(_)
+            | FetchOrPublishError::Batch(_) => "other",
+        }
+    }
+
+    /// Whether the node answered the RPC call with a JSON-RPC "method not
+    /// found" error, e.g. as its version doesn't have the RPC method yet.
+    pub fn is_method_not_found(&self) -> bool {
+        match self {
+            FetchOrPublishError::Rpc(RPCError::JsonRpc(jsonrpc::Error::Rpc(e))) => {
+                i64::from(e.code) == METHOD_NOT_FOUND_CODE
+            }
+            FetchOrPublishError::JsonRpc { code, .. } => *code == METHOD_NOT_FOUND_CODE,
+            _ => false,
+        }
+    }
+}
+
+/// The JSON-RPC error code of a call to a method the node doesn't know.
+const METHOD_NOT_FOUND_CODE: i64 = -32601;
+
+/// Classifies an error of the corepc RPC client.
+fn rpc_error_kind(e: &RPCError) -> &'static str {
+    match e {
+        RPCError::InvalidCookieFile => "auth",
+        RPCError::Io(e) => io_error_kind(e),
+        RPCError::Json(_) | RPCError::HexToArray(_) | RPCError::HexToBytes(_) => "decode",
+        RPCError::JsonRpc(jsonrpc::Error::Rpc(_)) => "rpc_error",
+        RPCError::JsonRpc(jsonrpc::Error::Json(_)) => "decode",
+        RPCError::JsonRpc(jsonrpc::Error::Transport(e)) => {
+            match e.downcast_ref::<bitreq_http::Error>() {
+                Some(bitreq_http::Error::Http(HttpError {
+                    status_code: 401 | 403,
+                    ..
+                })) => "auth",
+                Some(bitreq_http::Error::Http(_)) => "other",
+                Some(bitreq_http::Error::Json(_)) => "decode",
+                _ => cause_kind(&**e),
+            }
+        }
+        _ => "other",
+    }
+}
+
+/// Classifies a transport error by the I/O error that caused it. Transport
+/// errors without one, e.g. a failed name resolution, are connection errors.
+fn cause_kind(e: &(dyn error::Error + 'static)) -> &'static str {
+    let mut cause = Some(e);
+    while let Some(e) = cause {
+        if let Some(e) = e.downcast_ref::<io::Error>() {
+            return io_error_kind(e);
+        }
+        cause = e.source();
+    }
+    "connection"
+}
+
+/// Classifies an I/O error of an RPC request, for example a non-200 HTTP
+/// response or a failed TLS handshake of the extractor's own HTTP client.
+fn io_error_kind(e: &io::Error) -> &'static str {
+    if let Some(status) = e.get_ref().and_then(|e| e.downcast_ref::<StatusError>()) {
+        return match status.code {
+            401 | 403 => "auth",
+            _ => "other",
+        };
+    }
+    if e.get_ref().is_some_and(|e| e.is::<rustls::Error>()) {
+        return "connection";
+    }
+    match e.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => "timeout",
+        io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => "decode",
+        _ => "connection",
+    }
 }
 
 impl fmt::Display for FetchOrPublishError {
@@ -19,7 +136,19 @@ impl fmt::Display for FetchOrPublishError {
         match self {
             FetchOrPublishError::Rpc(e) => write!(f, "RPC error: {}", e),
             FetchOrPublishError::SystemTime(e) => write!(f, "system time error {}", e),
-            FetchOrPublishError::NatsPublish(e) => write!(f, "NATS publish error {}", e),
+            FetchOrPublishError::Publish(e) => write!(f, "{}", e),
+            FetchOrPublishError::Timeout(d) => write!(f, "RPC call timed out after {:?}", d),
+            FetchOrPublishError::Join(e) => write!(f, "RPC call task error {}", e),
+            FetchOrPublishError::Serialization(e) => write!(f, "serialization error {}", e),
+            FetchOrPublishError::Http(e) => write!(f, "RPC request error: {}", e),
+            FetchOrPublishError::CookieFile(e) => {
+                write!(f, "could not read the cookie file: {}", e)
+            }
+            FetchOrPublishError::JsonRpc { code, message } => {
+                write!(f, "JSON-RPC error: {} (code {})", message, code)
+            }
+            FetchOrPublishError::BatchFailed(e) => write!(f, "batch request failed: {}", e),
+            FetchOrPublishError::Batch(e) => write!(f, "JSON-RPC error: {}", e),
         }
     }
 }
@@ -29,7 +158,15 @@ impl error::Error for FetchOrPublishError {
         match *self {
             FetchOrPublishError::Rpc(ref e) => Some(e),
             FetchOrPublishError::SystemTime(ref e) => Some(e),
-            FetchOrPublishError::NatsPublish(ref e) => Some(e),
+            FetchOrPublishError::Publish(ref e) => Some(e),
+            FetchOrPublishError::Timeout(_) => None,
+            FetchOrPublishError::Join(ref e) => Some(e),
+            FetchOrPublishError::Serialization(ref e) => Some(e),
+            FetchOrPublishError::Http(ref e) => Some(e),
+            FetchOrPublishError::CookieFile(ref e) => Some(e),
+            FetchOrPublishError::JsonRpc { .. } => None,
+            FetchOrPublishError::BatchFailed(ref e) => Some(e.as_ref()),
+            FetchOrPublishError::Batch(_) => None,
         }
     }
 }
@@ -46,9 +183,92 @@ impl From<SystemTimeError> for FetchOrPublishError {
     }
 }
 
-impl From<async_nats::error::Error<async_nats::client::PublishErrorKind>> for FetchOrPublishError {
+impl From<PublishError> for FetchOrPublishError {
+    fn from(e: PublishError) -> Self {
+        FetchOrPublishError::Publish(e)
+    }
+}
+
+impl From<JoinError> for FetchOrPublishError {
+    fn from(e: JoinError) -> Self {
+        FetchOrPublishError::Join(e)
+    }
+}
+
+impl From<serde_json::Error> for FetchOrPublishError {
+    fn from(e: serde_json::Error) -> Self {
+        FetchOrPublishError::Serialization(e)
+    }
+}
+
+#[derive(Debug)]
+pub enum PublishError {
+    Nats(async_nats::error::Error<async_nats::client::PublishErrorKind>),
+    NatsFlush(async_nats::client::FlushError),
+    /// The payload of this many bytes was dropped as the buffer for messages
+    /// published while disconnected from the NATS server is full.
+    NatsPendingBufferFull(usize),
+    Kafka(String),
+    File(String),
+}
+
+impl PublishError {
+    /// Whether NATS rejected the payload as it exceeds the server's maximum
+    /// payload size.
+    pub fn is_payload_too_large(&self) -> bool {
+        matches!(
+            self,
+            PublishError::Nats(e) if e.kind() == async_nats::client::PublishErrorKind::MaxPayloadExceeded
+        )
+    }
+}
+
+impl fmt::Display for PublishError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PublishError::Nats(e) => write!(f, "NATS publish error {}", e),
+            PublishError::NatsFlush(e) => write!(f, "NATS flush error {}", e),
+            PublishError::NatsPendingBufferFull(size) => write!(
+                f,
+                "dropped {} bytes as the NATS pending buffer is full",
+                size
+            ),
+            PublishError::Kafka(e) => write!(f, "Kafka publish error {}", e),
+            PublishError::File(e) => write!(f, "file sink error {}", e),
+        }
+    }
+}
+
+impl error::Error for PublishError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            PublishError::Nats(ref e) => Some(e),
+            PublishError::NatsFlush(ref e) => Some(e),
+            PublishError::NatsPendingBufferFull(_) => None,
+            PublishError::Kafka(_) => None,
+            PublishError::File(_) => None,
+        }
+    }
+}
+
+impl From<async_nats::error::Error<async_nats::client::PublishErrorKind>> for PublishError {
     fn from(e: async_nats::error::Error<async_nats::client::PublishErrorKind>) -> Self {
-        FetchOrPublishError::NatsPublish(e)
+        PublishError::Nats(e)
+    }
+}
+
+impl From<NatsPublishError> for PublishError {
+    fn from(e: NatsPublishError) -> Self {
+        match e {
+            NatsPublishError::Publish(e) => PublishError::Nats(e),
+            NatsPublishError::PendingBufferFull(size) => PublishError::NatsPendingBufferFull(size),
+        }
+    }
+}
+
+impl From<async_nats::client::FlushError> for PublishError {
+    fn from(e: async_nats::client::FlushError) -> Self {
+        PublishError::NatsFlush(e)
     }
 }
 
@@ -58,6 +278,11 @@ pub enum RuntimeError {
     Io(io::Error),
     Corepc(shared::corepc_client::client_sync::Error),
     NatsConnect(shared::async_nats::error::Error<ConnectErrorKind>),
+    KafkaConnect(PublishError),
+    InvalidArgs(String),
+    Chain(FetchOrPublishError),
+    UnexpectedRpcResult(String),
+    StartupCheck(Vec<String>),
 }
 
 impl fmt::Display for RuntimeError {
@@ -67,6 +292,13 @@ impl fmt::Display for RuntimeError {
             RuntimeError::Io(e) => write!(f, "IO error {}", e),
             RuntimeError::Corepc(e) => write!(f, "RPC client error {}", e),
             RuntimeError::NatsConnect(e) => write!(f, "NATS connection error {}", e),
+            RuntimeError::KafkaConnect(e) => write!(f, "Kafka connection error {}", e),
+            RuntimeError::InvalidArgs(e) => write!(f, "invalid arguments: {}", e),
+            RuntimeError::Chain(e) => write!(f, "could not learn the chain of the node: {}", e),
+            RuntimeError::UnexpectedRpcResult(e) => write!(f, "unexpected RPC result: {}", e),
+            RuntimeError::StartupCheck(failures) => {
+                write!(f, "startup check failed: {}", failures.join("; "))
+            }
         }
     }
 }
@@ -78,6 +310,11 @@ impl error::Error for RuntimeError {
             RuntimeError::Io(ref e) => Some(e),
             RuntimeError::Corepc(ref e) => Some(e),
             RuntimeError::NatsConnect(ref e) => Some(e),
+            RuntimeError::KafkaConnect(ref e) => Some(e),
+            RuntimeError::InvalidArgs(_) => None,
+            RuntimeError::Chain(ref e) => Some(e),
+            RuntimeError::UnexpectedRpcResult(_) => None,
+            RuntimeError::StartupCheck(_) => None,
         }
     }
 }
@@ -105,3 +342,221 @@ impl From<shared::async_nats::error::Error<ConnectErrorKind>> for RuntimeError {
         RuntimeError::NatsConnect(e)
     }
 }
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(String, io::Error),
+    Toml(toml::de::Error),
+    UnknownKey(String),
+    InvalidValue(String),
+    BitcoinConf(String),
+    Clap(clap::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => {
+                write!(f, "could not read the config file '{}': {}", path, e)
+            }
+            ConfigError::Toml(e) => write!(f, "invalid config file: {}", e),
+            ConfigError::UnknownKey(key) => write!(f, "unknown key '{}' in the config file", key),
+            ConfigError::InvalidValue(key) => {
+                write!(f, "invalid value for the key '{}' in the config file", key)
+            }
+            ConfigError::BitcoinConf(e) => write!(f, "invalid bitcoin.conf: {}", e),
+            ConfigError::Clap(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ConfigError::Io(_, ref e) => Some(e),
+            ConfigError::Toml(ref e) => Some(e),
+            ConfigError::UnknownKey(_) => None,
+            ConfigError::InvalidValue(_) => None,
+            ConfigError::BitcoinConf(_) => None,
+            ConfigError::Clap(ref e) => Some(e),
+        }
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(e: toml::de::Error) -> Self {
+        ConfigError::Toml(e)
+    }
+}
+
+impl From<clap::Error> for ConfigError {
+    fn from(e: clap::Error) -> Self {
+        ConfigError::Clap(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_kind() {
+        assert_eq!(
+            FetchOrPublishError::Timeout(Duration::from_secs(1)).kind(),
+            "timeout"
+        );
+        assert_eq!(
+            FetchOrPublishError::Serialization(serde_json::from_str::<u64>("x").unwrap_err())
+                .kind(),
+            "decode"
+        );
+
+        let http = |e: io::Error| FetchOrPublishError::Http(e).kind();
+        let status = |code: u16| {
+            http(io::Error::other(StatusError {
+                code,
+                status_line: format!("HTTP/1.1 {}", code),
+            }))
+        };
+        assert_eq!(status(401), "auth");
+        assert_eq!(status(403), "auth");
+        assert_eq!(status(500), "other");
+        assert_eq!(http(io::ErrorKind::TimedOut.into()), "timeout");
+        assert_eq!(http(io::ErrorKind::WouldBlock.into()), "timeout");
+        assert_eq!(http(io::ErrorKind::ConnectionRefused.into()), "connection");
+        assert_eq!(
+            http(io::Error::new(
+                io::ErrorKind::InvalidData,
+                rustls::Error::InvalidCertificate(rustls::CertificateError::UnknownIssuer),
+            )),
+            "connection"
+        );
+        // only the error variant matters, not the message
+        assert_eq!(
+            http(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                "could not parse the cookie"
+            )),
+            "connection"
+        );
+        assert_eq!(
+            http(io::Error::new(io::ErrorKind::InvalidData, "incomplete")),
+            "decode"
+        );
+        assert_eq!(
+            FetchOrPublishError::CookieFile(io::ErrorKind::NotFound.into()).kind(),
+            "auth"
+        );
+        assert_eq!(
+            FetchOrPublishError::JsonRpc {
+                code: -8,
+                message: "invalid cookie parameter".to_string()
+            }
+            .kind(),
+            "rpc_error"
+        );
+        assert_eq!(
+            FetchOrPublishError::BatchFailed(Arc::new(FetchOrPublishError::Timeout(
+                Duration::from_secs(1)
+            )))
+            .kind(),
+            "timeout"
+        );
+        assert_eq!(
+            FetchOrPublishError::Batch("missing response for request 0".to_string()).kind(),
+            "other"
+        );
+
+        let rpc = |e: RPCError| FetchOrPublishError::Rpc(e).kind();
+        assert_eq!(rpc(RPCError::InvalidCookieFile), "auth");
+        assert_eq!(
+            rpc(RPCError::Io(io::ErrorKind::ConnectionRefused.into())),
+            "connection"
+        );
+        assert_eq!(
+            rpc(RPCError::JsonRpc(jsonrpc::Error::Rpc(rpc_error(
+                -1,
+                "the cookie is invalid"
+            )))),
+            "rpc_error"
+        );
+        let transport =
+            |e: bitreq_http::Error| rpc(RPCError::JsonRpc(jsonrpc::Error::Transport(Box::new(e))));
+        assert_eq!(
+            transport(bitreq_http::Error::Http(HttpError {
+                status_code: 401,
+                body: String::new(),
+            })),
+            "auth"
+        );
+        assert_eq!(
+            transport(bitreq_http::Error::Http(HttpError {
+                status_code: 500,
+                body: "parse error".to_string(),
+            })),
+            "other"
+        );
+        assert_eq!(
+            transport(bitreq_http::Error::Json(
+                serde_json::from_str::<u64>("x").unwrap_err()
+            )),
+            "decode"
+        );
+    }
+
+    fn rpc_error(code: i32, message: &str) -> jsonrpc::error::RpcError {
+        jsonrpc::error::RpcError {
+            code,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
+    #[test]
+    fn test_is_method_not_found() {
+        let json_rpc = |code: i64| FetchOrPublishError::JsonRpc {
+            code,
+            message: "Method not found".to_string(),
+        };
+        assert!(json_rpc(-32601).is_method_not_found());
+        assert!(!json_rpc(-8).is_method_not_found());
+        let corepc = |code: i32, message: &str| {
+            FetchOrPublishError::Rpc(RPCError::JsonRpc(jsonrpc::Error::Rpc(rpc_error(
+                code, message,
+            ))))
+        };
+        assert!(corepc(-32601, "Method not found").is_method_not_found());
+        assert!(!corepc(-8, "code: -32601").is_method_not_found());
+        assert!(
+            !FetchOrPublishError::Http(io::ErrorKind::ConnectionRefused.into())
+                .is_method_not_found()
+        );
+        assert!(!FetchOrPublishError::Timeout(Duration::from_secs(1)).is_method_not_found());
+    }
+
+    #[test]
+    fn test_is_payload_too_large() {
+        use async_nats::client::PublishErrorKind;
+
+        let too_large = PublishError::Nats(PublishErrorKind::MaxPayloadExceeded.into());
+        assert!(too_large.is_payload_too_large());
+        let bad_subject = PublishError::Nats(PublishErrorKind::BadSubject.into());
+        assert!(!bad_subject.is_payload_too_large());
+        assert!(!PublishError::Kafka("message too large".to_string()).is_payload_too_large());
+    }
+
+    #[test]
+    fn test_pending_buffer_full() {
+        let full = PublishError::from(NatsPublishError::PendingBufferFull(1024));
+        assert!(matches!(full, PublishError::NatsPendingBufferFull(1024)));
+        assert!(!full.is_payload_too_large());
+        assert_eq!(
+            full.to_string(),
+            "dropped 1024 bytes as the NATS pending buffer is full"
+        );
+        let too_large = PublishError::from(NatsPublishError::Publish(
+            async_nats::client::PublishErrorKind::MaxPayloadExceeded.into(),
+        ));
+        assert!(too_large.is_payload_too_large());
+    }
+}
@@ -0,0 +1,263 @@
+use crate::Format;
+use crate::error::PublishError;
+use crate::publisher::{Headers, Publisher};
+use shared::futures::future::BoxFuture;
+use shared::log;
+use shared::nats_publisher::{self, ErrorCounter};
+use shared::prost::Message;
+use shared::protobuf::event::Event;
+use shared::serde_json::{self, Value, json};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Writes the events as JSON lines to files in a directory. A new file is
+/// started once the current one reached `max_bytes` or is older than
+/// `max_age`.
+pub struct FilePublisher {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_age: Duration,
+    file: Mutex<Option<SinkFile>>,
+}
+
+struct SinkFile {
+    writer: BufWriter<File>,
+    bytes: u64,
+    opened: Instant,
+}
+
+impl FilePublisher {
+    /// Creates the `dir` if it doesn't exist yet. The first file is only
+    /// created once an event is published.
+    pub fn new(dir: PathBuf, max_bytes: u64, max_age: Duration) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            max_bytes,
+            max_age,
+            file: Mutex::new(None),
+        })
+    }
+
+    fn write_line(&self, line: &[u8]) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+        if let Some(current) = file.as_mut()
+            && (current.bytes >= self.max_bytes || current.opened.elapsed() >= self.max_age)
+        {
+            current.writer.flush()?;
+            *file = None;
+        }
+        let current = match file.as_mut() {
+            Some(current) => current,
+            None => file.insert(SinkFile::create(&self.dir)?),
+        };
+        current.writer.write_all(line)?;
+        current.writer.write_all(b"\n")?;
+        current.bytes += line.len() as u64 + 1;
+        Ok(())
+    }
+}
+
+impl SinkFile {
+    fn create(dir: &Path) -> io::Result<Self> {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let mut path = dir.join(format!("events-{}.jsonl", millis));
+        // Don't overwrite a file created within the same millisecond.
+        let mut suffix = 1;
+        while path.exists() {
+            path = dir.join(format!("events-{}-{}.jsonl", millis, suffix));
+            suffix += 1;
+        }
+        log::info!("Writing events to {}", path.display());
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            bytes: 0,
+            opened: Instant::now(),
+        })
+    }
+}
+
+/// Converts a published `payload` into a JSON line containing the `subject`
+/// and the event. Protobuf payloads are decoded, so that the line always
+/// contains JSON regardless of `--serialization`.
+pub(crate) fn json_line(
+    subject: &str,
+    headers: &Headers,
+    payload: &[u8],
+) -> Result<Vec<u8>, PublishError> {
+    let json_content_type = Format::Json.content_type();
+    let event: Value = if headers.get("Content-Type").map(String::as_str) == Some(json_content_type)
+    {
+        serde_json::from_slice(payload).map_err(|e| PublishError::File(e.to_string()))?
+    } else {
+        let event = Event::decode(payload).map_err(|e| PublishError::File(e.to_string()))?;
+        serde_json::to_value(event).map_err(|e| PublishError::File(e.to_string()))?
+    };
+    Ok(json!({ "subject": subject, "event": event })
+        .to_string()
+        .into_bytes())
+}
+
+impl Publisher for FilePublisher {
+    fn publish<'a>(
+        &'a self,
+        subject: &'a str,
+        headers: Headers,
+        payload: Vec<u8>,
+        errors: &'a dyn ErrorCounter,
+    ) -> BoxFuture<'a, Result<(), PublishError>> {
+        Box::pin(nats_publisher::counted(errors, async move {
+            let line = json_line(subject, &headers, &payload)?;
+            self.write_line(&line)
+                .map_err(|e| PublishError::File(e.to_string()))
+        }))
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), PublishError>> {
+        Box::pin(async move {
+            if let Some(current) = self.file.lock().unwrap().as_mut() {
+                current
+                    .writer
+                    .flush()
+                    .map_err(|e| PublishError::File(e.to_string()))?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::prometheus::IntCounter;
+    use shared::protobuf::event::event::PeerObserverEvent;
+    use shared::protobuf::rpc_extractor::{self, rpc::RpcEvent};
+    use shared::tokio;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "rpc-extractor-file-sink-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn uptime_event(uptime: u32) -> Event {
+        Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+            rpc_event: Some(RpcEvent::Uptime(uptime)),
+        }))
+        .unwrap()
+    }
+
+    fn read_lines(dir: &Path) -> Vec<Vec<Value>> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        paths.sort();
+        paths
+            .iter()
+            .map(|path| {
+                fs::read_to_string(path)
+                    .unwrap()
+                    .lines()
+                    .map(|line| serde_json::from_str(line).unwrap())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_json_line() {
+        let event = uptime_event(42);
+        let mut headers = Headers::new();
+        headers.insert("Content-Type", Format::Protobuf.content_type());
+        let from_protobuf = json_line("rpc", &headers, &event.encode_to_vec()).unwrap();
+
+        headers.insert("Content-Type", Format::Json.content_type());
+        let from_json = json_line("rpc", &headers, &serde_json::to_vec(&event).unwrap()).unwrap();
+
+        assert_eq!(from_protobuf, from_json);
+        let line: Value = serde_json::from_slice(&from_json).unwrap();
+        assert_eq!(line["subject"], "rpc");
+        let decoded: Event = serde_json::from_value(line["event"].clone()).unwrap();
+        assert_eq!(decoded, event);
+
+        assert!(json_line("rpc", &headers, b"not json").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rotate_by_size() {
+        let errors = IntCounter::new("errors", "errors").unwrap();
+        let dir = test_dir("size");
+        // every line exceeds the limit, so each event starts a new file
+        let publisher = FilePublisher::new(dir.clone(), 1, Duration::from_secs(3600)).unwrap();
+        for uptime in 0..3 {
+            let mut headers = Headers::new();
+            headers.insert("Content-Type", Format::Protobuf.content_type());
+            publisher
+                .publish(
+                    "rpc",
+                    headers,
+                    uptime_event(uptime).encode_to_vec(),
+                    &errors,
+                )
+                .await
+                .unwrap();
+        }
+        publisher.flush().await.unwrap();
+
+        let files = read_lines(&dir);
+        assert_eq!(files.len(), 3);
+        assert!(files.iter().all(|lines| lines.len() == 1));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rotate_by_age() {
+        let errors = IntCounter::new("errors", "errors").unwrap();
+        let dir = test_dir("age");
+        // every file is already too old when the next event is written
+        let publisher = FilePublisher::new(dir.clone(), u64::MAX, Duration::ZERO).unwrap();
+        for uptime in 0..2 {
+            publisher
+                .publish(
+                    "rpc",
+                    Headers::new(),
+                    uptime_event(uptime).encode_to_vec(),
+                    &errors,
+                )
+                .await
+                .unwrap();
+        }
+        publisher.flush().await.unwrap();
+        assert_eq!(read_lines(&dir).len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let publisher = FilePublisher::new(dir.clone(), u64::MAX, Duration::MAX).unwrap();
+        for uptime in 0..2 {
+            publisher
+                .publish(
+                    "rpc",
+                    Headers::new(),
+                    uptime_event(uptime).encode_to_vec(),
+                    &errors,
+                )
+                .await
+                .unwrap();
+        }
+        publisher.flush().await.unwrap();
+        let files = read_lines(&dir);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].len(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,90 @@
+use crate::metrics::Metrics;
+use shared::util;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// The health of the extractor, as reported by the health endpoints of the
+/// metrics server.
+#[derive(Debug, Clone)]
+pub struct Health {
+    fetched: Arc<AtomicBool>,
+    // UNIX epoch timestamp of the last iteration of the main loop.
+    heartbeat: Arc<AtomicU64>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            fetched: Arc::new(AtomicBool::new(false)),
+            heartbeat: Arc::new(AtomicU64::new(util::current_timestamp())),
+        }
+    }
+}
+
+impl Health {
+    /// Records that RPC data was successfully fetched and published.
+    pub fn record_fetch(&self) {
+        self.fetched.store(true, Ordering::SeqCst);
+    }
+
+    /// The extractor is ready once it fetched RPC data at least once and
+    /// while it's connected to the NATS server.
+    pub fn ready(&self, metrics: &Metrics) -> bool {
+        self.fetched.load(Ordering::SeqCst) && metrics.nats_connected.get() == 1
+    }
+
+    /// Like `ready`, but for publishing to a backend other than NATS, where
+    /// the NATS connection doesn't matter.
+    pub fn fetched(&self) -> bool {
+        self.fetched.load(Ordering::SeqCst)
+    }
+
+    /// Records that the main loop is still running.
+    pub fn beat(&self) {
+        self.heartbeat
+            .store(util::current_timestamp(), Ordering::SeqCst);
+    }
+
+    /// The extractor is alive as long as the main loop keeps running, i.e.
+    /// the last heartbeat isn't older than `threshold_secs`. This is
+    /// independent of whether RPC calls currently succeed.
+    pub fn alive(&self, threshold_secs: u64) -> bool {
+        let age = util::current_timestamp().saturating_sub(self.heartbeat.load(Ordering::SeqCst));
+        age <= threshold_secs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ready() {
+        let health = Health::default();
+        let metrics = Metrics::default();
+        assert!(!health.ready(&metrics));
+
+        metrics.nats_connected.set(1);
+        assert!(!health.ready(&metrics));
+
+        health.record_fetch();
+        assert!(health.ready(&metrics));
+
+        metrics.nats_connected.set(0);
+        assert!(!health.ready(&metrics));
+    }
+
+    #[test]
+    fn test_alive() {
+        let health = Health::default();
+        assert!(health.alive(60));
+
+        health
+            .heartbeat
+            .store(util::current_timestamp() - 120, Ordering::SeqCst);
+        assert!(!health.alive(60));
+
+        health.beat();
+        assert!(health.alive(60));
+    }
+}
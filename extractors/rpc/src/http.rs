@@ -0,0 +1,297 @@
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, StreamOwned};
+use std::error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A response with a status other than `200 OK`, returned as the inner
+/// error of an [io::Error].
+#[derive(Debug)]
+pub(crate) struct StatusError {
+    pub(crate) code: u16,
+    status_line: String,
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unexpected HTTP response status '{}'", self.status_line)
+    }
+}
+
+impl error::Error for StatusError {}
+
+/// Sends the JSON `body` as an HTTP POST request to the `path` on the `host`
+/// and returns the response body. The connection is closed after the response.
+/// With a `tls` configuration, the request is sent over HTTPS.
+pub(crate) fn post(
+    host: &str,
+    path: &str,
+    authorization: Option<&str>,
+    body: &str,
+    timeout: Duration,
+    tls: Option<&Arc<ClientConfig>>,
+) -> io::Result<String> {
+    let address = host.to_socket_addrs()?.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("could not resolve '{}'", host),
+        )
+    })?;
+    let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        path,
+        host,
+        body.len()
+    );
+    if let Some(authorization) = authorization {
+        request.push_str(&format!("Authorization: {}\r\n", authorization));
+    }
+    request.push_str("\r\n");
+    request.push_str(body);
+
+    let response = match tls {
+        Some(config) => {
+            let connection = ClientConnection::new(config.clone(), server_name(host)?)
+                .map_err(io::Error::other)?;
+            exchange(&mut StreamOwned::new(connection, stream), &request)?
+        }
+        None => exchange(&mut stream, &request)?,
+    };
+    parse_http_response(&response)
+}
+
+/// Writes the `request` to the `stream` and reads the response until the
+/// connection is closed.
+fn exchange(stream: &mut (impl Read + Write), request: &str) -> io::Result<Vec<u8>> {
+    stream.write_all(request.as_bytes())?;
+    stream.flush()?;
+    let mut response = vec![];
+    match stream.read_to_end(&mut response) {
+        // Some servers close the TLS connection without a close_notify after
+        // the response.
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof && !response.is_empty() => {}
+        result => {
+            result?;
+        }
+    }
+    Ok(response)
+}
+
+/// The name the TLS certificate of the `host` (as `<name>:<port>`) is
+/// verified against.
+fn server_name(host: &str) -> io::Result<ServerName<'static>> {
+    let name = host.rsplit_once(':').map_or(host, |(name, _)| name);
+    let name = name.trim_start_matches('[').trim_end_matches(']');
+    ServerName::try_from(name.to_string()).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid TLS server name '{}': {}", name, e),
+        )
+    })
+}
+
+/// Parses a raw HTTP response and returns its body. Responses with a status
+/// other than `200 OK` are an error.
+fn parse_http_response(response: &[u8]) -> io::Result<String> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let head_end =
+        find(response, b"\r\n\r\n").ok_or_else(|| invalid("incomplete HTTP response"))?;
+    let head = String::from_utf8_lossy(&response[..head_end]);
+    let body = &response[head_end + 4..];
+    let mut lines = head.lines();
+    let status = lines.next().unwrap_or_default();
+    let code = status
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| invalid("invalid HTTP response status line"))?;
+    if code != 200 {
+        return Err(io::Error::other(StatusError {
+            code,
+            status_line: status.to_string(),
+        }));
+    }
+    let chunked = lines.any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.starts_with("transfer-encoding:") && line.contains("chunked")
+    });
+    let body = if chunked {
+        decode_chunked(body).ok_or_else(|| invalid("invalid chunked HTTP response body"))?
+    } else {
+        body.to_vec()
+    };
+    String::from_utf8(body).map_err(|_| invalid("HTTP response body isn't valid UTF-8"))
+}
+
+/// Decodes a `Transfer-Encoding: chunked` HTTP body. The chunks are split at
+/// arbitrary bytes, e.g. within a multi-byte UTF-8 character, so they are
+/// joined as bytes.
+fn decode_chunked(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = vec![];
+    loop {
+        let line_end = find(body, b"\r\n")?;
+        let size = std::str::from_utf8(&body[..line_end]).ok()?;
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        let rest = &body[line_end + 2..];
+        if size == 0 {
+            return Some(decoded);
+        }
+        decoded.extend_from_slice(rest.get(..size)?);
+        body = rest.get(size..)?.strip_prefix(b"\r\n")?;
+    }
+}
+
+/// Returns the position of the first occurrence of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn test_parse_http_response() {
+        let response =
+            b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n[]";
+        assert_eq!(parse_http_response(response).unwrap(), "[]");
+
+        let chunked =
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\n[{}\r\n1\r\n]\r\n0\r\n\r\n";
+        assert_eq!(parse_http_response(chunked).unwrap(), "[{}]");
+
+        // the two bytes of the 'ä' are split across the chunks
+        let split_char = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\n[\"\xc3\r\n3\r\n\xa4\"]\r\n0\r\n\r\n";
+        assert_eq!(parse_http_response(split_char).unwrap(), "[\"\u{e4}\"]");
+
+        let invalid_utf8 = b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\n\xff";
+        assert_eq!(
+            parse_http_response(invalid_utf8).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        let unauthorized = b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n";
+        let e = parse_http_response(unauthorized).unwrap_err();
+        let status = e.get_ref().and_then(|e| e.downcast_ref::<StatusError>());
+        assert_eq!(status.map(|status| status.code), Some(401));
+        assert_eq!(
+            e.to_string(),
+            "unexpected HTTP response status 'HTTP/1.1 401 Unauthorized'"
+        );
+        assert!(parse_http_response(b"HTTP/1.1 200 OK\r\n").is_err());
+    }
+
+    #[test]
+    fn test_server_name() {
+        assert_eq!(
+            server_name("127.0.0.1:8332").unwrap(),
+            ServerName::try_from("127.0.0.1").unwrap()
+        );
+        assert_eq!(
+            server_name("node.example.com:443").unwrap(),
+            ServerName::try_from("node.example.com").unwrap()
+        );
+        assert_eq!(
+            server_name("[::1]:8332").unwrap(),
+            ServerName::try_from("::1").unwrap()
+        );
+        assert!(server_name("in valid:443").is_err());
+    }
+
+    #[test]
+    fn test_post_https() {
+        let ca_file = fixture_path("ca.pem");
+        let (host, requests) = spawn_https_server();
+        let config = crate::tls::client_config(Some(&ca_file), false).unwrap();
+        let body = post(&host, "/", None, "[]", TIMEOUT, Some(&config)).unwrap();
+        assert_eq!(body, "[]");
+        assert!(requests.recv().unwrap().starts_with("POST / HTTP/1.1"));
+
+        // The server certificate isn't signed by a trusted CA.
+        let (host, _requests) = spawn_https_server();
+        let untrusted =
+            crate::tls::client_config(Some(&fixture_path("server-cert.pem")), false).unwrap();
+        let e = post(&host, "/", None, "[]", TIMEOUT, Some(&untrusted)).unwrap_err();
+        assert!(e.to_string().contains("certificate"), "{}", e);
+
+        let (host, _requests) = spawn_https_server();
+        let insecure = crate::tls::client_config(None, true).unwrap();
+        assert_eq!(
+            post(&host, "/", None, "[]", TIMEOUT, Some(&insecure)).unwrap(),
+            "[]"
+        );
+
+        assert!(crate::tls::client_config(Some(&fixture_path("server-key.pem")), false).is_err());
+    }
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn fixture_path(name: &str) -> String {
+        format!(
+            "{}/../../shared/src/fixtures/tls/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        )
+    }
+
+    /// Starts a stub HTTPS server answering a single request with `200 OK`
+    /// and an empty JSON array. The request is sent to the returned receiver.
+    fn spawn_https_server() -> (String, mpsc::Receiver<String>) {
+        let certs = rustls_pemfile::certs(&mut BufReader::new(
+            File::open(fixture_path("server-cert.pem")).unwrap(),
+        ))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        let key = rustls_pemfile::private_key(&mut BufReader::new(
+            File::open(fixture_path("server-key.pem")).unwrap(),
+        ))
+        .unwrap()
+        .unwrap();
+        let config = Arc::new(
+            rustls::ServerConfig::builder_with_provider(Arc::new(
+                rustls::crypto::ring::default_provider(),
+            ))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .unwrap(),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = listener.local_addr().unwrap().to_string();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let connection = rustls::ServerConnection::new(config).unwrap();
+            let mut stream = StreamOwned::new(connection, stream);
+            let mut request = vec![0; 4096];
+            let Ok(n) = stream.read(&mut request) else {
+                // the client rejected the certificate
+                return;
+            };
+            tx.send(String::from_utf8_lossy(&request[..n]).to_string())
+                .unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n[]")
+                .unwrap();
+            stream.conn.send_close_notify();
+            stream.flush().unwrap();
+        });
+        (host, rx)
+    }
+}
@@ -0,0 +1,114 @@
+use shared::log::{self, Log, Metadata, Record, SetLoggerError, kv};
+use shared::serde_json::{self, Map, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A logger writing each log record as a single JSON object per line to
+/// stderr. Besides the `timestamp` (UNIX epoch timestamp in milliseconds),
+/// `level`, `target`, and `message`, the key-values of a record (e.g.
+/// `rpc_method`, `duration_ms`, or `error`) are included as fields.
+struct JsonLogger {
+    level: log::Level,
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{}", format_record(record));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Sets up the JSON logger with the `level` as the global logger.
+pub fn init(level: log::Level) -> Result<(), SetLoggerError> {
+    log::set_boxed_logger(Box::new(JsonLogger { level }))?;
+    log::set_max_level(level.to_level_filter());
+    Ok(())
+}
+
+/// Formats the `record` as a JSON object.
+fn format_record(record: &Record) -> String {
+    let mut fields = Map::new();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    fields.insert("timestamp".to_string(), Value::from(timestamp));
+    fields.insert("level".to_string(), Value::from(record.level().as_str()));
+    fields.insert("target".to_string(), Value::from(record.target()));
+    fields.insert(
+        "message".to_string(),
+        Value::from(record.args().to_string()),
+    );
+    // The visitor never fails.
+    let _ = record.key_values().visit(&mut FieldVisitor(&mut fields));
+    Value::Object(fields).to_string()
+}
+
+/// Collects the key-values of a log record as JSON fields.
+struct FieldVisitor<'a>(&'a mut Map<String, Value>);
+
+impl<'kvs> kv::VisitSource<'kvs> for FieldVisitor<'_> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(b) = value.to_bool() {
+            Value::from(b)
+        } else if let Some(n) = value.to_u64() {
+            Value::from(n)
+        } else if let Some(n) = value.to_i64() {
+            Value::from(n)
+        } else if let Some(n) = value.to_f64() {
+            serde_json::Number::from_f64(n).map_or(Value::Null, Value::Number)
+        } else {
+            Value::from(value.to_string())
+        };
+        self.0.insert(key.as_str().to_string(), value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_record() {
+        let key_values: [(&str, kv::Value); 3] = [
+            ("rpc_method", kv::Value::from("uptime")),
+            ("duration_ms", kv::Value::from(12u64)),
+            ("error", kv::Value::from("RPC call timed out after 5s")),
+        ];
+        let record = Record::builder()
+            .level(log::Level::Error)
+            .target("rpc_extractor")
+            .args(format_args!("Could not fetch and publish 'uptime'"))
+            .key_values(&key_values)
+            .build();
+
+        let json: Value = serde_json::from_str(&format_record(&record)).unwrap();
+        assert_eq!(json["level"], "ERROR");
+        assert_eq!(json["target"], "rpc_extractor");
+        assert_eq!(json["message"], "Could not fetch and publish 'uptime'");
+        assert_eq!(json["rpc_method"], "uptime");
+        assert_eq!(json["duration_ms"], 12);
+        assert_eq!(json["error"], "RPC call timed out after 5s");
+        assert!(json["timestamp"].as_u64().unwrap() > 0);
+    }
+
+    #[test]
+    fn test_format_record_without_key_values() {
+        let record = Record::builder()
+            .level(log::Level::Info)
+            .args(format_args!("a \"quoted\"\nmessage"))
+            .build();
+
+        let formatted = format_record(&record);
+        assert!(!formatted.contains('\n'));
+        let json: Value = serde_json::from_str(&formatted).unwrap();
+        assert_eq!(json["message"], "a \"quoted\"\nmessage");
+    }
+}
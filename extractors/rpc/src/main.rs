@@ -1,29 +1,50 @@
-use rpc_extractor::Args;
+use rpc_extractor::{ConfigError, LogFormat, json_logger};
 use shared::log;
+use shared::simple_logger;
 use shared::tokio::{self, signal, sync::watch};
-use shared::{clap::Parser, simple_logger};
+use std::{env, process};
 
 #[tokio::main]
 async fn main() {
-    let args = Args::parse();
+    let args = match rpc_extractor::config::parse_args(env::args_os()) {
+        Ok(args) => args,
+        Err(ConfigError::Clap(e)) => e.exit(),
+        Err(e) => {
+            eprintln!("rpc extractor error: {}", e);
+            process::exit(1);
+        }
+    };
 
-    if let Err(e) = simple_logger::init_with_level(args.log_level) {
+    let logger = match args.log_format {
+        LogFormat::Text => simple_logger::init_with_level(args.log_level),
+        LogFormat::Json => json_logger::init(args.log_level),
+    };
+    if let Err(e) = logger {
         eprintln!("rpc extractor error: {}", e);
     }
 
     let (shutdown_tx, shutdown_rx) = watch::channel(false);
-    let rpc_handle = tokio::spawn(rpc_extractor::run(args, shutdown_rx));
+    let mut rpc_handle = tokio::spawn(rpc_extractor::run(args, shutdown_rx));
 
-    tokio::select! {
+    let result = tokio::select! {
         _ = signal::ctrl_c() => {
             log::info!("Received Ctrl+C. Stopping...");
             let _ = shutdown_tx.send(true);
+            // Returning right away would drop the runtime and cancel the
+            // final flush of the published events.
+            rpc_handle.await
+        }
+        result = &mut rpc_handle => result,
+    };
+    match result {
+        Ok(Ok(_)) => log::info!("rpc-extractor task completed."),
+        Ok(Err(e)) => {
+            log::error!("rpc-extractor task failed: {e}");
+            process::exit(1);
         }
-        result = rpc_handle => {
-            match result.unwrap() {
-                Ok(_) => log::info!("rpc-extractor task completed."),
-                Err(e) => log::error!("rpc-extractor task failed: {e}"),
-            }
+        Err(e) => {
+            log::error!("rpc-extractor task panicked: {e}");
+            process::exit(1);
         }
     }
 }
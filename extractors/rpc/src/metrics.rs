@@ -0,0 +1,669 @@
+use crate::summary::SummaryVec;
+use shared::prometheus::{
+    Gauge, GaugeVec, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+};
+use shared::prometheus::{
+    HistogramOpts, Opts, Registry, register_gauge_vec_with_registry, register_gauge_with_registry,
+    register_histogram_vec_with_registry, register_int_counter_vec_with_registry,
+    register_int_counter_with_registry, register_int_gauge_vec_with_registry,
+    register_int_gauge_with_registry,
+};
+use std::collections::HashMap;
+
+/// The namespace (metric name prefix) used if no custom namespace is set.
+pub const DEFAULT_NAMESPACE: &str = "rpcextractor";
+
+pub const LABEL_RPC_METHOD: &str = "rpc_method";
+pub const LABEL_DIRECTION: &str = "direction";
+pub const LABEL_INDEX: &str = "index";
+// The kind of a failed RPC call, see `FetchOrPublishError::kind`.
+pub const LABEL_ERROR_KIND: &str = "error_kind";
+pub const LABEL_VERSION: &str = "version";
+pub const LABEL_GIT_SHA: &str = "git_sha";
+pub const LABEL_RUSTC: &str = "rustc";
+// Constant label set on all metrics if a node identifier is configured.
+pub const LABEL_NODE_ID: &str = "node_id";
+pub const LABEL_CHAIN: &str = "chain";
+
+// The version, git commit, and rustc version the extractor was built with.
+// The git commit and rustc version are set by the build script.
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const GIT_SHA: &str = env!("RPC_EXTRACTOR_GIT_SHA");
+const RUSTC_VERSION: &str = env!("RPC_EXTRACTOR_RUSTC_VERSION");
+
+// Buckets for the RPC call duration in seconds.
+pub const RPC_DURATION_BUCKETS: [f64; 14] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+// Buckets for the size of the published payloads in bytes.
+pub const PAYLOAD_SIZE_BUCKETS: [f64; 10] = [
+    64.0, 256.0, 1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0, 4194304.0, 16777216.0,
+];
+
+macro_rules! gv {
+    ($name:ident, $desc:expr, $labels:expr, $registry:expr, $const_labels:expr) => {
+        let $name: GaugeVec = register_gauge_vec_with_registry!(
+            Opts::new(stringify!($name), $desc).const_labels($const_labels.clone()),
+            &$labels,
+            $registry
+        )
+        .expect(concat!("Could not create metric '", stringify!($name), "'"));
+    };
+}
+
+macro_rules! g {
+    ($name:ident, $desc:expr, $registry:expr, $const_labels:expr) => {
+        let $name: Gauge = register_gauge_with_registry!(
+            Opts::new(stringify!($name), $desc).const_labels($const_labels.clone()),
+            $registry
+        )
+        .expect(concat!("Could not create metric '", stringify!($name), "'"));
+    };
+}
+
+macro_rules! igv {
+    ($name:ident, $desc:expr, $labels:expr, $registry:expr, $const_labels:expr) => {
+        let $name: IntGaugeVec = register_int_gauge_vec_with_registry!(
+            Opts::new(stringify!($name), $desc).const_labels($const_labels.clone()),
+            &$labels,
+            $registry
+        )
+        .expect(concat!("Could not create metric '", stringify!($name), "'"));
+    };
+}
+
+macro_rules! icv {
+    ($name:ident, $desc:expr, $labels:expr, $registry:expr, $const_labels:expr) => {
+        let $name: IntCounterVec = register_int_counter_vec_with_registry!(
+            Opts::new(stringify!($name), $desc).const_labels($const_labels.clone()),
+            &$labels,
+            $registry
+        )
+        .expect(concat!("Could not create metric '", stringify!($name), "'"));
+    };
+}
+
+macro_rules! ic {
+    ($name:ident, $desc:expr, $registry:expr, $const_labels:expr) => {
+        let $name: IntCounter = register_int_counter_with_registry!(
+            Opts::new(stringify!($name), $desc).const_labels($const_labels.clone()),
+            $registry
+        )
+        .expect(concat!("Could not create metric '", stringify!($name), "'"));
+    };
+}
+
+macro_rules! ig {
+    ($name:ident, $desc:expr, $registry:expr, $const_labels:expr) => {
+        let $name: IntGauge = register_int_gauge_with_registry!(
+            Opts::new(stringify!($name), $desc).const_labels($const_labels.clone()),
+            $registry
+        )
+        .expect(concat!("Could not create metric '", stringify!($name), "'"));
+    };
+}
+
+macro_rules! hv {
+    ($name:ident, $desc:expr, $buckets:expr, $labels:expr, $registry:expr, $const_labels:expr) => {
+        let $name: HistogramVec = register_histogram_vec_with_registry!(
+            HistogramOpts::new(stringify!($name), $desc)
+                .const_labels($const_labels.clone())
+                .buckets($buckets.to_vec()),
+            &$labels,
+            $registry
+        )
+        .expect(concat!("Could not create metric '", stringify!($name), "'"));
+    };
+}
+
+/// How the durations of the RPC calls are recorded in
+/// `rpc_fetch_duration_seconds`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RpcDuration {
+    /// A histogram with these bucket upper bounds.
+    Histogram(Vec<f64>),
+    /// A summary with these quantiles.
+    Summary(Vec<f64>),
+}
+
+impl Default for RpcDuration {
+    fn default() -> Self {
+        RpcDuration::Histogram(RPC_DURATION_BUCKETS.to_vec())
+    }
+}
+
+/// The `rpc_fetch_duration_seconds` metric, either a histogram or a summary.
+#[derive(Debug, Clone)]
+pub enum DurationVec {
+    Histogram(HistogramVec),
+    Summary(SummaryVec),
+}
+
+impl DurationVec {
+    pub fn observe(&self, rpc_method: &str, seconds: f64) {
+        match self {
+            DurationVec::Histogram(histogram) => {
+                histogram.with_label_values(&[rpc_method]).observe(seconds)
+            }
+            DurationVec::Summary(summary) => summary.observe(rpc_method, seconds),
+        }
+    }
+
+    /// Returns the number of recorded durations of the `rpc_method`.
+    pub fn sample_count(&self, rpc_method: &str) -> u64 {
+        match self {
+            DurationVec::Histogram(histogram) => histogram
+                .with_label_values(&[rpc_method])
+                .get_sample_count(),
+            DurationVec::Summary(summary) => summary.sample_count(rpc_method),
+        }
+    }
+}
+
+/// Prometheus metrics about the rpc-extractor itself.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    pub registry: Registry,
+
+    pub nats_connected: IntGauge,
+    pub nats_reconnects_total: IntCounter,
+    pub nats_rtt_seconds: Gauge,
+    pub rpc_fetch_errors: IntCounterVec,
+    pub rpc_fetch_success_total: IntCounterVec,
+    pub rpc_fetch_duration_seconds: DurationVec,
+    pub rpc_last_success_timestamp_seconds: GaugeVec,
+    pub nats_publish_duration_seconds: HistogramVec,
+    pub nats_publish_bytes: HistogramVec,
+    pub nats_publish_errors: IntCounterVec,
+    pub nats_payload_too_large_total: IntCounterVec,
+    pub nats_dropped_total: IntCounterVec,
+    pub nats_publish_success_total: IntCounterVec,
+    pub rpc_dedup_skipped_total: IntCounterVec,
+    pub rpc_throttled_total: IntCounterVec,
+    pub rpc_method_unsupported_total: IntCounterVec,
+    pub rpc_effective_interval_seconds: GaugeVec,
+
+    // getpeerinfo
+    pub peer_count: IntGaugeVec,
+
+    // getindexinfo
+    pub index_best_block_height: IntGaugeVec,
+
+    // getblockchaininfo
+    pub reorgs_total: IntCounter,
+
+    // getnetworkinfo
+    pub network_connections: IntGauge,
+    pub network_connections_in: IntGauge,
+    pub network_connections_out: IntGauge,
+    pub network_version: IntGauge,
+    pub network_protocol_version: IntGauge,
+
+    // getmempoolinfo
+    pub mempool_size: IntGauge,
+    pub mempool_bytes: IntGauge,
+    pub mempool_usage: IntGauge,
+    pub mempool_min_fee: Gauge,
+}
+
+impl Metrics {
+    /// Creates the metrics in a new registry. The `rpc_duration` determines
+    /// whether `rpc_fetch_duration_seconds` is a histogram or a summary. With
+    /// a `chain`, the metrics carry it as `chain` label.
+    pub fn new(
+        namespace: Option<&str>,
+        node_id: Option<&str>,
+        chain: Option<&str>,
+        rpc_duration: &RpcDuration,
+    ) -> Self {
+        let namespace = namespace.unwrap_or(DEFAULT_NAMESPACE);
+        let registry = Registry::new_custom(Some(namespace.to_string()), None)
+            .expect("Could not setup prometheus metric registry");
+        // Registered once per registry, as it's the same for all nodes.
+        igv!(
+            build_info,
+            "Always 1. The version, git commit, and rustc version the extractor was built with.",
+            [LABEL_VERSION, LABEL_GIT_SHA, LABEL_RUSTC],
+            registry,
+            const_labels(node_id, chain)
+        );
+        build_info
+            .with_label_values(&[VERSION, GIT_SHA, RUSTC_VERSION])
+            .set(1);
+        Self::with_registry(registry, node_id, chain, rpc_duration)
+    }
+
+    /// Registers the metrics of a node with the `node_id` in an existing
+    /// `registry`. This allows serving the metrics of multiple nodes, which
+    /// are distinguished by their `node_id` label, from one registry. Each
+    /// node can be on another `chain`.
+    #[rustfmt::skip]
+    pub fn with_registry(registry: Registry, node_id: Option<&str>, chain: Option<&str>, rpc_duration: &RpcDuration) -> Self {
+        let const_labels = const_labels(node_id, chain);
+
+        ig!(nats_connected, "Whether the extractor is currently connected to the NATS server (1) or not (0).", registry, const_labels);
+        ic!(nats_reconnects_total, "Number of times the extractor reconnected to the NATS server after losing the connection.", registry, const_labels);
+        g!(nats_rtt_seconds, "Round-trip time of the last PING to the NATS server in seconds.", registry, const_labels);
+        icv!(rpc_fetch_errors, "Number of failed or timed out RPC calls by the kind of error.", [LABEL_RPC_METHOD, LABEL_ERROR_KIND], registry, const_labels);
+        icv!(rpc_fetch_success_total, "Number of successful RPC calls.", [LABEL_RPC_METHOD], registry, const_labels);
+        let rpc_fetch_duration_seconds = match rpc_duration {
+            RpcDuration::Histogram(buckets) => {
+                hv!(rpc_fetch_duration_seconds, "Duration of successful RPC calls in seconds.", buckets, [LABEL_RPC_METHOD], registry, const_labels);
+                DurationVec::Histogram(rpc_fetch_duration_seconds)
+            }
+            RpcDuration::Summary(quantiles) => {
+                let summary = SummaryVec::new("rpc_fetch_duration_seconds", "Duration of successful RPC calls in seconds.", LABEL_RPC_METHOD, quantiles, const_labels.clone())
+                    .expect("Could not create metric 'rpc_fetch_duration_seconds'");
+                registry.register(Box::new(summary.clone())).expect("Could not register metric 'rpc_fetch_duration_seconds'");
+                DurationVec::Summary(summary)
+            }
+        };
+        gv!(rpc_last_success_timestamp_seconds, "UNIX epoch timestamp of the last successful RPC call.", [LABEL_RPC_METHOD], registry, const_labels);
+
+        hv!(nats_publish_duration_seconds, "Duration of publishing an event to NATS in seconds.", RPC_DURATION_BUCKETS, [LABEL_RPC_METHOD], registry, const_labels);
+        hv!(nats_publish_bytes, "Size of the serialized events published to NATS in bytes.", PAYLOAD_SIZE_BUCKETS, [LABEL_RPC_METHOD], registry, const_labels);
+        icv!(nats_publish_errors, "Number of events that could not be published to NATS.", [LABEL_RPC_METHOD], registry, const_labels);
+        icv!(nats_payload_too_large_total, "Number of events that could not be published as they exceeded the maximum payload size of the NATS server. These are also counted in nats_publish_errors.", [LABEL_RPC_METHOD], registry, const_labels);
+        icv!(nats_dropped_total, "Number of events dropped as the buffer for events published while disconnected from the NATS server was full. These are also counted in nats_publish_errors.", [LABEL_RPC_METHOD], registry, const_labels);
+        icv!(nats_publish_success_total, "Number of events published to NATS.", [LABEL_RPC_METHOD], registry, const_labels);
+        icv!(rpc_dedup_skipped_total, "Number of events not published as their payload was unchanged since the last published event.", [LABEL_RPC_METHOD], registry, const_labels);
+        icv!(rpc_throttled_total, "Number of RPC calls deferred as they exceeded the maximum number of RPC calls per second.", [LABEL_RPC_METHOD], registry, const_labels);
+        icv!(rpc_method_unsupported_total, "Number of RPC methods no longer queried as the node answered with a method not found error. At most 1 per method.", [LABEL_RPC_METHOD], registry, const_labels);
+        gv!(rpc_effective_interval_seconds, "Time between the last two queries of an RPC method in seconds, including the jitter and skipped queries. The configured interval until the method was queried twice.", [LABEL_RPC_METHOD], registry, const_labels);
+
+        igv!(peer_count, "Number of connected peers by connection direction (inbound or outbound) as reported by getpeerinfo.", [LABEL_DIRECTION], registry, const_labels);
+
+        igv!(index_best_block_height, "Height of the last block processed by an index (e.g. txindex) as reported by getindexinfo.", [LABEL_INDEX], registry, const_labels);
+
+        ic!(reorgs_total, "Number of reorgs detected from a change of the best block in getblockchaininfo to a block not extending the previous best block.", registry, const_labels);
+
+        ig!(network_connections, "Total number of connections as reported by getnetworkinfo.", registry, const_labels);
+        ig!(network_connections_in, "Number of inbound connections as reported by getnetworkinfo.", registry, const_labels);
+        ig!(network_connections_out, "Number of outbound connections as reported by getnetworkinfo.", registry, const_labels);
+        ig!(network_version, "Version of the Bitcoin Core node (e.g. 280000) as reported by getnetworkinfo.", registry, const_labels);
+        ig!(network_protocol_version, "P2P protocol version of the Bitcoin Core node as reported by getnetworkinfo.", registry, const_labels);
+
+        ig!(mempool_size, "Number of transactions in the mempool as reported by getmempoolinfo.", registry, const_labels);
+        ig!(mempool_bytes, "Sum of the virtual sizes of the mempool transactions in vbytes as reported by getmempoolinfo.", registry, const_labels);
+        ig!(mempool_usage, "Memory usage of the mempool in bytes as reported by getmempoolinfo.", registry, const_labels);
+        g!(mempool_min_fee, "Minimum fee rate in BTC/kvB for a transaction to be accepted into the mempool as reported by getmempoolinfo.", registry, const_labels);
+
+        Self {
+            registry,
+            nats_connected,
+            nats_reconnects_total,
+            nats_rtt_seconds,
+            rpc_fetch_errors,
+            rpc_fetch_success_total,
+            rpc_fetch_duration_seconds,
+            rpc_last_success_timestamp_seconds,
+            nats_publish_duration_seconds,
+            nats_publish_bytes,
+            nats_publish_errors,
+            nats_payload_too_large_total,
+            nats_dropped_total,
+            nats_publish_success_total,
+            rpc_dedup_skipped_total,
+            rpc_throttled_total,
+            rpc_method_unsupported_total,
+            rpc_effective_interval_seconds,
+            peer_count,
+            index_best_block_height,
+            reorgs_total,
+            network_connections,
+            network_connections_in,
+            network_connections_out,
+            network_version,
+            network_protocol_version,
+            mempool_size,
+            mempool_bytes,
+            mempool_usage,
+            mempool_min_fee,
+        }
+    }
+}
+
+/// The constant labels set on all metrics of the node with the `node_id` on
+/// the `chain`.
+fn const_labels(node_id: Option<&str>, chain: Option<&str>) -> HashMap<String, String> {
+    let mut labels = HashMap::new();
+    if let Some(id) = node_id {
+        labels.insert(LABEL_NODE_ID.to_string(), id.to_string());
+    }
+    if let Some(chain) = chain {
+        labels.insert(LABEL_CHAIN.to_string(), chain.to_string());
+    }
+    labels
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new(None, None, None, &RpcDuration::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::prometheus::{Encoder, TextEncoder};
+
+    fn encode(metrics: &Metrics) -> String {
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&metrics.registry.gather(), &mut buffer)
+            .expect("should be able to encode the metrics");
+        String::from_utf8(buffer).expect("metrics should be valid UTF-8")
+    }
+
+    #[test]
+    fn test_build_info() {
+        let encoded = encode(&Metrics::default());
+        let line = encoded
+            .lines()
+            .find(|line| line.starts_with("rpcextractor_build_info{"))
+            .expect("build_info should be registered");
+        assert!(line.ends_with("} 1"));
+        assert!(line.contains(&format!("version=\"{}\"", env!("CARGO_PKG_VERSION"))));
+        assert!(!line.contains("version=\"\""));
+        assert!(line.contains("git_sha=\""));
+        assert!(line.contains("rustc=\""));
+
+        // registered only once for the additional nodes in the same registry
+        let metrics = Metrics::default();
+        Metrics::with_registry(
+            metrics.registry.clone(),
+            Some("node2"),
+            None,
+            &RpcDuration::default(),
+        );
+        assert_eq!(
+            encode(&metrics).matches("rpcextractor_build_info{").count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_nats_connected_registered() {
+        let metrics = Metrics::default();
+
+        assert!(encode(&metrics).contains("\nrpcextractor_nats_connected 0\n"));
+        assert_eq!(metrics.nats_connected.get(), 0);
+    }
+
+    #[test]
+    fn test_nats_rtt_seconds() {
+        let metrics = Metrics::default();
+        assert!(encode(&metrics).contains("\nrpcextractor_nats_rtt_seconds 0\n"));
+
+        metrics.nats_rtt_seconds.set(0.0025);
+        assert!(encode(&metrics).contains("\nrpcextractor_nats_rtt_seconds 0.0025\n"));
+    }
+
+    #[test]
+    fn test_nats_reconnects_total_increment() {
+        let metrics = Metrics::default();
+        assert!(encode(&metrics).contains("\nrpcextractor_nats_reconnects_total 0\n"));
+
+        metrics.nats_reconnects_total.inc();
+        metrics.nats_reconnects_total.inc();
+
+        assert_eq!(metrics.nats_reconnects_total.get(), 2);
+        assert!(encode(&metrics).contains("\nrpcextractor_nats_reconnects_total 2\n"));
+    }
+
+    #[test]
+    fn test_rpc_fetch_errors_increment() {
+        let metrics = Metrics::default();
+
+        metrics
+            .rpc_fetch_errors
+            .with_label_values(&["uptime", "timeout"])
+            .inc();
+
+        assert_eq!(
+            metrics
+                .rpc_fetch_errors
+                .with_label_values(&["uptime", "timeout"])
+                .get(),
+            1
+        );
+        assert!(encode(&metrics).contains(
+            "\nrpcextractor_rpc_fetch_errors{error_kind=\"timeout\",rpc_method=\"uptime\"} 1\n"
+        ));
+    }
+
+    #[test]
+    fn test_rpc_fetch_success_total_increment() {
+        let metrics = Metrics::default();
+
+        metrics
+            .rpc_fetch_success_total
+            .with_label_values(&["getpeerinfo"])
+            .inc();
+
+        assert_eq!(
+            metrics
+                .rpc_fetch_success_total
+                .with_label_values(&["getpeerinfo"])
+                .get(),
+            1
+        );
+        assert!(
+            encode(&metrics)
+                .contains("\nrpcextractor_rpc_fetch_success_total{rpc_method=\"getpeerinfo\"} 1\n")
+        );
+    }
+
+    #[test]
+    fn test_nats_publish_duration_timer() {
+        let metrics = Metrics::default();
+
+        let timer = metrics
+            .nats_publish_duration_seconds
+            .with_label_values(&["uptime"])
+            .start_timer();
+        timer.observe_duration();
+
+        let histogram = metrics
+            .nats_publish_duration_seconds
+            .with_label_values(&["uptime"]);
+        assert_eq!(histogram.get_sample_count(), 1);
+        assert!(histogram.get_sample_sum() >= 0.0);
+        assert!(encode(&metrics).contains(
+            "\nrpcextractor_nats_publish_duration_seconds_count{rpc_method=\"uptime\"} 1\n"
+        ));
+    }
+
+    #[test]
+    fn test_nats_publish_bytes() {
+        let metrics = Metrics::default();
+
+        metrics
+            .nats_publish_bytes
+            .with_label_values(&["getrawmempool"])
+            .observe(5000.0);
+
+        let histogram = metrics
+            .nats_publish_bytes
+            .with_label_values(&["getrawmempool"]);
+        assert_eq!(histogram.get_sample_count(), 1);
+        assert_eq!(histogram.get_sample_sum(), 5000.0);
+        let encoded = encode(&metrics);
+        assert!(encoded.contains(
+            "\nrpcextractor_nats_publish_bytes_bucket{rpc_method=\"getrawmempool\",le=\"4096\"} 0\n"
+        ));
+        assert!(encoded.contains(
+            "\nrpcextractor_nats_publish_bytes_bucket{rpc_method=\"getrawmempool\",le=\"16384\"} 1\n"
+        ));
+    }
+
+    #[test]
+    fn test_nats_publish_success_total_increment() {
+        let metrics = Metrics::default();
+
+        metrics
+            .nats_publish_success_total
+            .with_label_values(&["getnettotals"])
+            .inc();
+        metrics
+            .nats_publish_success_total
+            .with_label_values(&["getnettotals"])
+            .inc();
+
+        assert_eq!(
+            metrics
+                .nats_publish_success_total
+                .with_label_values(&["getnettotals"])
+                .get(),
+            2
+        );
+        assert!(encode(&metrics).contains(
+            "\nrpcextractor_nats_publish_success_total{rpc_method=\"getnettotals\"} 2\n"
+        ));
+    }
+
+    #[test]
+    fn test_custom_rpc_duration_buckets() {
+        let metrics = Metrics::new(
+            None,
+            None,
+            None,
+            &RpcDuration::Histogram(vec![0.0005, 0.002]),
+        );
+        metrics.rpc_fetch_duration_seconds.observe("uptime", 0.001);
+
+        let encoded = encode(&metrics);
+        for (bucket, count) in [("0.0005", 0), ("0.002", 1), ("+Inf", 1)] {
+            assert!(encoded.contains(&format!(
+                "\nrpcextractor_rpc_fetch_duration_seconds_bucket{{rpc_method=\"uptime\",le=\"{}\"}} {}\n",
+                bucket, count
+            )));
+        }
+        assert!(!encoded.contains(
+            "rpcextractor_rpc_fetch_duration_seconds_bucket{rpc_method=\"uptime\",le=\"0.001\"}"
+        ));
+
+        // the default buckets are used if none are set
+        let metrics = Metrics::default();
+        metrics.rpc_fetch_duration_seconds.observe("uptime", 0.001);
+        let encoded = encode(&metrics);
+        assert!(encoded.contains(
+            "rpcextractor_rpc_fetch_duration_seconds_bucket{rpc_method=\"uptime\",le=\"0.001\"} 1\n"
+        ));
+        assert!(!encoded.contains("le=\"0.0005\""));
+    }
+
+    #[test]
+    fn test_rpc_duration_histogram_or_summary() {
+        let histogram = Metrics::default();
+        histogram
+            .rpc_fetch_duration_seconds
+            .observe("uptime", 0.001);
+        let encoded = encode(&histogram);
+        assert!(encoded.contains("\n# TYPE rpcextractor_rpc_fetch_duration_seconds histogram\n"));
+        assert_eq!(
+            histogram.rpc_fetch_duration_seconds.sample_count("uptime"),
+            1
+        );
+
+        let summary = Metrics::new(
+            None,
+            Some("node1"),
+            None,
+            &RpcDuration::Summary(vec![0.5, 0.99]),
+        );
+        let node2 = Metrics::with_registry(
+            summary.registry.clone(),
+            Some("node2"),
+            None,
+            &RpcDuration::Summary(vec![0.5, 0.99]),
+        );
+        for duration in [0.001, 0.002, 0.003] {
+            summary
+                .rpc_fetch_duration_seconds
+                .observe("uptime", duration);
+        }
+        node2.rpc_fetch_duration_seconds.observe("uptime", 0.5);
+        let encoded = encode(&summary);
+        assert!(encoded.contains("\n# TYPE rpcextractor_rpc_fetch_duration_seconds summary\n"));
+        assert!(!encoded.contains("rpcextractor_rpc_fetch_duration_seconds_bucket"));
+        assert!(encoded.contains("\nrpcextractor_rpc_fetch_duration_seconds{node_id=\"node1\",rpc_method=\"uptime\",quantile=\"0.5\"} 0.002\n"));
+        assert!(encoded.contains("\nrpcextractor_rpc_fetch_duration_seconds{node_id=\"node1\",rpc_method=\"uptime\",quantile=\"0.99\"} 0.003\n"));
+        assert!(encoded.contains("\nrpcextractor_rpc_fetch_duration_seconds_count{node_id=\"node1\",rpc_method=\"uptime\"} 3\n"));
+        assert!(encoded.contains("\nrpcextractor_rpc_fetch_duration_seconds_count{node_id=\"node2\",rpc_method=\"uptime\"} 1\n"));
+        assert_eq!(summary.rpc_fetch_duration_seconds.sample_count("uptime"), 3);
+    }
+
+    #[test]
+    fn test_custom_namespace() {
+        let metrics = Metrics::new(Some("custom"), None, None, &RpcDuration::default());
+
+        let encoded = encode(&metrics);
+        assert!(encoded.contains("\ncustom_nats_connected 0\n"));
+        assert!(!encoded.contains("rpcextractor_"));
+    }
+
+    #[test]
+    fn test_node_id_label() {
+        let metrics = Metrics::new(None, Some("node-1"), None, &RpcDuration::default());
+
+        let encoded = encode(&metrics);
+        assert!(encoded.contains("\nrpcextractor_nats_connected{node_id=\"node-1\"} 0\n"));
+    }
+
+    #[test]
+    fn test_reorgs_total() {
+        let metrics = Metrics::default();
+        assert!(encode(&metrics).contains("\nrpcextractor_reorgs_total 0\n"));
+        metrics.reorgs_total.inc();
+        assert!(encode(&metrics).contains("\nrpcextractor_reorgs_total 1\n"));
+    }
+
+    #[test]
+    fn test_chain_label() {
+        let metrics = Metrics::new(
+            None,
+            Some("node1"),
+            Some("regtest"),
+            &RpcDuration::default(),
+        );
+        Metrics::with_registry(
+            metrics.registry.clone(),
+            Some("node2"),
+            Some("signet"),
+            &RpcDuration::default(),
+        );
+
+        let encoded = encode(&metrics);
+        let connected: Vec<&str> = encoded
+            .lines()
+            .filter(|line| line.starts_with("rpcextractor_nats_connected{"))
+            .collect();
+        assert_eq!(connected.len(), 2);
+        // each node is labeled with its own chain
+        for (node_id, chain) in [("node1", "regtest"), ("node2", "signet")] {
+            assert!(connected.iter().any(|line| {
+                line.contains(&format!("chain=\"{}\"", chain))
+                    && line.contains(&format!("node_id=\"{}\"", node_id))
+            }));
+        }
+        assert!(!encode(&Metrics::default()).contains("chain="));
+    }
+
+    #[test]
+    fn test_multiple_nodes_in_one_registry() {
+        let node1 = Metrics::new(None, Some("node1"), None, &RpcDuration::default());
+        let node2 = Metrics::with_registry(
+            node1.registry.clone(),
+            Some("node2"),
+            None,
+            &RpcDuration::default(),
+        );
+        node2.nats_reconnects_total.inc();
+
+        let encoded = encode(&node1);
+        assert!(encoded.contains("\nrpcextractor_nats_reconnects_total{node_id=\"node1\"} 0\n"));
+        assert!(encoded.contains("\nrpcextractor_nats_reconnects_total{node_id=\"node2\"} 1\n"));
+    }
+}
@@ -0,0 +1,55 @@
+use crate::metrics::Metrics;
+use shared::async_nats;
+use shared::log;
+use shared::tokio::sync::watch;
+use shared::tokio::time::{self, Duration, Instant};
+
+/// Measures the round-trip time to the NATS server every `interval` until
+/// the extractor is shut down and records it in the `nats_rtt_seconds` gauge
+/// of each of the `node_metrics`. A flush sends a PING to the server and
+/// completes once the PONG is received.
+pub(crate) async fn measure_periodically(
+    client: async_nats::Client,
+    node_metrics: Vec<Metrics>,
+    interval: Duration,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    log::debug!("Measuring the NATS round-trip time every {:?}", interval);
+    let mut ticks = time::interval(interval);
+    loop {
+        shared::tokio::select! {
+            _ = ticks.tick() => {
+                if let Some(rtt) = measure(&client, interval).await {
+                    for metrics in node_metrics.iter() {
+                        metrics.nats_rtt_seconds.set(rtt.as_secs_f64());
+                    }
+                }
+            }
+            res = shutdown_rx.changed() => {
+                if res.is_err() || *shutdown_rx.borrow() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Returns the round-trip time to the NATS server, or `None` if the flush
+/// failed or didn't complete within the `timeout`, e.g. while reconnecting.
+async fn measure(client: &async_nats::Client, timeout: Duration) -> Option<Duration> {
+    let start = Instant::now();
+    match time::timeout(timeout, client.flush()).await {
+        Ok(Ok(())) => Some(start.elapsed()),
+        Ok(Err(e)) => {
+            log::debug!("Could not measure the NATS round-trip time: {}", e);
+            None
+        }
+        Err(_) => {
+            log::debug!(
+                "Could not measure the NATS round-trip time within {:?}",
+                timeout
+            );
+            None
+        }
+    }
+}
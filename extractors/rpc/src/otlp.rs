@@ -0,0 +1,291 @@
+use crate::OtlpEndpoint;
+use crate::http;
+use shared::log;
+use shared::prometheus::Registry;
+use shared::prometheus::proto::{LabelPair, Metric, MetricFamily, MetricType};
+use shared::serde_json::{Map, Value, json};
+use shared::tokio::sync::watch;
+use shared::tokio::task;
+use shared::tokio::time::{self, Duration};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Timeout for a single export to the OTLP endpoint.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+// OTLP AggregationTemporality of the Prometheus counters and histograms.
+const AGGREGATION_TEMPORALITY_CUMULATIVE: u8 = 2;
+const SERVICE_NAME: &str = "rpc-extractor";
+
+/// Exports the metrics in the `registry` to the OTLP `endpoint` every
+/// `interval` until the extractor is shut down. The metrics are exported once
+/// more when shutting down. A failed export is logged and the metrics are
+/// exported again on the next interval.
+pub(crate) async fn export_periodically(
+    endpoint: OtlpEndpoint,
+    registry: Registry,
+    interval: Duration,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    log::info!(
+        "Exporting metrics to the OTLP endpoint {} every {:?}",
+        endpoint,
+        interval
+    );
+    let start_nanos = unix_nanos();
+    let mut interval = time::interval(interval);
+    loop {
+        shared::tokio::select! {
+            _ = interval.tick() => export(&endpoint, &registry, start_nanos).await,
+            res = shutdown_rx.changed() => {
+                if res.is_err() || *shutdown_rx.borrow() {
+                    export(&endpoint, &registry, start_nanos).await;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn export(endpoint: &OtlpEndpoint, registry: &Registry, start_nanos: u64) {
+    let body = export_request(&registry.gather(), start_nanos, unix_nanos()).to_string();
+    let (host, path) = (endpoint.host.clone(), endpoint.path.clone());
+    let handle =
+        task::spawn_blocking(move || http::post(&host, &path, None, &body, EXPORT_TIMEOUT, None));
+    match handle.await {
+        Ok(Ok(_)) => log::debug!("Exported the metrics to {}", endpoint),
+        Ok(Err(e)) => log::warn!("Could not export the metrics to {}: {}", endpoint, e),
+        Err(e) => log::warn!("Could not export the metrics to {}: {}", endpoint, e),
+    }
+}
+
+fn unix_nanos() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+/// Converts the gathered Prometheus metric `families` into an OTLP/HTTP JSON
+/// `ExportMetricsServiceRequest`. Counters become monotonic sums, gauges
+/// gauges, histograms explicit-bucket histograms, and summaries summaries,
+/// all cumulative since `start_nanos`.
+fn export_request(families: &[MetricFamily], start_nanos: u64, now_nanos: u64) -> Value {
+    let metrics: Vec<Value> = families
+        .iter()
+        .filter_map(|family| {
+            let data_points = family
+                .metric
+                .iter()
+                .map(|metric| data_point(family.type_(), metric, start_nanos, now_nanos));
+            let (kind, data) = match family.type_() {
+                MetricType::COUNTER => (
+                    "sum",
+                    json!({
+                        "dataPoints": data_points.collect::<Vec<_>>(),
+                        "aggregationTemporality": AGGREGATION_TEMPORALITY_CUMULATIVE,
+                        "isMonotonic": true,
+                    }),
+                ),
+                MetricType::GAUGE => (
+                    "gauge",
+                    json!({ "dataPoints": data_points.collect::<Vec<_>>() }),
+                ),
+                MetricType::HISTOGRAM => (
+                    "histogram",
+                    json!({
+                        "dataPoints": data_points.collect::<Vec<_>>(),
+                        "aggregationTemporality": AGGREGATION_TEMPORALITY_CUMULATIVE,
+                    }),
+                ),
+                MetricType::SUMMARY => (
+                    "summary",
+                    json!({ "dataPoints": data_points.collect::<Vec<_>>() }),
+                ),
+                // not used by the extractor
+                _ => return None,
+            };
+            let mut metric = Map::new();
+            metric.insert("name".to_string(), Value::from(family.name()));
+            metric.insert("description".to_string(), Value::from(family.help()));
+            metric.insert(kind.to_string(), data);
+            Some(Value::Object(metric))
+        })
+        .collect();
+
+    json!({
+        "resourceMetrics": [{
+            "resource": {
+                "attributes": [{
+                    "key": "service.name",
+                    "value": { "stringValue": SERVICE_NAME },
+                }],
+            },
+            "scopeMetrics": [{
+                "scope": { "name": SERVICE_NAME, "version": env!("CARGO_PKG_VERSION") },
+                "metrics": metrics,
+            }],
+        }],
+    })
+}
+
+/// Converts a single Prometheus `metric` of the `metric_type` into an OTLP
+/// data point. 64-bit integers are encoded as strings, as required by the
+/// OTLP JSON encoding.
+fn data_point(metric_type: MetricType, metric: &Metric, start_nanos: u64, now_nanos: u64) -> Value {
+    let mut fields = Map::new();
+    fields.insert("attributes".to_string(), attributes(&metric.label));
+    fields.insert(
+        "startTimeUnixNano".to_string(),
+        Value::from(start_nanos.to_string()),
+    );
+    fields.insert(
+        "timeUnixNano".to_string(),
+        Value::from(now_nanos.to_string()),
+    );
+    match metric_type {
+        MetricType::COUNTER => {
+            fields.insert("asDouble".to_string(), json!(metric.counter.value()));
+        }
+        MetricType::GAUGE => {
+            fields.insert("asDouble".to_string(), json!(metric.gauge.value()));
+        }
+        MetricType::HISTOGRAM => {
+            let histogram = &metric.histogram;
+            // Prometheus buckets are cumulative and don't include the +Inf
+            // bucket. OTLP bucket counts are per bucket, including +Inf.
+            let mut bucket_counts = vec![];
+            let mut previous = 0;
+            for bucket in histogram.bucket.iter() {
+                bucket_counts.push((bucket.cumulative_count() - previous).to_string());
+                previous = bucket.cumulative_count();
+            }
+            bucket_counts.push((histogram.sample_count() - previous).to_string());
+            let bounds: Vec<f64> = histogram
+                .bucket
+                .iter()
+                .map(|bucket| bucket.upper_bound())
+                .collect();
+            fields.insert(
+                "count".to_string(),
+                json!(histogram.sample_count().to_string()),
+            );
+            fields.insert("sum".to_string(), json!(histogram.sample_sum()));
+            fields.insert("bucketCounts".to_string(), json!(bucket_counts));
+            fields.insert("explicitBounds".to_string(), json!(bounds));
+        }
+        MetricType::SUMMARY => {
+            let summary = &metric.summary;
+            let quantiles: Vec<Value> = summary
+                .quantile
+                .iter()
+                .map(|q| json!({ "quantile": q.quantile(), "value": q.value() }))
+                .collect();
+            fields.insert(
+                "count".to_string(),
+                json!(summary.sample_count().to_string()),
+            );
+            fields.insert("sum".to_string(), json!(summary.sample_sum()));
+            fields.insert("quantileValues".to_string(), json!(quantiles));
+        }
+        _ => (),
+    }
+    Value::Object(fields)
+}
+
+fn attributes(labels: &[LabelPair]) -> Value {
+    labels
+        .iter()
+        .map(|label| {
+            json!({
+                "key": label.name(),
+                "value": { "stringValue": label.value() },
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::summary::SummaryVec;
+    use shared::prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts};
+
+    #[test]
+    fn test_export_request() {
+        let registry = Registry::new();
+        let counter = IntCounterVec::new(
+            Opts::new("rpc_fetch_errors", "Number of failed RPC calls.").namespace("test"),
+            &["rpc_method"],
+        )
+        .unwrap();
+        let histogram = HistogramVec::new(
+            HistogramOpts::new("rpc_fetch_duration_seconds", "Duration of RPC calls.")
+                .namespace("test")
+                .buckets(vec![0.1, 1.0]),
+            &["rpc_method"],
+        )
+        .unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        registry.register(Box::new(histogram.clone())).unwrap();
+        counter.with_label_values(&["uptime"]).inc_by(3);
+        for duration in [0.0625, 0.5, 5.0, 6.0] {
+            histogram.with_label_values(&["uptime"]).observe(duration);
+        }
+
+        let request = export_request(&registry.gather(), 1, 2);
+        let scope = &request["resourceMetrics"][0]["scopeMetrics"][0];
+        let metrics = scope["metrics"].as_array().unwrap();
+        assert_eq!(metrics.len(), 2);
+
+        let errors = metrics
+            .iter()
+            .find(|m| m["name"] == "test_rpc_fetch_errors")
+            .unwrap();
+        assert_eq!(errors["description"], "Number of failed RPC calls.");
+        assert_eq!(errors["sum"]["isMonotonic"], true);
+        let point = &errors["sum"]["dataPoints"][0];
+        assert_eq!(point["asDouble"], 3.0);
+        assert_eq!(point["startTimeUnixNano"], "1");
+        assert_eq!(point["timeUnixNano"], "2");
+        assert_eq!(point["attributes"][0]["key"], "rpc_method");
+        assert_eq!(point["attributes"][0]["value"]["stringValue"], "uptime");
+
+        let duration = metrics
+            .iter()
+            .find(|m| m["name"] == "test_rpc_fetch_duration_seconds")
+            .unwrap();
+        let point = &duration["histogram"]["dataPoints"][0];
+        assert_eq!(point["count"], "4");
+        assert_eq!(point["sum"], 11.5625);
+        assert_eq!(point["explicitBounds"], json!([0.1, 1.0]));
+        assert_eq!(point["bucketCounts"], json!(["1", "1", "2"]));
+    }
+
+    #[test]
+    fn test_export_request_summary() {
+        let registry = Registry::new();
+        let summary = SummaryVec::new(
+            "rpc_fetch_duration_seconds",
+            "Duration of RPC calls.",
+            "rpc_method",
+            &[0.5],
+            Default::default(),
+        )
+        .unwrap();
+        registry.register(Box::new(summary.clone())).unwrap();
+        for duration in [0.25, 0.5, 2.0] {
+            summary.observe("uptime", duration);
+        }
+
+        let request = export_request(&registry.gather(), 1, 2);
+        let metric = &request["resourceMetrics"][0]["scopeMetrics"][0]["metrics"][0];
+        assert_eq!(metric["name"], "rpc_fetch_duration_seconds");
+        let point = &metric["summary"]["dataPoints"][0];
+        assert_eq!(point["count"], "3");
+        assert_eq!(point["sum"], 2.75);
+        assert_eq!(
+            point["quantileValues"],
+            json!([{ "quantile": 0.5, "value": 0.5 }])
+        );
+        assert_eq!(point["attributes"][0]["value"]["stringValue"], "uptime");
+    }
+}
@@ -0,0 +1,158 @@
+use shared::protobuf::rpc_extractor::{PeerInfo, PeerInfos};
+
+// Declares the fields of a `PeerInfo` that `--getpeerinfo-fields` can select,
+// each with the name of the field in the `getpeerinfo` RPC result.
+macro_rules! peer_info_fields {
+    ($(($field:ident, $rpc_name:literal)),* $(,)?) => {
+        /// The names of the `PeerInfo` fields and of the corresponding fields
+        /// in the `getpeerinfo` RPC result.
+        const FIELDS: &[(&str, &str)] = &[$((stringify!($field), $rpc_name)),*];
+
+        /// Moves the field with the `name` from the peer info `from` to `to`.
+        fn move_field(name: &str, from: &mut PeerInfo, to: &mut PeerInfo) {
+            match name {
+                $(stringify!($field) => to.$field = std::mem::take(&mut from.$field),)*
+                _ => unreachable!("unknown PeerInfo field '{}'", name),
+            }
+        }
+    };
+}
+
+peer_info_fields!(
+    (id, "id"),
+    (address, "addr"),
+    (address_bind, "addrbind"),
+    (address_local, "addrlocal"),
+    (network, "network"),
+    (mapped_as, "mapped_as"),
+    (services, "services"),
+    (relay_transactions, "relaytxes"),
+    (last_send, "lastsend"),
+    (last_received, "lastrecv"),
+    (last_transaction, "last_transaction"),
+    (last_block, "last_block"),
+    (bytes_sent, "bytessent"),
+    (bytes_received, "bytesrecv"),
+    (connection_time, "conntime"),
+    (time_offset, "timeoffset"),
+    (ping_time, "pingtime"),
+    (minimum_ping, "minping"),
+    (ping_wait, "pingwait"),
+    (version, "version"),
+    (subversion, "subver"),
+    (inbound, "inbound"),
+    (bip152_hb_to, "bip152_hb_to"),
+    (bip152_hb_from, "bip152_hb_from"),
+    (starting_height, "startingheight"),
+    (synced_headers, "synced_headers"),
+    (synced_blocks, "synced_blocks"),
+    (inflight, "inflight"),
+    (addr_relay_enabled, "addr_relay_enabled"),
+    (addr_processed, "addr_processed"),
+    (addr_rate_limited, "addr_rate_limited"),
+    (permissions, "permissions"),
+    (minfeefilter, "minfeefilter"),
+    (bytes_sent_per_message, "bytessent_per_msg"),
+    (bytes_received_per_message, "bytesrecv_per_msg"),
+    (connection_type, "connection_type"),
+    (transport_protocol_type, "transport_protocol_type"),
+    (inv_to_send, "inv_to_send"),
+    (cpu_load, "cpu_load"),
+);
+
+/// Parses a field of `--getpeerinfo-fields`, either the name of the field in
+/// the `getpeerinfo` RPC result (e.g. `subver`) or in the published
+/// `PeerInfo` (e.g. `subversion`). Returns the `PeerInfo` field name.
+pub(crate) fn parse_field(s: &str) -> Result<String, String> {
+    let s = s.trim();
+    FIELDS
+        .iter()
+        .find(|(field, rpc_name)| *field == s || *rpc_name == s)
+        .map(|(field, _)| field.to_string())
+        .ok_or_else(|| format!("unknown getpeerinfo field '{}'", s))
+}
+
+/// Keeps only the `fields` (as returned by [parse_field]) of each peer in
+/// the `peer_infos`. The other fields are reset to their default, i.e. zero
+/// or empty.
+pub(crate) fn project(peer_infos: &mut PeerInfos, fields: &[String]) {
+    for info in peer_infos.infos.iter_mut() {
+        let mut projected = PeerInfo::default();
+        for field in fields {
+            move_field(field, info, &mut projected);
+        }
+        *info = projected;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::prost::Message;
+    use std::collections::HashMap;
+
+    fn peer_info() -> PeerInfo {
+        PeerInfo {
+            id: 7,
+            address: "203.0.113.1:8333".to_string(),
+            network: "ipv4".to_string(),
+            bytes_sent: 1000,
+            bytes_received: 2000,
+            subversion: "/Satoshi:29.0.0/".to_string(),
+            inbound: true,
+            inflight: vec![900_000, 900_001],
+            permissions: vec!["noban".to_string()],
+            bytes_sent_per_message: HashMap::from([("inv".to_string(), 500)]),
+            bytes_received_per_message: HashMap::from([("tx".to_string(), 1500)]),
+            connection_type: "inbound".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_field() {
+        assert_eq!(parse_field("subver"), Ok("subversion".to_string()));
+        assert_eq!(parse_field("subversion"), Ok("subversion".to_string()));
+        assert_eq!(parse_field(" bytesrecv"), Ok("bytes_received".to_string()));
+        assert_eq!(
+            parse_field("bytessent_per_msg"),
+            Ok("bytes_sent_per_message".to_string())
+        );
+        assert!(parse_field("unknown").is_err());
+        assert!(parse_field("").is_err());
+    }
+
+    #[test]
+    fn test_project_all_fields() {
+        let mut all = PeerInfos {
+            infos: vec![peer_info()],
+        };
+        let fields: Vec<String> = FIELDS.iter().map(|(field, _)| field.to_string()).collect();
+        project(&mut all, &fields);
+        assert_eq!(all.infos, vec![peer_info()]);
+    }
+
+    #[test]
+    fn test_project() {
+        let mut peer_infos = PeerInfos {
+            infos: vec![peer_info(), peer_info()],
+        };
+        let full_size = peer_infos.encoded_len();
+        let fields: Vec<String> = ["addr", "subver", "inbound", "bytessent", "bytesrecv"]
+            .iter()
+            .map(|field| parse_field(field).unwrap())
+            .collect();
+        project(&mut peer_infos, &fields);
+
+        let expected = PeerInfo {
+            address: "203.0.113.1:8333".to_string(),
+            subversion: "/Satoshi:29.0.0/".to_string(),
+            inbound: true,
+            bytes_sent: 1000,
+            bytes_received: 2000,
+            ..Default::default()
+        };
+        assert_eq!(peer_infos.infos, vec![expected.clone(), expected]);
+        assert!(peer_infos.encoded_len() < full_size);
+    }
+}
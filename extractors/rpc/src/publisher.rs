@@ -0,0 +1,344 @@
+use crate::error::PublishError;
+use shared::futures::future::{self, BoxFuture};
+use shared::log;
+use shared::nats_publisher::{self, ErrorCounter, NatsPublisher};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+pub use shared::nats_publisher::Headers;
+
+/// Publishes the serialized events. The extractor only depends on this trait,
+/// so that the events can be published to NATS or Kafka.
+pub trait Publisher: Send + Sync {
+    /// Publishes the `payload` with the `headers` on the `subject` and
+    /// increments the `errors` counter if that fails.
+    fn publish<'a>(
+        &'a self,
+        subject: &'a str,
+        headers: Headers,
+        payload: Vec<u8>,
+        errors: &'a dyn ErrorCounter,
+    ) -> BoxFuture<'a, Result<(), PublishError>>;
+
+    /// Waits until the already published events were sent.
+    fn flush(&self) -> BoxFuture<'_, Result<(), PublishError>>;
+}
+
+impl Publisher for NatsPublisher {
+    fn publish<'a>(
+        &'a self,
+        subject: &'a str,
+        headers: Headers,
+        payload: Vec<u8>,
+        errors: &'a dyn ErrorCounter,
+    ) -> BoxFuture<'a, Result<(), PublishError>> {
+        Box::pin(async move {
+            Ok(NatsPublisher::publish(self, subject, &headers, payload, errors).await?)
+        })
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), PublishError>> {
+        Box::pin(async move { Ok(NatsPublisher::flush(self).await?) })
+    }
+}
+
+/// Publishes the events to all of the `publishers`, e.g. to NATS and the
+/// Server-Sent Events clients. Returns the first error, but still publishes
+/// to the remaining publishers.
+pub struct FanOutPublisher {
+    publishers: Vec<Box<dyn Publisher>>,
+}
+
+impl FanOutPublisher {
+    pub fn new(publishers: Vec<Box<dyn Publisher>>) -> Self {
+        Self { publishers }
+    }
+}
+
+impl Publisher for FanOutPublisher {
+    fn publish<'a>(
+        &'a self,
+        subject: &'a str,
+        headers: Headers,
+        payload: Vec<u8>,
+        errors: &'a dyn ErrorCounter,
+    ) -> BoxFuture<'a, Result<(), PublishError>> {
+        Box::pin(async move {
+            let results = future::join_all(self.publishers.iter().map(|publisher| {
+                publisher.publish(subject, headers.clone(), payload.clone(), errors)
+            }))
+            .await;
+            results.into_iter().collect()
+        })
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), PublishError>> {
+        Box::pin(async move {
+            let results =
+                future::join_all(self.publishers.iter().map(|publisher| publisher.flush())).await;
+            results.into_iter().collect()
+        })
+    }
+}
+
+/// A record produced to a Kafka topic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KafkaRecord {
+    pub key: String,
+    pub headers: Headers,
+    pub payload: Vec<u8>,
+}
+
+/// Produces records to Kafka topics.
+pub trait KafkaProducer: Send + Sync {
+    fn send<'a>(
+        &'a self,
+        topic: &'a str,
+        record: KafkaRecord,
+    ) -> BoxFuture<'a, Result<(), PublishError>>;
+}
+
+impl<P: KafkaProducer> KafkaProducer for Arc<P> {
+    fn send<'a>(
+        &'a self,
+        topic: &'a str,
+        record: KafkaRecord,
+    ) -> BoxFuture<'a, Result<(), PublishError>> {
+        self.as_ref().send(topic, record)
+    }
+}
+
+/// Publishes the events to a Kafka topic. The NATS subject of an event is
+/// used as record key, so that consumers can still tell the events of
+/// different nodes apart.
+pub struct KafkaPublisher<P> {
+    producer: P,
+    topic: String,
+}
+
+impl<P: KafkaProducer> KafkaPublisher<P> {
+    pub fn new(producer: P, topic: String) -> Self {
+        Self { producer, topic }
+    }
+}
+
+impl<P: KafkaProducer> Publisher for KafkaPublisher<P> {
+    fn publish<'a>(
+        &'a self,
+        subject: &'a str,
+        headers: Headers,
+        payload: Vec<u8>,
+        errors: &'a dyn ErrorCounter,
+    ) -> BoxFuture<'a, Result<(), PublishError>> {
+        let record = KafkaRecord {
+            key: subject.to_string(),
+            headers,
+            payload,
+        };
+        Box::pin(nats_publisher::counted(
+            errors,
+            self.producer.send(&self.topic, record),
+        ))
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), PublishError>> {
+        // Records are sent to the broker before send() returns.
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// A Kafka producer writing to partition 0 of the topics.
+pub struct RsKafkaProducer {
+    client: rskafka::client::Client,
+    partitions: Mutex<HashMap<String, Arc<rskafka::client::partition::PartitionClient>>>,
+}
+
+impl RsKafkaProducer {
+    /// Connects to the Kafka cluster via the bootstrap `brokers`.
+    pub async fn connect(brokers: Vec<String>) -> Result<Self, PublishError> {
+        let client = rskafka::client::ClientBuilder::new(brokers)
+            .build()
+            .await
+            .map_err(|e| PublishError::Kafka(e.to_string()))?;
+        Ok(Self {
+            client,
+            partitions: Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn partition(
+        &self,
+        topic: &str,
+    ) -> Result<Arc<rskafka::client::partition::PartitionClient>, PublishError> {
+        if let Some(partition) = self.partitions.lock().unwrap().get(topic) {
+            return Ok(partition.clone());
+        }
+        let partition = Arc::new(
+            self.client
+                .partition_client(
+                    topic.to_string(),
+                    0,
+                    rskafka::client::partition::UnknownTopicHandling::Retry,
+                )
+                .await
+                .map_err(|e| PublishError::Kafka(e.to_string()))?,
+        );
+        log::debug!("Created Kafka partition client for topic '{}'", topic);
+        self.partitions
+            .lock()
+            .unwrap()
+            .insert(topic.to_string(), partition.clone());
+        Ok(partition)
+    }
+}
+
+impl KafkaProducer for RsKafkaProducer {
+    fn send<'a>(
+        &'a self,
+        topic: &'a str,
+        record: KafkaRecord,
+    ) -> BoxFuture<'a, Result<(), PublishError>> {
+        Box::pin(async move {
+            let headers: BTreeMap<String, Vec<u8>> = record
+                .headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.as_bytes().to_vec()))
+                .collect();
+            let record = rskafka::record::Record {
+                key: Some(record.key.into_bytes()),
+                value: Some(record.payload),
+                headers,
+                timestamp: chrono::Utc::now(),
+            };
+            self.partition(topic)
+                .await?
+                .produce(
+                    vec![record],
+                    rskafka::client::partition::Compression::NoCompression,
+                )
+                .await
+                .map_err(|e| PublishError::Kafka(e.to_string()))?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::prometheus::IntCounter;
+    use shared::tokio;
+
+    /// Records the produced records instead of sending them to Kafka.
+    #[derive(Default)]
+    struct MockProducer {
+        sent: Mutex<Vec<(String, KafkaRecord)>>,
+    }
+
+    impl KafkaProducer for MockProducer {
+        fn send<'a>(
+            &'a self,
+            topic: &'a str,
+            record: KafkaRecord,
+        ) -> BoxFuture<'a, Result<(), PublishError>> {
+            self.sent.lock().unwrap().push((topic.to_string(), record));
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    /// Fails to produce any record.
+    struct FailingProducer;
+
+    impl KafkaProducer for FailingProducer {
+        fn send<'a>(
+            &'a self,
+            _topic: &'a str,
+            _record: KafkaRecord,
+        ) -> BoxFuture<'a, Result<(), PublishError>> {
+            Box::pin(async { Err(PublishError::Kafka("broker down".to_string())) })
+        }
+    }
+
+    fn errors() -> IntCounter {
+        IntCounter::new("nats_publish_errors", "failed publishes").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fan_out_publisher() {
+        let errors = errors();
+        let first = Arc::new(MockProducer::default());
+        let second = Arc::new(MockProducer::default());
+        let publisher = FanOutPublisher::new(vec![
+            Box::new(KafkaPublisher::new(first.clone(), "a".to_string())),
+            Box::new(KafkaPublisher::new(second.clone(), "b".to_string())),
+        ]);
+        publisher
+            .publish("rpc", Headers::new(), vec![1], &errors)
+            .await
+            .unwrap();
+        publisher.flush().await.unwrap();
+        assert_eq!(errors.get(), 0);
+
+        for (producer, topic) in [(first, "a"), (second, "b")] {
+            let sent = producer.sent.lock().unwrap();
+            assert_eq!(sent.len(), 1);
+            assert_eq!(sent[0].0, topic);
+            assert_eq!(sent[0].1.payload, vec![1]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_kafka_publisher() {
+        let errors = errors();
+        let publisher = KafkaPublisher::new(MockProducer::default(), "peer-observer".to_string());
+        let mut headers = Headers::new();
+        headers.insert("Content-Type", "application/protobuf");
+
+        publisher
+            .publish("rpc.node1", headers.clone(), vec![1, 2, 3], &errors)
+            .await
+            .unwrap();
+        publisher
+            .publish("rpc", Headers::new(), vec![4], &errors)
+            .await
+            .unwrap();
+        publisher.flush().await.unwrap();
+        assert_eq!(errors.get(), 0);
+
+        let sent = publisher.producer.sent.lock().unwrap();
+        assert_eq!(
+            *sent,
+            vec![
+                (
+                    "peer-observer".to_string(),
+                    KafkaRecord {
+                        key: "rpc.node1".to_string(),
+                        headers,
+                        payload: vec![1, 2, 3],
+                    }
+                ),
+                (
+                    "peer-observer".to_string(),
+                    KafkaRecord {
+                        key: "rpc".to_string(),
+                        headers: Headers::new(),
+                        payload: vec![4],
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_kafka_publish_errors_are_counted() {
+        let errors = errors();
+        let publisher = KafkaPublisher::new(FailingProducer, "peer-observer".to_string());
+        assert!(
+            publisher
+                .publish("rpc", Headers::new(), vec![1], &errors)
+                .await
+                .is_err()
+        );
+        assert_eq!(errors.get(), 1);
+    }
+}
@@ -0,0 +1,74 @@
+use shared::log;
+use shared::prometheus::{self, Registry};
+use shared::tokio::sync::watch;
+use shared::tokio::task;
+use shared::tokio::time::{self, Duration};
+use std::collections::HashMap;
+
+/// The job label used if no node identifier is configured.
+const DEFAULT_JOB: &str = "rpc-extractor";
+
+/// Returns the Pushgateway job label for the extractor of the node with the
+/// `node_id`, so that extractors of different nodes don't replace each
+/// other's metrics on the Pushgateway.
+pub(crate) fn job(node_id: Option<&str>) -> String {
+    match node_id {
+        Some(node_id) => format!("{}-{}", DEFAULT_JOB, node_id),
+        None => DEFAULT_JOB.to_string(),
+    }
+}
+
+/// Pushes the metrics in the `registry` to the Pushgateway at `url` every
+/// `interval` until the extractor is shut down, and once more when shutting
+/// down. Each push replaces the metrics of the `job` on the Pushgateway.
+pub(crate) async fn push_periodically(
+    url: String,
+    job: String,
+    registry: Registry,
+    interval: Duration,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    log::info!(
+        "Pushing metrics to the Pushgateway at {} as job '{}' every {:?}",
+        url,
+        job,
+        interval
+    );
+    let mut interval = time::interval(interval);
+    loop {
+        shared::tokio::select! {
+            _ = interval.tick() => push(&url, &job, &registry).await,
+            res = shutdown_rx.changed() => {
+                if res.is_err() || *shutdown_rx.borrow() {
+                    push(&url, &job, &registry).await;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn push(url: &str, job: &str, registry: &Registry) {
+    let families = registry.gather();
+    let (push_url, push_job) = (url.to_string(), job.to_string());
+    // The push client is blocking.
+    let handle = task::spawn_blocking(move || {
+        prometheus::push_metrics(&push_job, HashMap::new(), &push_url, families, None)
+    });
+    match handle.await {
+        Ok(Ok(())) => log::debug!("Pushed the metrics to {}", url),
+        Ok(Err(e)) => log::warn!("Could not push the metrics to {}: {}", url, e),
+        Err(e) => log::warn!("Could not push the metrics to {}: {}", url, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job() {
+        assert_eq!(job(None), "rpc-extractor");
+        assert_eq!(job(Some("node1")), "rpc-extractor-node1");
+    }
+}
@@ -0,0 +1,90 @@
+use shared::tokio::time::{self, Duration, Instant};
+use std::sync::Mutex;
+
+/// A token bucket limiting the number of RPC calls per second. The bucket
+/// holds up to one second worth of calls. A call that exceeds the limit
+/// reserves the next token and waits for it instead of failing.
+#[derive(Debug)]
+pub struct RateLimiter {
+    calls_per_sec: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    // Can be negative if calls are waiting for a token.
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(calls_per_sec: u32) -> Self {
+        Self {
+            calls_per_sec: calls_per_sec as f64,
+            bucket: Mutex::new(Bucket {
+                tokens: calls_per_sec as f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until a call is allowed. Returns true if the call was throttled,
+    /// i.e. it had to wait.
+    pub async fn acquire(&self) -> bool {
+        let wait = self.reserve(Instant::now());
+        if wait.is_zero() {
+            return false;
+        }
+        time::sleep(wait).await;
+        true
+    }
+
+    /// Takes a token from the bucket and returns how long to wait until the
+    /// token is available at `now`.
+    fn reserve(&self, now: Instant) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * self.calls_per_sec).min(self.calls_per_sec);
+        bucket.last_refill = now;
+        bucket.tokens -= 1.0;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.calls_per_sec)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_burst() {
+        let limiter = RateLimiter::new(2);
+        let now = Instant::now();
+        assert_eq!(limiter.reserve(now), Duration::ZERO);
+        assert_eq!(limiter.reserve(now), Duration::ZERO);
+        // the bucket is empty, so the following calls queue up
+        assert_eq!(limiter.reserve(now), Duration::from_millis(500));
+        assert_eq!(limiter.reserve(now), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn test_reserve_refill() {
+        let limiter = RateLimiter::new(1);
+        let now = Instant::now();
+        assert_eq!(limiter.reserve(now), Duration::ZERO);
+        assert_eq!(limiter.reserve(now), Duration::from_secs(1));
+        // the reserved token is available after one second
+        assert_eq!(
+            limiter.reserve(now + Duration::from_secs(2)),
+            Duration::ZERO
+        );
+        // the bucket doesn't fill up beyond one second worth of calls
+        let later = now + Duration::from_secs(60);
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+        assert_eq!(limiter.reserve(later), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,174 @@
+use crate::error::FetchOrPublishError;
+use shared::protobuf::rpc_extractor::{BlockchainInfo, Reorg};
+use shared::serde_json::Value;
+use std::future::Future;
+use std::sync::Mutex;
+
+/// The best block of a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Tip {
+    pub hash: String,
+    pub height: u32,
+}
+
+impl From<&BlockchainInfo> for Tip {
+    fn from(info: &BlockchainInfo) -> Self {
+        Tip {
+            hash: info.bestblockhash.clone(),
+            height: info.blocks,
+        }
+    }
+}
+
+/// The fields of a `getblockheader` result needed to detect reorgs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BlockHeader {
+    pub previous_block_hash: Option<String>,
+    // -1 if the block isn't in the active chain
+    pub confirmations: i64,
+}
+
+impl BlockHeader {
+    /// Parses the verbose `getblockheader` result.
+    pub(crate) fn from_json(header: &Value) -> Result<Self, FetchOrPublishError> {
+        let confirmations = header["confirmations"].as_i64().ok_or_else(|| {
+            FetchOrPublishError::Batch("getblockheader result without confirmations".to_string())
+        })?;
+        Ok(BlockHeader {
+            previous_block_hash: header["previousblockhash"].as_str().map(str::to_string),
+            confirmations,
+        })
+    }
+}
+
+/// Remembers the best block of a node as last seen in `getblockchaininfo`.
+#[derive(Debug, Default)]
+pub(crate) struct TipTracker {
+    tip: Mutex<Option<Tip>>,
+}
+
+impl TipTracker {
+    /// Records the best block of the `info`. Returns the previous best block
+    /// if it changed.
+    pub(crate) fn update(&self, info: &BlockchainInfo) -> Option<Tip> {
+        let current = Tip::from(info);
+        let mut tip = self.tip.lock().unwrap();
+        match tip.replace(current.clone()) {
+            Some(previous) if previous != current => Some(previous),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the reorg from the `previous` to the `current` best block, or
+/// `None` if the current best block extends the previous one. The headers of
+/// the blocks are requested with `header`. The previous best block is only
+/// looked up if it isn't the parent of the current one, as the node might
+/// have connected multiple blocks since.
+pub(crate) async fn detect<F, Fut>(
+    previous: &Tip,
+    current: &Tip,
+    header: F,
+) -> Result<Option<Reorg>, FetchOrPublishError>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<BlockHeader, FetchOrPublishError>>,
+{
+    let current_header = header(current.hash.clone()).await?;
+    if current_header.previous_block_hash.as_deref() == Some(previous.hash.as_str()) {
+        return Ok(None);
+    }
+    if header(previous.hash.clone()).await?.confirmations >= 0 {
+        return Ok(None);
+    }
+    Ok(Some(Reorg {
+        old_best_block_hash: previous.hash.clone(),
+        old_height: previous.height,
+        new_best_block_hash: current.hash.clone(),
+        new_height: current.height,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::serde_json::json;
+    use shared::tokio;
+    use std::collections::HashMap;
+
+    fn tip(hash: &str, height: u32) -> Tip {
+        Tip {
+            hash: hash.to_string(),
+            height,
+        }
+    }
+
+    fn header(previous: &str, confirmations: i64) -> BlockHeader {
+        BlockHeader {
+            previous_block_hash: Some(previous.to_string()),
+            confirmations,
+        }
+    }
+
+    // The active chain is a <- b2 <- c2. The block b was reorged out.
+    async fn detect_in_chain(previous: &Tip, current: &Tip) -> Option<Reorg> {
+        let headers = HashMap::from([
+            ("b", header("a", -1)),
+            ("b2", header("a", 2)),
+            ("c2", header("b2", 1)),
+        ]);
+        detect(previous, current, |hash| {
+            let header = headers.get(hash.as_str()).cloned();
+            async move {
+                header.ok_or_else(|| FetchOrPublishError::Batch("unknown block".to_string()))
+            }
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_detect() {
+        assert_eq!(detect_in_chain(&tip("b2", 2), &tip("c2", 3)).await, None);
+        assert_eq!(detect_in_chain(&tip("a", 1), &tip("c2", 3)).await, None);
+        assert_eq!(
+            detect_in_chain(&tip("b", 2), &tip("c2", 3)).await,
+            Some(Reorg {
+                old_best_block_hash: "b".to_string(),
+                old_height: 2,
+                new_best_block_hash: "c2".to_string(),
+                new_height: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn test_tip_tracker() {
+        let info = |hash: &str, blocks: u32| BlockchainInfo {
+            bestblockhash: hash.to_string(),
+            blocks,
+            ..Default::default()
+        };
+        let tracker = TipTracker::default();
+        assert_eq!(tracker.update(&info("a", 1)), None);
+        assert_eq!(tracker.update(&info("a", 1)), None);
+        assert_eq!(tracker.update(&info("b", 2)), Some(tip("a", 1)));
+    }
+
+    #[test]
+    fn test_block_header_from_json() {
+        assert_eq!(
+            BlockHeader::from_json(&json!({"confirmations": -1, "previousblockhash": "a"}))
+                .unwrap(),
+            header("a", -1)
+        );
+        assert_eq!(
+            BlockHeader::from_json(&json!({"confirmations": 10})).unwrap(),
+            BlockHeader {
+                previous_block_hash: None,
+                confirmations: 10,
+            }
+        );
+        assert!(BlockHeader::from_json(&json!({})).is_err());
+    }
+}
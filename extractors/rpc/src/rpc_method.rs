@@ -0,0 +1,128 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// An RPC method queried by the rpc-extractor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RpcMethod {
+    GetPeerInfo,
+    GetMempoolInfo,
+    Uptime,
+    GetNetTotals,
+    GetMemoryInfo,
+    GetAddrmanInfo,
+    GetChainTxStats,
+    GetNetworkInfo,
+    GetBlockchainInfo,
+    GetBlockCount,
+    GetMiningInfo,
+    GetRawMempool,
+    GetChainTips,
+    GetNodeAddresses,
+    ListBanned,
+    EstimateSmartFee,
+    GetIndexInfo,
+    GetDeploymentInfo,
+    GetTxOutSetInfo,
+}
+
+impl RpcMethod {
+    /// All RPC methods the extractor knows about, in the order they are
+    /// queried.
+    pub const ALL: [RpcMethod; 19] = [
+        RpcMethod::GetPeerInfo,
+        RpcMethod::GetMempoolInfo,
+        RpcMethod::Uptime,
+        RpcMethod::GetNetTotals,
+        RpcMethod::GetMemoryInfo,
+        RpcMethod::GetAddrmanInfo,
+        RpcMethod::GetNetworkInfo,
+        RpcMethod::GetChainTxStats,
+        RpcMethod::GetBlockchainInfo,
+        RpcMethod::GetBlockCount,
+        RpcMethod::GetMiningInfo,
+        RpcMethod::GetRawMempool,
+        RpcMethod::GetChainTips,
+        RpcMethod::GetNodeAddresses,
+        RpcMethod::ListBanned,
+        RpcMethod::EstimateSmartFee,
+        RpcMethod::GetIndexInfo,
+        RpcMethod::GetDeploymentInfo,
+        RpcMethod::GetTxOutSetInfo,
+    ];
+
+    /// The name of the method as used by the Bitcoin Core RPC interface.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RpcMethod::GetPeerInfo => "getpeerinfo",
+            RpcMethod::GetMempoolInfo => "getmempoolinfo",
+            RpcMethod::Uptime => "uptime",
+            RpcMethod::GetNetTotals => "getnettotals",
+            RpcMethod::GetMemoryInfo => "getmemoryinfo",
+            RpcMethod::GetAddrmanInfo => "getaddrmaninfo",
+            RpcMethod::GetChainTxStats => "getchaintxstats",
+            RpcMethod::GetNetworkInfo => "getnetworkinfo",
+            RpcMethod::GetBlockchainInfo => "getblockchaininfo",
+            RpcMethod::GetBlockCount => "getblockcount",
+            RpcMethod::GetMiningInfo => "getmininginfo",
+            RpcMethod::GetRawMempool => "getrawmempool",
+            RpcMethod::GetChainTips => "getchaintips",
+            RpcMethod::GetNodeAddresses => "getnodeaddresses",
+            RpcMethod::ListBanned => "listbanned",
+            RpcMethod::EstimateSmartFee => "estimatesmartfee",
+            RpcMethod::GetIndexInfo => "getindexinfo",
+            RpcMethod::GetDeploymentInfo => "getdeploymentinfo",
+            RpcMethod::GetTxOutSetInfo => "gettxoutsetinfo",
+        }
+    }
+
+    /// The interval (in seconds) the method is queried in, if no per-method
+    /// interval is configured. Queries that can be run less frequently use a
+    /// multiple of the global query interval.
+    pub fn default_interval(&self, query_interval: u64) -> u64 {
+        match self {
+            RpcMethod::GetChainTxStats
+            | RpcMethod::GetBlockchainInfo
+            | RpcMethod::GetChainTips
+            | RpcMethod::ListBanned
+            | RpcMethod::GetDeploymentInfo => query_interval * 60,
+            _ => query_interval,
+        }
+    }
+}
+
+impl fmt::Display for RpcMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl serde::Serialize for RpcMethod {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl FromStr for RpcMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RpcMethod::ALL
+            .iter()
+            .find(|method| method.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown RPC method '{}'", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rpc_method_roundtrip() {
+        for method in RpcMethod::ALL {
+            assert_eq!(method.as_str().parse::<RpcMethod>(), Ok(method));
+        }
+        assert!("getfoo".parse::<RpcMethod>().is_err());
+    }
+}
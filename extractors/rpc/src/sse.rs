@@ -0,0 +1,116 @@
+use crate::error::PublishError;
+use crate::file_sink::json_line;
+use crate::publisher::{Headers, Publisher};
+use shared::futures::future::BoxFuture;
+use shared::log;
+use shared::metricserver::EventStream;
+use shared::nats_publisher::ErrorCounter;
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+
+// Number of events buffered per client. Events for a client that doesn't keep
+// up are dropped instead of blocking the extractor.
+const CLIENT_BUFFER: usize = 1024;
+
+/// Forwards the published events to the clients connected to the
+/// Server-Sent Events endpoint. Each event is sent as the same JSON line the
+/// file output writes.
+#[derive(Clone, Default)]
+pub struct SsePublisher {
+    clients: Arc<Mutex<Vec<SyncSender<String>>>>,
+}
+
+impl SsePublisher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn subscribe(&self) -> mpsc::Receiver<String> {
+        let (tx, rx) = mpsc::sync_channel(CLIENT_BUFFER);
+        self.clients.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// The event stream to serve on the `path` of the metrics server.
+    pub fn event_stream(&self, path: &str) -> EventStream {
+        let publisher = self.clone();
+        EventStream::new(path, move || publisher.subscribe())
+    }
+}
+
+impl Publisher for SsePublisher {
+    fn publish<'a>(
+        &'a self,
+        subject: &'a str,
+        headers: Headers,
+        payload: Vec<u8>,
+        _errors: &'a dyn ErrorCounter,
+    ) -> BoxFuture<'a, Result<(), PublishError>> {
+        Box::pin(async move {
+            let mut clients = self.clients.lock().unwrap();
+            if clients.is_empty() {
+                return Ok(());
+            }
+            let line = String::from_utf8(json_line(subject, &headers, &payload)?)
+                .expect("serde_json produces UTF-8");
+            clients.retain(|client| match client.try_send(line.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    log::warn!("Dropped an event for a slow Server-Sent Events client");
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+            Ok(())
+        })
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), PublishError>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::prometheus::IntCounter;
+    use shared::prost::Message;
+    use shared::protobuf::event::{Event, event::PeerObserverEvent};
+    use shared::protobuf::rpc_extractor::{self, rpc::RpcEvent};
+    use shared::serde_json::{self, Value};
+    use shared::tokio;
+
+    #[tokio::test]
+    async fn test_sse_publisher() {
+        let errors = IntCounter::new("errors", "errors").unwrap();
+        let publisher = SsePublisher::new();
+        let event = Event::new(PeerObserverEvent::RpcExtractor(rpc_extractor::Rpc {
+            rpc_event: Some(RpcEvent::Uptime(7)),
+        }))
+        .unwrap();
+
+        // without clients, nothing is sent
+        publisher
+            .publish("rpc", Headers::new(), event.encode_to_vec(), &errors)
+            .await
+            .unwrap();
+
+        let first = (publisher.event_stream("/events").subscribe)();
+        let second = (publisher.event_stream("/events").subscribe)();
+        drop(second);
+        publisher
+            .publish("rpc", Headers::new(), event.encode_to_vec(), &errors)
+            .await
+            .unwrap();
+
+        let line: Value = serde_json::from_str(&first.try_recv().unwrap()).unwrap();
+        assert_eq!(line["subject"], "rpc");
+        assert_eq!(
+            serde_json::from_value::<Event>(line["event"].clone()).unwrap(),
+            event
+        );
+        assert!(first.try_recv().is_err());
+        // the disconnected client was removed
+        assert_eq!(publisher.clients.lock().unwrap().len(), 1);
+    }
+}
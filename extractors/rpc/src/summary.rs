@@ -0,0 +1,204 @@
+use shared::prometheus::core::{Collector, Desc};
+use shared::prometheus::proto::{LabelPair, Metric, MetricFamily, MetricType, Quantile, Summary};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// The quantiles are calculated over the observations of this time window.
+const MAX_AGE: Duration = Duration::from_secs(600);
+// At most this many observations per label value are kept for the window.
+const MAX_WINDOW_OBSERVATIONS: usize = 10_000;
+
+/// A Prometheus summary partitioned by a single label. The prometheus crate
+/// doesn't implement summaries, so this is a custom collector. The quantiles
+/// are calculated over the observations of the last ten minutes, while the
+/// count and sum include all observations, as in the other Prometheus client
+/// libraries.
+#[derive(Debug, Clone)]
+pub struct SummaryVec {
+    desc: Desc,
+    quantiles: Vec<f64>,
+    label_name: String,
+    summaries: Arc<Mutex<BTreeMap<String, Observations>>>,
+}
+
+#[derive(Debug, Default)]
+struct Observations {
+    count: u64,
+    sum: f64,
+    window: VecDeque<(Instant, f64)>,
+}
+
+impl SummaryVec {
+    /// Creates a summary `name` with the `quantiles` (between 0 and 1),
+    /// which is partitioned by the `label_name`.
+    pub fn new(
+        name: &str,
+        help: &str,
+        label_name: &str,
+        quantiles: &[f64],
+        const_labels: HashMap<String, String>,
+    ) -> shared::prometheus::Result<Self> {
+        let desc = Desc::new(
+            name.to_string(),
+            help.to_string(),
+            vec![label_name.to_string()],
+            const_labels,
+        )?;
+        Ok(Self {
+            desc,
+            quantiles: quantiles.to_vec(),
+            label_name: label_name.to_string(),
+            summaries: Arc::new(Mutex::new(BTreeMap::new())),
+        })
+    }
+
+    /// Records the `value` for the `label_value`.
+    pub fn observe(&self, label_value: &str, value: f64) {
+        let now = Instant::now();
+        let mut summaries = self.summaries.lock().unwrap();
+        let observations = summaries.entry(label_value.to_string()).or_default();
+        observations.count += 1;
+        observations.sum += value;
+        observations.window.push_back((now, value));
+        observations.expire(now);
+        if observations.window.len() > MAX_WINDOW_OBSERVATIONS {
+            observations.window.pop_front();
+        }
+    }
+
+    /// Returns the number of observations for the `label_value`.
+    pub fn sample_count(&self, label_value: &str) -> u64 {
+        self.summaries
+            .lock()
+            .unwrap()
+            .get(label_value)
+            .map(|observations| observations.count)
+            .unwrap_or_default()
+    }
+
+    fn metric(&self, label_value: &str, observations: &mut Observations) -> Metric {
+        observations.expire(Instant::now());
+        let mut values: Vec<f64> = observations.window.iter().map(|(_, v)| *v).collect();
+        values.sort_by(f64::total_cmp);
+
+        let mut summary = Summary::new();
+        summary.set_sample_count(observations.count);
+        summary.set_sample_sum(observations.sum);
+        summary.quantile = self
+            .quantiles
+            .iter()
+            .map(|q| {
+                let mut quantile = Quantile::new();
+                quantile.set_quantile(*q);
+                quantile.set_value(nearest_rank(&values, *q));
+                quantile
+            })
+            .collect();
+
+        let mut label = LabelPair::new();
+        label.set_name(self.label_name.clone());
+        label.set_value(label_value.to_string());
+        let mut labels = self.desc.const_label_pairs.clone();
+        labels.push(label);
+        labels.sort_by(|a, b| a.name().cmp(b.name()));
+        let mut metric = Metric::new();
+        metric.label = labels;
+        metric.summary = Some(summary).into();
+        metric
+    }
+}
+
+impl Observations {
+    fn expire(&mut self, now: Instant) {
+        while let Some((observed, _)) = self.window.front() {
+            if now.duration_since(*observed) <= MAX_AGE {
+                break;
+            }
+            self.window.pop_front();
+        }
+    }
+}
+
+impl Collector for SummaryVec {
+    fn desc(&self) -> Vec<&Desc> {
+        vec![&self.desc]
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let mut summaries = self.summaries.lock().unwrap();
+        let mut family = MetricFamily::new();
+        family.set_name(self.desc.fq_name.clone());
+        family.set_help(self.desc.help.clone());
+        family.set_type(MetricType::SUMMARY);
+        family.metric = summaries
+            .iter_mut()
+            .map(|(label_value, observations)| self.metric(label_value, observations))
+            .collect();
+        vec![family]
+    }
+}
+
+/// Returns the `quantile` of the sorted `values` with the nearest-rank
+/// method, or NaN if there are no values.
+fn nearest_rank(values: &[f64], quantile: f64) -> f64 {
+    if values.is_empty() {
+        return f64::NAN;
+    }
+    let rank = (quantile * values.len() as f64).ceil() as usize;
+    values[rank.clamp(1, values.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nearest_rank() {
+        let values: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert_eq!(nearest_rank(&values, 0.0), 1.0);
+        assert_eq!(nearest_rank(&values, 0.5), 5.0);
+        assert_eq!(nearest_rank(&values, 0.9), 9.0);
+        assert_eq!(nearest_rank(&values, 0.99), 10.0);
+        assert_eq!(nearest_rank(&values, 1.0), 10.0);
+        assert!(nearest_rank(&[], 0.5).is_nan());
+    }
+
+    #[test]
+    fn test_summary_vec_collect() {
+        let summary = SummaryVec::new(
+            "duration_seconds",
+            "A duration.",
+            "rpc_method",
+            &[0.5, 0.9],
+            HashMap::from([("node_id".to_string(), "node1".to_string())]),
+        )
+        .unwrap();
+        for value in [0.1, 0.2, 0.3, 0.4] {
+            summary.observe("uptime", value);
+        }
+        assert_eq!(summary.sample_count("uptime"), 4);
+        assert_eq!(summary.sample_count("getpeerinfo"), 0);
+
+        let families = summary.collect();
+        assert_eq!(families.len(), 1);
+        assert_eq!(families[0].name(), "duration_seconds");
+        assert_eq!(families[0].type_(), MetricType::SUMMARY);
+        let metric = &families[0].metric[0];
+        let labels: Vec<(&str, &str)> = metric
+            .label
+            .iter()
+            .map(|label| (label.name(), label.value()))
+            .collect();
+        assert_eq!(labels, vec![("node_id", "node1"), ("rpc_method", "uptime")]);
+        assert_eq!(metric.summary.sample_count(), 4);
+        assert!((metric.summary.sample_sum() - 1.0).abs() < 1e-9);
+        let quantiles: Vec<(f64, f64)> = metric
+            .summary
+            .quantile
+            .iter()
+            .map(|q| (q.quantile(), q.value()))
+            .collect();
+        assert_eq!(quantiles, vec![(0.5, 0.2), (0.9, 0.4)]);
+    }
+}
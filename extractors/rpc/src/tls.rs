@@ -0,0 +1,107 @@
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{CryptoProvider, ring};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use shared::log;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::sync::Arc;
+
+/// Builds the TLS client configuration for `https://` RPC hosts. The server
+/// certificate is verified against the certificates in the `ca_file` or, if
+/// none is given, the system's trusted certificates. With `insecure`, the
+/// certificate isn't verified at all.
+pub(crate) fn client_config(
+    ca_file: Option<&str>,
+    insecure: bool,
+) -> io::Result<Arc<ClientConfig>> {
+    let provider = Arc::new(ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?;
+
+    let config = if insecure {
+        log::warn!("The TLS certificate of the RPC host isn't verified (--rpc-insecure).");
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoVerification(provider)))
+            .with_no_client_auth()
+    } else {
+        builder
+            .with_root_certificates(root_certificates(ca_file)?)
+            .with_no_client_auth()
+    };
+    Ok(Arc::new(config))
+}
+
+fn root_certificates(ca_file: Option<&str>) -> io::Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    let certificates = match ca_file {
+        Some(path) => {
+            let certificates = rustls_pemfile::certs(&mut BufReader::new(File::open(path)?))
+                .collect::<Result<Vec<_>, _>>()?;
+            if certificates.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("no certificates found in the CA file '{}'", path),
+                ));
+            }
+            certificates
+        }
+        None => rustls_native_certs::load_native_certs()?,
+    };
+    let (_, ignored) = roots.add_parsable_certificates(certificates);
+    if ignored > 0 {
+        log::warn!("Ignored {} invalid CA certificates.", ignored);
+    }
+    Ok(roots)
+}
+
+/// Accepts any server certificate. Only the handshake signatures are checked.
+#[derive(Debug)]
+struct NoVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
@@ -0,0 +1,53 @@
+use shared::async_nats::ConnectErrorKind;
+use std::error;
+use std::fmt;
+use std::io;
+use zeromq::ZmqError;
+
+#[derive(Debug)]
+pub enum RuntimeError {
+    Io(io::Error),
+    NatsConnect(shared::async_nats::error::Error<ConnectErrorKind>),
+    Zmq(ZmqError),
+    NoTopics,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeError::Io(e) => write!(f, "IO error {}", e),
+            RuntimeError::NatsConnect(e) => write!(f, "NATS connection error {}", e),
+            RuntimeError::Zmq(e) => write!(f, "ZMQ error {}", e),
+            RuntimeError::NoTopics => write!(f, "all ZMQ topics are disabled"),
+        }
+    }
+}
+
+impl error::Error for RuntimeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            RuntimeError::Io(ref e) => Some(e),
+            RuntimeError::NatsConnect(ref e) => Some(e),
+            RuntimeError::Zmq(ref e) => Some(e),
+            RuntimeError::NoTopics => None,
+        }
+    }
+}
+
+impl From<io::Error> for RuntimeError {
+    fn from(e: io::Error) -> Self {
+        RuntimeError::Io(e)
+    }
+}
+
+impl From<shared::async_nats::error::Error<ConnectErrorKind>> for RuntimeError {
+    fn from(e: shared::async_nats::error::Error<ConnectErrorKind>) -> Self {
+        RuntimeError::NatsConnect(e)
+    }
+}
+
+impl From<ZmqError> for RuntimeError {
+    fn from(e: ZmqError) -> Self {
+        RuntimeError::Zmq(e)
+    }
+}
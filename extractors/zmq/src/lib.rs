@@ -0,0 +1,320 @@
+#![cfg_attr(feature = "strict", deny(warnings))]
+
+use error::RuntimeError;
+use shared::clap::Parser;
+use shared::log;
+use shared::metricserver;
+use shared::nats_publisher::{Headers, NatsPublisher};
+use shared::nats_subjects::Subject;
+use shared::nats_util::{self, NatsArgs};
+use shared::prost::Message;
+use shared::protobuf::event::Event;
+use shared::protobuf::event::event::PeerObserverEvent;
+use shared::protobuf::zmq_extractor::{self, HashBlock, HashTx, RawBlock, RawTx, zmq::ZmqEvent};
+use shared::tokio::{self, sync::watch};
+use std::fmt;
+use zeromq::{Socket, SocketRecv, SubSocket};
+
+mod error;
+mod metrics;
+
+pub use metrics::Metrics;
+
+pub const DEFAULT_ZMQ_ENDPOINT: &str = "tcp://127.0.0.1:28332";
+
+/// A ZMQ notification topic published by Bitcoin Core.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topic {
+    RawTx,
+    RawBlock,
+    HashBlock,
+    HashTx,
+}
+
+impl Topic {
+    pub const ALL: [Topic; 4] = [
+        Topic::RawTx,
+        Topic::RawBlock,
+        Topic::HashBlock,
+        Topic::HashTx,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Topic::RawTx => "rawtx",
+            Topic::RawBlock => "rawblock",
+            Topic::HashBlock => "hashblock",
+            Topic::HashTx => "hashtx",
+        }
+    }
+
+    fn from_bytes(topic: &[u8]) -> Option<Topic> {
+        Topic::ALL
+            .into_iter()
+            .find(|t| t.as_str().as_bytes() == topic)
+    }
+}
+
+impl fmt::Display for Topic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// The peer-observer zmq-extractor subscribes to the ZMQ notifications of a
+/// Bitcoin Core node and publishes the received transactions, blocks, and
+/// hashes as events into a NATS pub-sub queue.
+#[derive(Parser, Debug, Clone)]
+#[command(version, about, long_about = None)]
+pub struct Args {
+    /// Arguments for the connection to the NATS server.
+    #[command(flatten)]
+    pub nats: nats_util::NatsArgs,
+
+    /// The ZMQ endpoint the node publishes its notifications on (as configured
+    /// with e.g. `-zmqpubrawtx=<endpoint>`). All enabled topics are subscribed
+    /// to on this endpoint.
+    #[arg(long, default_value = DEFAULT_ZMQ_ENDPOINT)]
+    pub zmq_endpoint: String,
+
+    /// Disable subscribing to and publishing of `rawtx` notifications.
+    #[arg(long, default_value_t = false)]
+    pub disable_rawtx: bool,
+
+    /// Disable subscribing to and publishing of `rawblock` notifications.
+    #[arg(long, default_value_t = false)]
+    pub disable_rawblock: bool,
+
+    /// Disable subscribing to and publishing of `hashblock` notifications.
+    #[arg(long, default_value_t = false)]
+    pub disable_hashblock: bool,
+
+    /// Disable subscribing to and publishing of `hashtx` notifications.
+    #[arg(long, default_value_t = false)]
+    pub disable_hashtx: bool,
+
+    /// If set, the extractor serves Prometheus metrics about itself on this
+    /// address (e.g. 127.0.0.1:8284).
+    #[arg(long)]
+    pub metrics_address: Option<String>,
+
+    /// The log level the extractor should run with. Valid log levels are "trace",
+    /// "debug", "info", "warn", "error". See https://docs.rs/log/latest/log/enum.Level.html.
+    #[arg(short, long, default_value_t = log::Level::Debug)]
+    pub log_level: log::Level,
+}
+
+impl Args {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        nats: NatsArgs,
+        zmq_endpoint: String,
+        disable_rawtx: bool,
+        disable_rawblock: bool,
+        disable_hashblock: bool,
+        disable_hashtx: bool,
+        metrics_address: Option<String>,
+        log_level: log::Level,
+    ) -> Args {
+        Self {
+            nats,
+            zmq_endpoint,
+            disable_rawtx,
+            disable_rawblock,
+            disable_hashblock,
+            disable_hashtx,
+            metrics_address,
+            log_level,
+        }
+    }
+
+    /// The topics that aren't disabled.
+    pub fn topics(&self) -> Vec<Topic> {
+        Topic::ALL
+            .into_iter()
+            .filter(|topic| match topic {
+                Topic::RawTx => !self.disable_rawtx,
+                Topic::RawBlock => !self.disable_rawblock,
+                Topic::HashBlock => !self.disable_hashblock,
+                Topic::HashTx => !self.disable_hashtx,
+            })
+            .collect()
+    }
+}
+
+pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(), RuntimeError> {
+    let topics = args.topics();
+    if topics.is_empty() {
+        return Err(RuntimeError::NoTopics);
+    }
+
+    let metrics = Metrics::new();
+    if let Some(address) = &args.metrics_address {
+        metricserver::start(address, Some(metrics.registry.clone()))?;
+    }
+
+    let nats_client = nats_util::prepare_connection(&args.nats)?
+        .connect(nats_util::server_addrs(&args.nats)?)
+        .await?;
+    log::info!("Connected to NATS server at {}", &args.nats.address);
+    let publisher = NatsPublisher::new(nats_client, false, nats_util::pending_buffer(&args.nats));
+
+    let mut socket = SubSocket::new();
+    socket.connect(&args.zmq_endpoint).await?;
+    for topic in topics.iter() {
+        socket.subscribe(topic.as_str()).await?;
+    }
+    log::info!(
+        "Subscribed to the ZMQ topics {:?} on {}",
+        topics.iter().map(Topic::as_str).collect::<Vec<_>>(),
+        &args.zmq_endpoint
+    );
+
+    loop {
+        tokio::select! {
+            message = socket.recv() => {
+                let frames: Vec<Vec<u8>> = message?
+                    .into_vec()
+                    .into_iter()
+                    .map(|frame| frame.to_vec())
+                    .collect();
+                process_message(&publisher, &frames, &metrics).await;
+            },
+            res = shutdown_rx.changed() => {
+                match res {
+                    Ok(_) => {
+                        if *shutdown_rx.borrow() {
+                            log::info!("zmq-extractor received shutdown signal.");
+                            break;
+                        }
+                    }
+                    Err(_) => {
+                        // all senders dropped -> treat as shutdown
+                        log::warn!("The shutdown notification sender was dropped. Shutting down.");
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn process_message(publisher: &NatsPublisher, frames: &[Vec<u8>], metrics: &Metrics) {
+    let (topic, event) = match zmq_event(frames) {
+        Ok(e) => e,
+        Err(e) => {
+            log::warn!("Could not decode ZMQ message: {}", e);
+            return;
+        }
+    };
+    metrics
+        .zmq_messages_received_total
+        .with_label_values(&[topic.as_str()])
+        .inc();
+
+    let zmq = zmq_extractor::Zmq {
+        zmq_event: Some(event),
+    };
+    match Event::new(PeerObserverEvent::ZmqExtractor(zmq)) {
+        Ok(proto) => {
+            if let Err(e) = publisher
+                .publish(
+                    &Subject::ZmqExtractor.to_string(),
+                    &Headers::new(),
+                    proto.encode_to_vec(),
+                    &metrics.publish_counters(topic.as_str()),
+                )
+                .await
+            {
+                log::error!("could not publish {} event into NATS: {}", topic, e);
+            } else {
+                log::trace!("published {} event into NATS: {:?}", topic, proto);
+            }
+        }
+        Err(e) => {
+            log::error!("Could not create new Event due to SystemTimeError: {}", e);
+        }
+    };
+}
+
+/// Decodes a ZMQ notification from the node. The notifications consist of
+/// three frames: the topic, the body, and a 4-byte little-endian sequence
+/// number, which is incremented per topic.
+fn zmq_event(frames: &[Vec<u8>]) -> Result<(Topic, ZmqEvent), String> {
+    let [topic, body, sequence] = frames else {
+        return Err(format!("expected 3 frames, got {}", frames.len()));
+    };
+    let topic = Topic::from_bytes(topic)
+        .ok_or_else(|| format!("unknown topic '{}'", String::from_utf8_lossy(topic)))?;
+    let sequence: [u8; 4] = sequence
+        .as_slice()
+        .try_into()
+        .map_err(|_| format!("expected a 4-byte sequence, got {} bytes", sequence.len()))?;
+    let sequence = u32::from_le_bytes(sequence);
+
+    let body = body.clone();
+    let event = match topic {
+        Topic::RawTx => ZmqEvent::RawTx(RawTx { tx: body, sequence }),
+        Topic::RawBlock => ZmqEvent::RawBlock(RawBlock {
+            block: body,
+            sequence,
+        }),
+        Topic::HashBlock => ZmqEvent::HashBlock(HashBlock {
+            hash: body,
+            sequence,
+        }),
+        Topic::HashTx => ZmqEvent::HashTx(HashTx {
+            txid: body,
+            sequence,
+        }),
+    };
+    Ok((topic, event))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zmq_event() {
+        let frames = vec![b"hashblock".to_vec(), vec![0xab; 32], vec![7, 0, 0, 0]];
+        assert_eq!(
+            zmq_event(&frames).unwrap(),
+            (
+                Topic::HashBlock,
+                ZmqEvent::HashBlock(HashBlock {
+                    hash: vec![0xab; 32],
+                    sequence: 7,
+                })
+            )
+        );
+
+        let frames = vec![b"rawtx".to_vec(), vec![1, 2, 3], vec![0, 1, 0, 0]];
+        assert_eq!(
+            zmq_event(&frames).unwrap(),
+            (
+                Topic::RawTx,
+                ZmqEvent::RawTx(RawTx {
+                    tx: vec![1, 2, 3],
+                    sequence: 256,
+                })
+            )
+        );
+
+        assert!(zmq_event(&frames[..2]).is_err());
+        assert!(zmq_event(&[b"sequence".to_vec(), vec![], vec![0; 4]]).is_err());
+        assert!(zmq_event(&[b"rawtx".to_vec(), vec![], vec![0; 2]]).is_err());
+    }
+
+    #[test]
+    fn test_topics() {
+        let mut args = Args::parse_from(["zmq-extractor"]);
+        assert_eq!(args.topics(), Topic::ALL.to_vec());
+
+        args.disable_rawtx = true;
+        args.disable_hashtx = true;
+        assert_eq!(args.topics(), vec![Topic::RawBlock, Topic::HashBlock]);
+    }
+}
@@ -0,0 +1,29 @@
+use shared::log;
+use shared::tokio::{self, signal, sync::watch};
+use shared::{clap::Parser, simple_logger};
+use zmq_extractor::Args;
+
+#[tokio::main]
+async fn main() {
+    let args = Args::parse();
+
+    if let Err(e) = simple_logger::init_with_level(args.log_level) {
+        eprintln!("zmq extractor error: {}", e);
+    }
+
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let zmq_handle = tokio::spawn(zmq_extractor::run(args, shutdown_rx));
+
+    tokio::select! {
+        _ = signal::ctrl_c() => {
+            log::info!("Received Ctrl+C. Stopping...");
+            let _ = shutdown_tx.send(true);
+        }
+        result = zmq_handle => {
+            match result.unwrap() {
+                Ok(_) => log::info!("zmq-extractor task completed."),
+                Err(e) => log::error!("zmq-extractor task failed: {e}"),
+            }
+        }
+    }
+}
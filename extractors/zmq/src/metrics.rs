@@ -0,0 +1,105 @@
+use shared::nats_publisher::PublishCounters;
+use shared::prometheus::{IntCounterVec, Opts, Registry, register_int_counter_vec_with_registry};
+
+pub const NAMESPACE: &str = "zmqextractor";
+
+pub const LABEL_TOPIC: &str = "topic";
+
+/// Prometheus metrics about the zmq-extractor itself.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    pub registry: Registry,
+
+    pub zmq_messages_received_total: IntCounterVec,
+    pub nats_publish_errors: IntCounterVec,
+    pub nats_dropped_total: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some(NAMESPACE.to_string()), None)
+            .expect("Could not setup prometheus metric registry");
+
+        let zmq_messages_received_total = register_int_counter_vec_with_registry!(
+            Opts::new(
+                "zmq_messages_received_total",
+                "Number of messages received from the node via ZMQ."
+            ),
+            &[LABEL_TOPIC],
+            registry
+        )
+        .expect("Could not create metric 'zmq_messages_received_total'");
+        let nats_publish_errors = register_int_counter_vec_with_registry!(
+            Opts::new(
+                "nats_publish_errors",
+                "Number of events that could not be published to NATS."
+            ),
+            &[LABEL_TOPIC],
+            registry
+        )
+        .expect("Could not create metric 'nats_publish_errors'");
+        let nats_dropped_total = register_int_counter_vec_with_registry!(
+            Opts::new(
+                "nats_dropped_total",
+                "Number of events dropped as the buffer for events published while disconnected from the NATS server was full. These are also counted in nats_publish_errors."
+            ),
+            &[LABEL_TOPIC],
+            registry
+        )
+        .expect("Could not create metric 'nats_dropped_total'");
+
+        Self {
+            registry,
+            zmq_messages_received_total,
+            nats_publish_errors,
+            nats_dropped_total,
+        }
+    }
+
+    /// The counters the NATS publisher increments for the published events of
+    /// the `topic`.
+    pub fn publish_counters(&self, topic: &str) -> PublishCounters {
+        PublishCounters {
+            errors: self.nats_publish_errors.with_label_values(&[topic]),
+            dropped: self.nats_dropped_total.with_label_values(&[topic]),
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::nats_publisher::ErrorCounter;
+    use shared::prometheus::{Encoder, TextEncoder};
+
+    #[test]
+    fn test_metrics_registered() {
+        let metrics = Metrics::new();
+        metrics
+            .zmq_messages_received_total
+            .with_label_values(&["rawtx"])
+            .inc();
+        metrics
+            .nats_publish_errors
+            .with_label_values(&["rawblock"])
+            .inc();
+        metrics.publish_counters("rawtx").inc_dropped();
+
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&metrics.registry.gather(), &mut buffer)
+            .unwrap();
+        let encoded = String::from_utf8(buffer).unwrap();
+        assert!(
+            encoded.contains("\nzmqextractor_zmq_messages_received_total{topic=\"rawtx\"} 1\n")
+        );
+        assert!(encoded.contains("\nzmqextractor_nats_publish_errors{topic=\"rawblock\"} 1\n"));
+        assert!(encoded.contains("\nzmqextractor_nats_dropped_total{topic=\"rawtx\"} 1\n"));
+    }
+}
@@ -0,0 +1,154 @@
+#![cfg(feature = "nats_integration_tests")]
+#![cfg(feature = "node_integration_tests")]
+
+use shared::{
+    async_nats,
+    bitcoin::{self, consensus::Decodable, hashes::Hash},
+    corepc_node,
+    futures::StreamExt,
+    log::{Level, LevelFilter, info},
+    nats_util::NatsArgs,
+    prost::Message,
+    protobuf::{
+        event::{Event, event::PeerObserverEvent},
+        zmq_extractor::zmq::ZmqEvent,
+    },
+    simple_logger::SimpleLogger,
+    testing::nats_server::NatsServerForTesting,
+    tokio::{
+        self,
+        sync::watch,
+        time::{Duration, sleep, timeout},
+    },
+};
+use std::net::TcpListener;
+use std::str::FromStr;
+use std::sync::Once;
+use zmq_extractor::Args;
+
+static INIT: Once = Once::new();
+
+fn setup() {
+    INIT.call_once(|| {
+        SimpleLogger::new()
+            .with_level(LevelFilter::Trace)
+            .init()
+            .unwrap();
+    });
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+fn make_test_args(nats_port: u16, zmq_endpoint: String) -> Args {
+    Args::new(
+        NatsArgs {
+            address: format!("127.0.0.1:{}", nats_port),
+            ..Default::default()
+        },
+        zmq_endpoint,
+        false,
+        false,
+        false,
+        false,
+        None,
+        Level::Trace,
+    )
+}
+
+fn setup_node(conf: corepc_node::Conf) -> corepc_node::Node {
+    info!("env BITCOIND_EXE={:?}", std::env::var("BITCOIND_EXE"));
+    info!("exe_path={:?}", corepc_node::exe_path());
+
+    if let Ok(exe_path) = corepc_node::exe_path() {
+        info!("Using bitcoind at '{}'", exe_path);
+        return corepc_node::Node::with_conf(exe_path, &conf).unwrap();
+    }
+
+    info!("Trying to download a bitcoind..");
+    corepc_node::Node::from_downloaded_with_conf(&conf).unwrap()
+}
+
+#[tokio::test]
+async fn test_integration_zmqextractor_block_events() {
+    println!("test that we receive the ZMQ notifications of a mined block");
+    setup();
+
+    let zmq_endpoint = format!("tcp://127.0.0.1:{}", free_port());
+    let zmq_args: Vec<String> = ["rawtx", "rawblock", "hashblock", "hashtx"]
+        .iter()
+        .map(|topic| format!("-zmqpub{}={}", topic, zmq_endpoint))
+        .collect();
+    let mut conf = corepc_node::Conf::default();
+    conf.args.extend(zmq_args.iter().map(String::as_str));
+    let node = setup_node(conf);
+    let nats_server = NatsServerForTesting::new(&[]).await;
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+    let nc = async_nats::connect(format!("127.0.0.1:{}", nats_server.port))
+        .await
+        .unwrap();
+    let mut sub = nc.subscribe("zmq-extractor").await.unwrap();
+
+    let args = make_test_args(nats_server.port, zmq_endpoint);
+    let zmq_extractor_handle = tokio::spawn(async move {
+        zmq_extractor::run(args, shutdown_rx)
+            .await
+            .expect("zmq extractor failed");
+    });
+
+    // Give the extractor time to subscribe. Notifications published before
+    // the subscription is established are dropped by ZMQ.
+    sleep(Duration::from_secs(1)).await;
+
+    let address = bitcoin::Address::from_str("bcrt1qs758ursh4q9z627kt3pp5yysm78ddny6txaqgw")
+        .unwrap()
+        .require_network(bitcoin::Network::Regtest)
+        .unwrap();
+    let block_hash = node.client.generate_to_address(1, &address).unwrap().0[0].clone();
+    let block_hash = bitcoin::BlockHash::from_str(&block_hash).unwrap();
+
+    let (mut raw_tx, mut raw_block, mut hash_block, mut hash_tx) = (false, false, false, false);
+    timeout(Duration::from_secs(10), async {
+        while !(raw_tx && raw_block && hash_block && hash_tx) {
+            let msg = sub.next().await.unwrap();
+            let event = Event::decode(msg.payload).unwrap();
+            let Some(PeerObserverEvent::ZmqExtractor(zmq)) = event.peer_observer_event else {
+                panic!("unexpected event {:?}", event);
+            };
+            match zmq.zmq_event.unwrap() {
+                ZmqEvent::RawTx(tx) => {
+                    let tx = bitcoin::Transaction::consensus_decode(&mut tx.tx.as_slice()).unwrap();
+                    assert!(tx.is_coinbase());
+                    raw_tx = true;
+                }
+                ZmqEvent::RawBlock(block) => {
+                    let block =
+                        bitcoin::Block::consensus_decode(&mut block.block.as_slice()).unwrap();
+                    assert_eq!(block.block_hash(), block_hash);
+                    raw_block = true;
+                }
+                ZmqEvent::HashBlock(hash) => {
+                    let mut expected = block_hash.to_byte_array();
+                    expected.reverse();
+                    assert_eq!(hash.hash, expected.to_vec());
+                    hash_block = true;
+                }
+                ZmqEvent::HashTx(txid) => {
+                    assert_eq!(txid.txid.len(), 32);
+                    hash_tx = true;
+                }
+            }
+        }
+    })
+    .await
+    .expect("expected rawtx, rawblock, hashblock, and hashtx events");
+
+    shutdown_tx.send(true).unwrap();
+    zmq_extractor_handle.await.unwrap();
+}
@@ -0,0 +1,336 @@
+use std::fs;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+const DNS_PORT: u16 = 53;
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+// The DNS record type and class of an SRV record in the Internet class.
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+// A UDP DNS message is at most 512 bytes unless EDNS is used. Some servers
+// send larger responses anyway.
+const MAX_MESSAGE_SIZE: usize = 4096;
+
+/// A target of an SRV record: a host and port a service is available on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvTarget {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub host: String,
+}
+
+/// Resolves DNS SRV names, e.g. `_nats._tcp.example.com`, into their targets.
+pub trait SrvResolver {
+    fn resolve(&self, name: &str) -> Result<Vec<SrvTarget>, io::Error>;
+}
+
+/// Resolves SRV names with the nameservers in `/etc/resolv.conf` over UDP.
+/// The nameservers are tried in order until one answers. Truncated
+/// responses aren't retried over TCP but fail the query.
+#[derive(Debug, Default)]
+pub struct SystemResolver;
+
+impl SrvResolver for SystemResolver {
+    fn resolve(&self, name: &str) -> Result<Vec<SrvTarget>, io::Error> {
+        let query_id = rand::random::<u16>();
+        let query = query(query_id, name)?;
+        let mut last_error = None;
+        for nameserver in nameservers(&fs::read_to_string(RESOLV_CONF).unwrap_or_default()) {
+            log::debug!("Resolving the SRV name {} with {}", name, nameserver);
+            match exchange(nameserver, &query) {
+                Ok(response) => {
+                    return parse_response(query_id, &response).map_err(|e| {
+                        io::Error::new(e.kind(), format!("could not resolve {}: {}", name, e))
+                    })
+                }
+                Err(e) => {
+                    log::debug!("No response from the nameserver {}: {}", nameserver, e);
+                    last_error = Some(io::Error::new(
+                        e.kind(),
+                        format!("no response from the nameserver {}: {}", nameserver, e),
+                    ));
+                }
+            }
+        }
+        Err(last_error.expect("there is always at least one nameserver"))
+    }
+}
+
+/// Sends the `query` to the `nameserver` and returns its response.
+fn exchange(nameserver: SocketAddr, query: &[u8]) -> Result<Vec<u8>, io::Error> {
+    let socket = UdpSocket::bind(match nameserver.ip() {
+        IpAddr::V4(_) => SocketAddr::from(([0, 0, 0, 0], 0)),
+        IpAddr::V6(_) => SocketAddr::from(([0u16; 8], 0)),
+    })?;
+    socket.set_read_timeout(Some(QUERY_TIMEOUT))?;
+    socket.connect(nameserver)?;
+    socket.send(query)?;
+    let mut response = vec![0; MAX_MESSAGE_SIZE];
+    let n = socket.recv(&mut response)?;
+    response.truncate(n);
+    Ok(response)
+}
+
+/// Returns the addresses of the `nameserver` entries of the `resolv_conf`
+/// in order, or of a resolver on localhost if there are none.
+fn nameservers(resolv_conf: &str) -> Vec<SocketAddr> {
+    let nameservers: Vec<SocketAddr> = resolv_conf
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        .filter_map(|address| address.trim().parse::<IpAddr>().ok())
+        .map(|ip| SocketAddr::new(ip, DNS_PORT))
+        .collect();
+    if nameservers.is_empty() {
+        return vec![SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), DNS_PORT)];
+    }
+    nameservers
+}
+
+/// Encodes a recursive DNS query with the `id` for the SRV records of the
+/// `name`.
+fn query(id: u16, name: &str) -> Result<Vec<u8>, io::Error> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid SRV name '{}'", name),
+        )
+    };
+    let mut message = Vec::with_capacity(12 + name.len() + 6);
+    message.extend_from_slice(&id.to_be_bytes());
+    // flags: recursion desired
+    message.extend_from_slice(&0x0100u16.to_be_bytes());
+    // one question, no answer, authority, and additional records
+    message.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+    let name = name.trim_end_matches('.');
+    if name.is_empty() {
+        return Err(invalid());
+    }
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(invalid());
+        }
+        message.push(label.len() as u8);
+        message.extend_from_slice(label.as_bytes());
+    }
+    message.push(0);
+    message.extend_from_slice(&TYPE_SRV.to_be_bytes());
+    message.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(message)
+}
+
+/// Parses the SRV records from the DNS `response` to the query with the
+/// `id`. Targets of `.`, meaning the service isn't available, are skipped.
+fn parse_response(id: u16, response: &[u8]) -> Result<Vec<SrvTarget>, io::Error> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let header = response
+        .get(..12)
+        .ok_or_else(|| invalid("the DNS response is too short"))?;
+    let u16_at = |offset: usize| u16::from_be_bytes([header[offset], header[offset + 1]]);
+    if u16_at(0) != id {
+        return Err(invalid("the DNS response doesn't match the query"));
+    }
+    let flags = u16_at(2);
+    if flags & 0x0200 != 0 {
+        return Err(invalid("the DNS response was truncated"));
+    }
+    match flags & 0x000f {
+        0 => {}
+        3 => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "the SRV name doesn't exist",
+            ));
+        }
+        rcode => {
+            return Err(invalid(&format!(
+                "the DNS query failed with rcode {}",
+                rcode
+            )))
+        }
+    }
+
+    let mut offset = 12;
+    for _ in 0..u16_at(4) {
+        offset = skip_name(response, offset).ok_or_else(|| invalid("malformed question"))? + 4;
+    }
+    let mut targets = vec![];
+    for _ in 0..u16_at(6) {
+        let (record, next) =
+            parse_record(response, offset).ok_or_else(|| invalid("malformed answer"))?;
+        offset = next;
+        if let Some(target) = record.filter(|target| !target.host.is_empty()) {
+            targets.push(target);
+        }
+    }
+    if targets.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "the SRV name has no targets",
+        ));
+    }
+    Ok(targets)
+}
+
+/// Parses the resource record at the `offset`. Returns the SRV target, or
+/// `None` for other record types, and the offset of the next record.
+fn parse_record(message: &[u8], offset: usize) -> Option<(Option<SrvTarget>, usize)> {
+    let offset = skip_name(message, offset)?;
+    let fixed = message.get(offset..offset + 10)?;
+    let record_type = u16::from_be_bytes([fixed[0], fixed[1]]);
+    let class = u16::from_be_bytes([fixed[2], fixed[3]]);
+    let rdlength = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+    let rdata_offset = offset + 10;
+    let rdata = message.get(rdata_offset..rdata_offset + rdlength)?;
+    let next = rdata_offset + rdlength;
+    if record_type != TYPE_SRV || class != CLASS_IN {
+        return Some((None, next));
+    }
+    let field = |i: usize| {
+        rdata
+            .get(i..i + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+    };
+    let target = SrvTarget {
+        priority: field(0)?,
+        weight: field(2)?,
+        port: field(4)?,
+        host: read_name(message, rdata_offset + 6)?,
+    };
+    Some((Some(target), next))
+}
+
+/// Returns the offset after the (possibly compressed) name at the `offset`.
+fn skip_name(message: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let len = *message.get(offset)? as usize;
+        match len {
+            0 => return Some(offset + 1),
+            // a pointer to a name elsewhere ends the name
+            len if len & 0xc0 == 0xc0 => return Some(offset + 2),
+            len => offset += 1 + len,
+        }
+    }
+}
+
+/// Reads the (possibly compressed) name at the `offset` without the trailing
+/// dot. The root name is returned as an empty string.
+fn read_name(message: &[u8], mut offset: usize) -> Option<String> {
+    let mut labels: Vec<String> = vec![];
+    // bounds the number of pointers followed, so names pointing to
+    // themselves don't loop forever
+    for _ in 0..message.len() {
+        let len = *message.get(offset)? as usize;
+        if len == 0 {
+            return Some(labels.join("."));
+        }
+        if len & 0xc0 == 0xc0 {
+            offset = ((len & 0x3f) << 8) | *message.get(offset + 1)? as usize;
+            continue;
+        }
+        let label = message.get(offset + 1..offset + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        offset += 1 + len;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A response to the query with the ID 0x1234 for _nats._tcp.example.com
+    // with two SRV records. The second target uses name compression.
+    fn response() -> Vec<u8> {
+        let mut response = vec![
+            0x12, 0x34, 0x81, 0x80, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00,
+        ];
+        // question
+        response.extend_from_slice(b"\x05_nats\x04_tcp\x07example\x03com\x00\x00\x21\x00\x01");
+        // answer: priority 20, weight 5, port 4223, nats2.example.com
+        response.extend_from_slice(b"\xc0\x0c\x00\x21\x00\x01\x00\x00\x01\x2c\x00\x0e");
+        response.extend_from_slice(b"\x00\x14\x00\x05\x10\x7f\x05nats2\xc0\x17");
+        // answer: priority 10, weight 0, port 4222, nats1.example.com
+        response.extend_from_slice(b"\xc0\x0c\x00\x21\x00\x01\x00\x00\x01\x2c\x00\x19");
+        response.extend_from_slice(b"\x00\x0a\x00\x00\x10\x7e\x05nats1\x07example\x03com\x00");
+        response
+    }
+
+    #[test]
+    fn test_query() {
+        let query = query(0x1234, "_nats._tcp.example.com.").unwrap();
+        assert_eq!(
+            &query[..12],
+            b"\x12\x34\x01\x00\x00\x01\x00\x00\x00\x00\x00\x00"
+        );
+        assert_eq!(
+            &query[12..],
+            b"\x05_nats\x04_tcp\x07example\x03com\x00\x00\x21\x00\x01"
+        );
+        assert!(super::query(1, "").is_err());
+        assert!(super::query(1, "a..b").is_err());
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let targets = parse_response(0x1234, &response()).unwrap();
+        assert_eq!(
+            targets,
+            vec![
+                SrvTarget {
+                    priority: 20,
+                    weight: 5,
+                    port: 4223,
+                    host: "nats2.example.com".to_string(),
+                },
+                SrvTarget {
+                    priority: 10,
+                    weight: 0,
+                    port: 4222,
+                    host: "nats1.example.com".to_string(),
+                },
+            ]
+        );
+
+        assert!(parse_response(0x4321, &response()).is_err());
+        let mut nxdomain = response();
+        nxdomain[3] = 0x83;
+        assert_eq!(
+            parse_response(0x1234, &nxdomain).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        let mut truncated = response();
+        truncated.truncate(60);
+        assert!(parse_response(0x1234, &truncated).is_err());
+    }
+
+    #[test]
+    fn test_truncated_response() {
+        let mut truncated = response();
+        // the TC flag
+        truncated[2] |= 0x02;
+        let error = parse_response(0x1234, &truncated).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "the DNS response was truncated");
+    }
+
+    #[test]
+    fn test_nameservers() {
+        assert_eq!(
+            nameservers(
+                "# comment\nsearch example.com\nnameserver 10.0.0.2\nnameserver invalid\nnameserver 10.0.0.3\n"
+            ),
+            vec![
+                "10.0.0.2:53".parse().unwrap(),
+                "10.0.0.3:53".parse().unwrap()
+            ]
+        );
+        assert_eq!(
+            nameservers("nameserver ::1\n"),
+            vec!["[::1]:53".parse().unwrap()]
+        );
+        assert_eq!(nameservers(""), vec!["127.0.0.1:53".parse().unwrap()]);
+    }
+}
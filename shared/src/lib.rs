@@ -6,13 +6,16 @@ pub extern crate clap;
 pub extern crate corepc_client;
 pub extern crate corepc_node;
 pub extern crate futures;
+pub extern crate jsonrpc;
 pub extern crate lazy_static;
 pub extern crate log;
 pub extern crate prometheus;
 pub extern crate prost;
 pub extern crate rand;
+pub extern crate serde_json;
 pub extern crate simple_logger;
 pub extern crate tokio;
+pub extern crate toml;
 
 /// Mappings and implementation for the protobuf types used in NATS
 /// to communicate between the extractors and tools.
@@ -33,6 +36,16 @@ pub mod util;
 /// Utillity functions for connecting to NATS servers.
 pub mod nats_util;
 
+/// Publishing events to NATS and counting the failed publishes.
+pub mod nats_publisher;
+
+/// Resolving DNS SRV names, e.g. the NATS servers of a service.
+pub mod dns_srv;
+
+/// Splitting payloads larger than the NATS maximum payload into chunks and
+/// reassembling them.
+pub mod nats_chunks;
+
 /// Log matchers for the different types of log messages.
 pub mod log_matchers;
 
@@ -1,6 +1,7 @@
 use crate::protobuf::log_extractor::log::LogEvent;
 use crate::protobuf::log_extractor::{
-    BlockCheckedLog, BlockConnectedLog, Log, LogDebugCategory, UnknownLogMessage,
+    BlockCheckedLog, BlockConnectedLog, Log, LogDebugCategory, MisbehavingLog, UnknownLogMessage,
+    UpdateTipLog,
 };
 use lazy_static::lazy_static;
 use regex::Regex;
@@ -78,6 +79,15 @@ lazy_static! {
         VALIDATION_STATE_PATTERN
     ))
     .unwrap();
+
+    static ref UPDATE_TIP_REGEX: Regex = Regex::new(&format!(
+        r"UpdateTip: new best=({}) height=(\d+) version=0x[0-9a-f]+ log2_work=([0-9.]+) tx=(\d+)",
+        BLOCK_HASH_PATTERN
+    ))
+    .unwrap();
+
+    static ref MISBEHAVING_REGEX: Regex =
+        Regex::new(r"Misbehaving: peer=(\d+)(?:: (.*))?$").unwrap();
 }
 
 trait LogMatcher {
@@ -122,6 +132,38 @@ impl LogMatcher for BlockCheckedLog {
     }
 }
 
+impl LogMatcher for UpdateTipLog {
+    fn parse_event(line: &str) -> Option<LogEvent> {
+        let caps = UPDATE_TIP_REGEX.captures(line)?;
+
+        let block_hash = caps.get(1)?.as_str().to_string();
+        let block_height = caps.get(2)?.as_str().parse::<u32>().ok()?;
+        let log2_work = caps.get(3)?.as_str().parse::<f64>().ok()?;
+        let tx_count = caps.get(4)?.as_str().parse::<u64>().ok()?;
+        Some(LogEvent::UpdateTipLog(UpdateTipLog {
+            block_hash,
+            block_height,
+            log2_work,
+            tx_count,
+        }))
+    }
+}
+
+impl LogMatcher for MisbehavingLog {
+    fn parse_event(line: &str) -> Option<LogEvent> {
+        let caps = MISBEHAVING_REGEX.captures(line)?;
+
+        let peer_id = caps.get(1)?.as_str().parse::<i64>().ok()?;
+        let message = caps
+            .get(2)
+            .map_or_else(String::new, |m| m.as_str().to_string());
+        Some(LogEvent::MisbehavingLog(MisbehavingLog {
+            peer_id,
+            message,
+        }))
+    }
+}
+
 impl BlockCheckedLog {
     pub fn is_mutated_block(&self) -> bool {
         matches!(
@@ -143,8 +185,12 @@ pub fn parse_log_event(line: &str) -> Log {
         message,
     } = parse_common_log_data(line);
 
-    let matchers: Vec<fn(&str) -> Option<LogEvent>> =
-        vec![BlockConnectedLog::parse_event, BlockCheckedLog::parse_event];
+    let matchers: Vec<fn(&str) -> Option<LogEvent>> = vec![
+        BlockConnectedLog::parse_event,
+        BlockCheckedLog::parse_event,
+        UpdateTipLog::parse_event,
+        MisbehavingLog::parse_event,
+    ];
     for matcher in &matchers {
         if let Some(event) = matcher(&message) {
             return Log {
@@ -165,6 +211,13 @@ pub fn parse_log_event(line: &str) -> Log {
     }
 }
 
+/// Returns whether the `line` is formatted like a debug.log line, i.e. starts
+/// with a timestamp. Other lines are still parsed by [`parse_log_event`], but
+/// their timestamp, category, and message are unknown.
+pub fn is_log_line(line: &str) -> bool {
+    LOG_LINE_REGEX.is_match(line)
+}
+
 struct CommonLogData {
     pub timestamp_micro: u64,
     pub category: LogDebugCategory,
@@ -450,4 +503,67 @@ mod tests {
         }
         panic!("Expected BlockCheckedLog event");
     }
+
+    #[test]
+    fn test_log_matcher_update_tip() {
+        let log = "2025-10-28T02:20:12Z UpdateTip: new best=41109f31c8ca4d8683ab5571ba462292ddb8486dee6ecd2e62901accc7952f0b height=437 version=0x20000000 log2_work=9.777255 tx=438 date='2025-10-28T02:20:12Z' progress=1.000000 cache=0.3MiB(437txo)";
+        let log_event = parse_log_event(log);
+
+        assert_eq!(log_event.log_timestamp, 1761618012000000);
+        assert_eq!(log_event.category, LogDebugCategory::Unknown as i32);
+
+        if let Some(LogEvent::UpdateTipLog(event)) = log_event.log_event {
+            assert_eq!(
+                event.block_hash,
+                "41109f31c8ca4d8683ab5571ba462292ddb8486dee6ecd2e62901accc7952f0b"
+            );
+            assert_eq!(event.block_height, 437);
+            assert_eq!(event.log2_work, 9.777255);
+            assert_eq!(event.tx_count, 438);
+            return;
+        }
+        panic!("Expected UpdateTipLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_misbehaving() {
+        let log =
+            "2025-10-28T02:20:12Z [msghand] [net] Misbehaving: peer=5: invalid header received";
+        let log_event = parse_log_event(log);
+
+        assert_eq!(log_event.category, LogDebugCategory::Net as i32);
+        assert_eq!(log_event.threadname, "msghand");
+
+        if let Some(LogEvent::MisbehavingLog(event)) = log_event.log_event {
+            assert_eq!(event.peer_id, 5);
+            assert_eq!(event.message, "invalid header received");
+            return;
+        }
+        panic!("Expected MisbehavingLog event");
+    }
+
+    #[test]
+    fn test_log_matcher_misbehaving_without_message() {
+        let log = "2025-10-28T02:20:12Z [net] Misbehaving: peer=12";
+        let log_event = parse_log_event(log);
+
+        if let Some(LogEvent::MisbehavingLog(event)) = log_event.log_event {
+            assert_eq!(event.peer_id, 12);
+            assert_eq!(event.message, "");
+            return;
+        }
+        panic!("Expected MisbehavingLog event");
+    }
+
+    #[test]
+    fn test_is_log_line() {
+        assert!(is_log_line(
+            "2025-10-02T02:31:14Z Verification progress: 50%"
+        ));
+        assert!(is_log_line(
+            "2025-10-02T02:31:21.123456Z [net] Flushed 0 addresses to peers.dat  2ms"
+        ));
+        assert!(!is_log_line("Verification progress: 50%"));
+        assert!(!is_log_line(""));
+    }
 }
@@ -0,0 +1,200 @@
+use async_nats::HeaderMap;
+use std::collections::{HashMap, VecDeque};
+
+/// Identifies the chunks of the same payload.
+pub const CHUNK_ID_HEADER: &str = "X-Chunk-Id";
+/// The position of a chunk in the payload, starting at 0.
+pub const CHUNK_INDEX_HEADER: &str = "X-Chunk-Index";
+/// The number of chunks the payload was split into.
+pub const CHUNK_TOTAL_HEADER: &str = "X-Chunk-Total";
+
+// Number of incompletely received payloads kept. If chunks are lost, the
+// oldest incomplete payload is dropped once this is exceeded.
+const MAX_PENDING_PAYLOADS: usize = 64;
+
+/// Splits a `payload` into chunks of at most `chunk_size` bytes. An empty
+/// payload is a single empty chunk.
+pub fn split(payload: &[u8], chunk_size: usize) -> Vec<&[u8]> {
+    if payload.is_empty() {
+        return vec![payload];
+    }
+    payload.chunks(chunk_size.max(1)).collect()
+}
+
+/// Reassembles payloads of messages that were split into chunks with the
+/// `X-Chunk-*` headers. A message without these headers is passed through
+/// unchanged. The chunks of a payload may arrive interleaved with the chunks
+/// of other payloads.
+#[derive(Debug, Default)]
+pub struct ChunkAssembler {
+    pending: HashMap<String, Pending>,
+    // IDs of the pending payloads, oldest first.
+    order: VecDeque<String>,
+}
+
+#[derive(Debug)]
+struct Pending {
+    chunks: Vec<Option<Vec<u8>>>,
+    received: usize,
+}
+
+impl ChunkAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a received message. Returns the complete payload once all of its
+    /// chunks have been received, and `None` while chunks are still missing.
+    /// Messages with invalid chunk headers are dropped.
+    pub fn push(&mut self, headers: Option<&HeaderMap>, payload: &[u8]) -> Option<Vec<u8>> {
+        let header = |name: &str| {
+            headers
+                .and_then(|headers| headers.get(name))
+                .map(|value| value.as_str().to_string())
+        };
+        let (id, index, total) = match (
+            header(CHUNK_ID_HEADER),
+            header(CHUNK_INDEX_HEADER),
+            header(CHUNK_TOTAL_HEADER),
+        ) {
+            (Some(id), Some(index), Some(total)) => {
+                match (index.parse::<usize>(), total.parse::<usize>()) {
+                    (Ok(index), Ok(total)) if index < total => (id, index, total),
+                    _ => {
+                        log::warn!(
+                            "Dropping a chunk with invalid headers: index={} total={}",
+                            index,
+                            total
+                        );
+                        return None;
+                    }
+                }
+            }
+            _ => return Some(payload.to_vec()),
+        };
+        if total == 1 {
+            return Some(payload.to_vec());
+        }
+
+        if !self.pending.contains_key(&id) {
+            if self.order.len() >= MAX_PENDING_PAYLOADS {
+                if let Some(oldest) = self.order.pop_front() {
+                    log::warn!("Dropping the incomplete chunked payload {}", oldest);
+                    self.pending.remove(&oldest);
+                }
+            }
+            self.order.push_back(id.clone());
+            self.pending.insert(
+                id.clone(),
+                Pending {
+                    chunks: vec![None; total],
+                    received: 0,
+                },
+            );
+        }
+        let pending = self.pending.get_mut(&id).expect("inserted above");
+        if pending.chunks.len() != total {
+            log::warn!(
+                "Dropping a chunk of {} with a changed total of {} chunks",
+                id,
+                total
+            );
+            return None;
+        }
+        if pending.chunks[index].is_none() {
+            pending.chunks[index] = Some(payload.to_vec());
+            pending.received += 1;
+        }
+        if pending.received < total {
+            return None;
+        }
+
+        let pending = self.pending.remove(&id).expect("exists");
+        self.order.retain(|pending_id| *pending_id != id);
+        Some(pending.chunks.into_iter().flatten().flatten().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk_headers(id: &str, index: usize, total: usize) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(CHUNK_ID_HEADER, id);
+        headers.insert(CHUNK_INDEX_HEADER, index.to_string().as_str());
+        headers.insert(CHUNK_TOTAL_HEADER, total.to_string().as_str());
+        headers
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split(b"abcde", 2), vec![&b"ab"[..], b"cd", b"e"]);
+        assert_eq!(split(b"abcd", 4), vec![&b"abcd"[..]]);
+        assert_eq!(split(b"", 4), vec![&b""[..]]);
+    }
+
+    #[test]
+    fn test_reassemble_interleaved() {
+        let mut assembler = ChunkAssembler::new();
+        let first = split(b"hello world", 4);
+        let second = split(b"chunked", 3);
+
+        assert_eq!(
+            assembler.push(None, b"unchunked"),
+            Some(b"unchunked".to_vec())
+        );
+        assert_eq!(
+            assembler.push(Some(&chunk_headers("1", 0, 3)), first[0]),
+            None
+        );
+        assert_eq!(
+            assembler.push(Some(&chunk_headers("2", 1, 3)), second[1]),
+            None
+        );
+        assert_eq!(
+            assembler.push(Some(&chunk_headers("1", 2, 3)), first[2]),
+            None
+        );
+        assert_eq!(
+            assembler.push(Some(&chunk_headers("2", 0, 3)), second[0]),
+            None
+        );
+        // duplicates are ignored
+        assert_eq!(
+            assembler.push(Some(&chunk_headers("2", 0, 3)), second[0]),
+            None
+        );
+        assert_eq!(
+            assembler.push(Some(&chunk_headers("1", 1, 3)), first[1]),
+            Some(b"hello world".to_vec())
+        );
+        assert_eq!(
+            assembler.push(Some(&chunk_headers("2", 2, 3)), second[2]),
+            Some(b"chunked".to_vec())
+        );
+        assert!(assembler.pending.is_empty());
+        assert!(assembler.order.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_and_lost_chunks() {
+        let mut assembler = ChunkAssembler::new();
+        assert_eq!(assembler.push(Some(&chunk_headers("1", 3, 3)), b"x"), None);
+        assert_eq!(
+            assembler.push(Some(&chunk_headers("1", 0, 1)), b"single"),
+            Some(b"single".to_vec())
+        );
+
+        // the oldest incomplete payload is dropped
+        for id in 0..=MAX_PENDING_PAYLOADS {
+            assembler.push(Some(&chunk_headers(&id.to_string(), 0, 2)), b"a");
+        }
+        assert_eq!(assembler.pending.len(), MAX_PENDING_PAYLOADS);
+        assert!(!assembler.pending.contains_key("0"));
+        assert_eq!(
+            assembler.push(Some(&chunk_headers("1", 1, 2)), b"b"),
+            Some(b"ab".to_vec())
+        );
+    }
+}
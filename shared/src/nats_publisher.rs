@@ -0,0 +1,572 @@
+use crate::nats_chunks::{self, CHUNK_ID_HEADER, CHUNK_INDEX_HEADER, CHUNK_TOTAL_HEADER};
+use async_nats::client::{FlushError, PublishError};
+use async_nats::connection::State;
+use async_nats::HeaderMap;
+use futures::future::BoxFuture;
+use prometheus::IntCounter;
+use std::error;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Sends messages to a NATS server. Implemented for `async_nats::Client`, so
+/// that the [NatsPublisher] can be tested without a server.
+pub trait NatsClient: Send + Sync {
+    fn publish(&self, subject: String, payload: Vec<u8>)
+        -> BoxFuture<'_, Result<(), PublishError>>;
+
+    fn publish_with_headers(
+        &self,
+        subject: String,
+        headers: HeaderMap,
+        payload: Vec<u8>,
+    ) -> BoxFuture<'_, Result<(), PublishError>>;
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), FlushError>>;
+
+    fn connection_state(&self) -> State;
+
+    /// The maximum payload size of the server, including the headers.
+    fn max_payload(&self) -> usize;
+}
+
+impl NatsClient for async_nats::Client {
+    fn publish(
+        &self,
+        subject: String,
+        payload: Vec<u8>,
+    ) -> BoxFuture<'_, Result<(), PublishError>> {
+        Box::pin(async_nats::Client::publish(self, subject, payload.into()))
+    }
+
+    fn publish_with_headers(
+        &self,
+        subject: String,
+        headers: HeaderMap,
+        payload: Vec<u8>,
+    ) -> BoxFuture<'_, Result<(), PublishError>> {
+        Box::pin(async_nats::Client::publish_with_headers(
+            self,
+            subject,
+            headers,
+            payload.into(),
+        ))
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), FlushError>> {
+        Box::pin(async_nats::Client::flush(self))
+    }
+
+    fn connection_state(&self) -> State {
+        async_nats::Client::connection_state(self)
+    }
+
+    fn max_payload(&self) -> usize {
+        self.server_info().max_payload
+    }
+}
+
+/// Counts failed publishes, e.g. the `nats_publish_errors` metric of an
+/// extractor with the label values of the event that failed.
+pub trait ErrorCounter: Send + Sync {
+    fn inc(&self);
+
+    /// Counts a message dropped as the [PendingBuffer] is full, e.g. in the
+    /// `nats_dropped_total` metric. The message is counted as failed publish
+    /// with [ErrorCounter::inc], too.
+    fn inc_dropped(&self) {}
+}
+
+impl ErrorCounter for IntCounter {
+    fn inc(&self) {
+        IntCounter::inc(self)
+    }
+}
+
+/// Doesn't count anything, for extractors without metrics.
+impl ErrorCounter for () {
+    fn inc(&self) {}
+}
+
+/// The `nats_publish_errors` and `nats_dropped_total` counters of an event.
+#[derive(Debug, Clone)]
+pub struct PublishCounters {
+    pub errors: IntCounter,
+    pub dropped: IntCounter,
+}
+
+impl ErrorCounter for PublishCounters {
+    fn inc(&self) {
+        self.errors.inc()
+    }
+
+    fn inc_dropped(&self) {
+        self.dropped.inc()
+    }
+}
+
+/// Awaits the `publish` future and increments the `errors` counter if it
+/// fails. The error is returned to the caller.
+pub async fn counted<E>(
+    errors: &(impl ErrorCounter + ?Sized),
+    publish: impl Future<Output = Result<(), E>>,
+) -> Result<(), E> {
+    let result = publish.await;
+    if result.is_err() {
+        errors.inc();
+    }
+    result
+}
+
+/// Message headers, e.g. the `Content-Type` of the payload.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Headers(Vec<(&'static str, String)>);
+
+impl Headers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: &'static str, value: impl Into<String>) {
+        self.0.retain(|(n, _)| *n != name);
+        self.0.push((name, value.into()));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.0.iter().find(|(n, _)| *n == name).map(|(_, v)| v)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        self.0.iter().map(|(n, v)| (*n, v.as_str()))
+    }
+}
+
+/// Bounds the bytes of messages published while disconnected from the NATS
+/// server. async-nats buffers these until it reconnects, so without a bound
+/// they pile up for as long as the outage lasts. The [NatsPublisher] checks
+/// with [PendingBuffer::reserve] before each publish and drops the message if
+/// it doesn't fit.
+#[derive(Debug)]
+pub struct PendingBuffer {
+    limit: usize,
+    pending: AtomicUsize,
+}
+
+impl PendingBuffer {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            pending: AtomicUsize::new(0),
+        }
+    }
+
+    /// Reserves `size` bytes for a message published in the connection
+    /// `state`. Returns false if the message doesn't fit into the buffer and
+    /// should be dropped. While connected, messages are sent right away, so
+    /// the buffer is emptied and nothing is reserved.
+    pub fn reserve(&self, state: State, size: usize) -> bool {
+        if state == State::Connected {
+            self.pending.store(0, Ordering::SeqCst);
+            return true;
+        }
+        self.pending
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pending| {
+                pending
+                    .checked_add(size)
+                    .filter(|total| *total <= self.limit)
+            })
+            .is_ok()
+    }
+}
+
+#[derive(Debug)]
+pub enum NatsPublishError {
+    Publish(PublishError),
+    /// The payload of this many bytes was dropped as the buffer for messages
+    /// published while disconnected from the NATS server is full.
+    PendingBufferFull(usize),
+}
+
+impl fmt::Display for NatsPublishError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NatsPublishError::Publish(e) => write!(f, "NATS publish error {}", e),
+            NatsPublishError::PendingBufferFull(size) => write!(
+                f,
+                "dropped {} bytes as the NATS pending buffer is full",
+                size
+            ),
+        }
+    }
+}
+
+impl error::Error for NatsPublishError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            NatsPublishError::Publish(ref e) => Some(e),
+            NatsPublishError::PendingBufferFull(_) => None,
+        }
+    }
+}
+
+impl From<PublishError> for NatsPublishError {
+    fn from(e: PublishError) -> Self {
+        NatsPublishError::Publish(e)
+    }
+}
+
+/// Publishes events to NATS and increments the error counter passed with
+/// each event when the publish fails. With `chunk_large_payloads`, a payload
+/// exceeding the maximum payload size of the server is split into chunks,
+/// which consumers can reassemble with a `nats_chunks::ChunkAssembler`.
+/// With a `pending_buffer`, payloads published while disconnected are
+/// dropped once the buffer is full.
+pub struct NatsPublisher<C = async_nats::Client> {
+    client: C,
+    chunk_large_payloads: bool,
+    pending_buffer: Option<PendingBuffer>,
+}
+
+impl<C: NatsClient> NatsPublisher<C> {
+    pub fn new(
+        client: C,
+        chunk_large_payloads: bool,
+        pending_buffer: Option<PendingBuffer>,
+    ) -> Self {
+        Self {
+            client,
+            chunk_large_payloads,
+            pending_buffer,
+        }
+    }
+
+    /// Publishes the `payload` with the `headers` on the `subject` and
+    /// increments `errors` if that fails, or also counts the payload as
+    /// dropped if the pending buffer is full.
+    pub async fn publish(
+        &self,
+        subject: &str,
+        headers: &Headers,
+        payload: Vec<u8>,
+        errors: &(impl ErrorCounter + ?Sized),
+    ) -> Result<(), NatsPublishError> {
+        let result = counted(errors, self.publish_uncounted(subject, headers, payload)).await;
+        if let Err(NatsPublishError::PendingBufferFull(size)) = &result {
+            log::debug!(
+                "Dropping a message of {} bytes on '{}' as the NATS pending buffer is full.",
+                size,
+                subject
+            );
+            errors.inc_dropped();
+        }
+        result
+    }
+
+    /// Waits until the already published messages were sent.
+    pub async fn flush(&self) -> Result<(), FlushError> {
+        self.client.flush().await
+    }
+
+    async fn publish_uncounted(
+        &self,
+        subject: &str,
+        headers: &Headers,
+        payload: Vec<u8>,
+    ) -> Result<(), NatsPublishError> {
+        let mut nats_headers = HeaderMap::new();
+        for (name, value) in headers.iter() {
+            nats_headers.insert(name, value);
+        }
+        let has_headers = headers.iter().next().is_some();
+        let size = match has_headers {
+            true => payload.len() + header_block_len(headers.iter()),
+            false => payload.len(),
+        };
+        if let Some(pending_buffer) = &self.pending_buffer {
+            if !pending_buffer.reserve(self.client.connection_state(), size) {
+                return Err(NatsPublishError::PendingBufferFull(size));
+            }
+        }
+        let max_payload = self.client.max_payload();
+        let chunk_size = match chunk_size(headers, max_payload) {
+            Some(chunk_size) if self.chunk_large_payloads && size > max_payload => chunk_size,
+            _ if has_headers => {
+                return Ok(self
+                    .client
+                    .publish_with_headers(subject.to_string(), nats_headers, payload)
+                    .await?);
+            }
+            _ => return Ok(self.client.publish(subject.to_string(), payload).await?),
+        };
+        let id = format!("{:016x}", rand::random::<u64>());
+        let chunks = nats_chunks::split(&payload, chunk_size);
+        let total = chunks.len().to_string();
+        log::debug!(
+            "Publishing a payload of {} bytes on '{}' in {} chunks",
+            payload.len(),
+            subject,
+            total
+        );
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let mut chunk_headers = nats_headers.clone();
+            chunk_headers.insert(CHUNK_ID_HEADER, id.as_str());
+            chunk_headers.insert(CHUNK_INDEX_HEADER, index.to_string().as_str());
+            chunk_headers.insert(CHUNK_TOTAL_HEADER, total.as_str());
+            self.client
+                .publish_with_headers(subject.to_string(), chunk_headers, chunk.to_vec())
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the size of the NATS header block with the `headers`: the
+/// `NATS/1.0` version line, a `Name: Value` line per header, and an empty line.
+fn header_block_len<'a>(headers: impl Iterator<Item = (&'a str, &'a str)>) -> usize {
+    "NATS/1.0\r\n\r\n".len()
+        + headers
+            .map(|(name, value)| name.len() + ": \r\n".len() + value.len())
+            .sum::<usize>()
+}
+
+/// Returns the maximum size of the chunks of a payload with the `headers`,
+/// so that a chunk including its headers doesn't exceed the `max_payload`.
+/// Returns `None` if the headers alone already exceed it.
+fn chunk_size(headers: &Headers, max_payload: usize) -> Option<usize> {
+    // The chunk ID is a 16 character hex string and the index and total have
+    // at most 20 digits.
+    let chunk_headers = [
+        (CHUNK_ID_HEADER, "0123456789abcdef"),
+        (CHUNK_INDEX_HEADER, "18446744073709551615"),
+        (CHUNK_TOTAL_HEADER, "18446744073709551615"),
+    ];
+    let headers_len = header_block_len(headers.iter().chain(chunk_headers));
+    max_payload
+        .checked_sub(headers_len)
+        .filter(|size| *size > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_nats::client::PublishErrorKind;
+    use prometheus::{IntCounterVec, Opts};
+    use std::sync::Mutex;
+
+    /// Fails every publish after the first `successes` ones and records the
+    /// sizes of the successfully published payloads.
+    struct MockClient {
+        successes: Mutex<usize>,
+        state: State,
+        max_payload: usize,
+        published: Mutex<Vec<usize>>,
+    }
+
+    impl MockClient {
+        fn new(successes: usize) -> Self {
+            Self {
+                successes: Mutex::new(successes),
+                state: State::Connected,
+                max_payload: 1024,
+                published: Mutex::new(vec![]),
+            }
+        }
+    }
+
+    impl NatsClient for MockClient {
+        fn publish(
+            &self,
+            subject: String,
+            payload: Vec<u8>,
+        ) -> BoxFuture<'_, Result<(), PublishError>> {
+            self.publish_with_headers(subject, HeaderMap::new(), payload)
+        }
+
+        fn publish_with_headers(
+            &self,
+            _subject: String,
+            _headers: HeaderMap,
+            payload: Vec<u8>,
+        ) -> BoxFuture<'_, Result<(), PublishError>> {
+            let mut successes = self.successes.lock().unwrap();
+            let result = if *successes > 0 {
+                *successes -= 1;
+                self.published.lock().unwrap().push(payload.len());
+                Ok(())
+            } else {
+                Err(PublishErrorKind::BadSubject.into())
+            };
+            Box::pin(async move { result })
+        }
+
+        fn flush(&self) -> BoxFuture<'_, Result<(), FlushError>> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn connection_state(&self) -> State {
+            self.state.clone()
+        }
+
+        fn max_payload(&self) -> usize {
+            self.max_payload
+        }
+    }
+
+    fn errors() -> IntCounterVec {
+        IntCounterVec::new(
+            Opts::new("nats_publish_errors", "failed publishes"),
+            &["event"],
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_publish_errors_are_counted() {
+        let errors = errors();
+        let publisher = NatsPublisher::new(MockClient::new(1), false, None);
+        let publish = |event: &'static str| {
+            let errors = errors.with_label_values(&[event]);
+            let publisher = &publisher;
+            async move {
+                publisher
+                    .publish("subject", &Headers::new(), vec![1, 2, 3], &errors)
+                    .await
+            }
+        };
+
+        assert!(publish("a").await.is_ok());
+        assert_eq!(errors.with_label_values(&["a"]).get(), 0);
+
+        match publish("a").await {
+            Err(NatsPublishError::Publish(e)) => {
+                assert_eq!(e.kind(), PublishErrorKind::BadSubject)
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        assert!(publish("b").await.is_err());
+        assert_eq!(errors.with_label_values(&["a"]).get(), 1);
+        assert_eq!(errors.with_label_values(&["b"]).get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pending_buffer_full_is_counted() {
+        let counters = PublishCounters {
+            errors: IntCounter::new("errors", "errors").unwrap(),
+            dropped: IntCounter::new("dropped", "dropped").unwrap(),
+        };
+        let mut client = MockClient::new(usize::MAX);
+        client.state = State::Disconnected;
+        let publisher = NatsPublisher::new(client, false, Some(PendingBuffer::new(100)));
+        let mut headers = Headers::new();
+        headers.insert("A", "bc");
+
+        assert!(publisher
+            .publish("subject", &Headers::new(), vec![0; 80], &counters)
+            .await
+            .is_ok());
+        // the header block counts towards the buffer, too
+        assert!(matches!(
+            publisher
+                .publish("subject", &headers, vec![0; 1], &counters)
+                .await,
+            Err(NatsPublishError::PendingBufferFull(20))
+        ));
+        assert!(publisher
+            .publish("subject", &Headers::new(), vec![0; 20], &counters)
+            .await
+            .is_ok());
+        assert!(publisher
+            .publish("subject", &Headers::new(), vec![0; 1], &counters)
+            .await
+            .is_err());
+        assert_eq!(counters.errors.get(), 2);
+        assert_eq!(counters.dropped.get(), 2);
+        assert_eq!(*publisher.client.published.lock().unwrap(), vec![80, 20]);
+
+        // other errors aren't counted as dropped
+        let publisher = NatsPublisher::new(MockClient::new(0), false, None);
+        assert!(publisher
+            .publish("subject", &Headers::new(), vec![0; 1], &counters)
+            .await
+            .is_err());
+        assert_eq!(counters.errors.get(), 3);
+        assert_eq!(counters.dropped.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_large_payloads() {
+        let errors = IntCounter::new("errors", "errors").unwrap();
+        let publisher = NatsPublisher::new(MockClient::new(usize::MAX), true, None);
+
+        publisher
+            .publish("subject", &Headers::new(), vec![0; 2000], &errors)
+            .await
+            .unwrap();
+        let published = publisher.client.published.lock().unwrap();
+        assert!(published.len() > 1);
+        assert_eq!(published.iter().sum::<usize>(), 2000);
+        assert_eq!(errors.get(), 0);
+    }
+
+    #[test]
+    fn test_pending_buffer() {
+        let buffer = PendingBuffer::new(100);
+        assert!(buffer.reserve(State::Disconnected, 60));
+        assert!(buffer.reserve(State::Pending, 40));
+        // a full buffer drops the message without reserving it
+        assert!(!buffer.reserve(State::Disconnected, 1));
+        assert!(!buffer.reserve(State::Disconnected, 1));
+
+        // reconnecting empties the buffer
+        assert!(buffer.reserve(State::Connected, 1000));
+        assert!(!buffer.reserve(State::Disconnected, 101));
+        assert!(buffer.reserve(State::Disconnected, 100));
+    }
+
+    #[test]
+    fn test_chunk_size() {
+        assert_eq!(header_block_len(std::iter::empty()), 12);
+        assert_eq!(header_block_len([("A", "bc")].into_iter()), 12 + 7);
+
+        let mut headers = Headers::new();
+        headers.insert("Content-Type", "application/json");
+        let size = chunk_size(&headers, 1024).unwrap();
+        let largest_chunk_headers = header_block_len(headers.iter().chain([
+            (CHUNK_ID_HEADER, "ffffffffffffffff"),
+            (CHUNK_INDEX_HEADER, "99999"),
+            (CHUNK_TOTAL_HEADER, "100000"),
+        ]));
+        assert!(size + largest_chunk_headers <= 1024);
+        assert!(size > 0);
+        assert_eq!(chunk_size(&headers, 100), None);
+    }
+
+    #[test]
+    fn test_headers() {
+        let mut headers = Headers::new();
+        headers.insert("Content-Type", "application/json");
+        headers.insert("X-Version", "1");
+        headers.insert("Content-Type", "application/protobuf");
+
+        assert_eq!(
+            headers.get("Content-Type"),
+            Some(&"application/protobuf".to_string())
+        );
+        assert_eq!(headers.get("X-Unknown"), None);
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            vec![("X-Version", "1"), ("Content-Type", "application/protobuf")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_counted() {
+        let errors = IntCounter::new("errors", "errors").unwrap();
+        assert_eq!(counted(&errors, async { Ok::<(), ()>(()) }).await, Ok(()));
+        assert_eq!(
+            counted(&errors, async { Err::<(), _>("failed") }).await,
+            Err("failed")
+        );
+        assert_eq!(errors.get(), 1);
+    }
+}
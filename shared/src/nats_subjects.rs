@@ -8,6 +8,7 @@ const NATS_SUBJECT_VALIDATION: &str = "validation";
 const NATS_SUBJECT_RPC: &str = "rpc";
 const NATS_SUBJECT_P2P_EXTRACTOR: &str = "p2p-extractor";
 const NATS_SUBJECT_LOG_EXTRACTOR: &str = "log-extractor";
+const NATS_SUBJECT_ZMQ_EXTRACTOR: &str = "zmq-extractor";
 
 pub enum Subject {
     Addrman,
@@ -18,6 +19,7 @@ pub enum Subject {
     Rpc,
     P2PExtractor,
     LogExtractor,
+    ZmqExtractor,
 }
 
 impl fmt::Display for Subject {
@@ -31,6 +33,7 @@ impl fmt::Display for Subject {
             Subject::Rpc => write!(f, "{}", NATS_SUBJECT_RPC),
             Subject::P2PExtractor => write!(f, "{}", NATS_SUBJECT_P2P_EXTRACTOR),
             Subject::LogExtractor => write!(f, "{}", NATS_SUBJECT_LOG_EXTRACTOR),
+            Subject::ZmqExtractor => write!(f, "{}", NATS_SUBJECT_ZMQ_EXTRACTOR),
         }
     }
 }
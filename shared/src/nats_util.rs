@@ -1,13 +1,20 @@
-use async_nats;
+use crate::dns_srv::{SrvResolver, SystemResolver};
+use crate::nats_publisher::PendingBuffer;
+use crate::util;
+use async_nats::{self, ServerAddr};
 use clap::Parser;
+use std::env;
 use std::fs;
 use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
 
 /// Arguments for the connection the the NATS server that each extractor and
 /// tool needs.
-#[derive(Parser, Debug, Clone, Default)]
+#[derive(Parser, Debug, Clone, serde::Serialize)]
 pub struct NatsArgs {
-    /// The NATS server address the extractor/tool should connect and subscribe to.
+    /// The NATS server address the extractor/tool should connect and subscribe to. Multiple
+    /// comma-separated addresses can be passed to fail over between them.
     #[arg(short = 'a', long = "nats-address", default_value = "127.0.0.1:4222")]
     pub address: String,
 
@@ -17,61 +24,378 @@ pub struct NatsArgs {
 
     /// The NATS password the extractor/tool should try to authentificate to the NATS server with.
     #[arg(short = 'p', long = "nats-password", default_value = None)]
+    #[serde(serialize_with = "util::serialize_redacted")]
     pub password: Option<String>,
 
     /// A path to a file containing a password the extractor/tool should try to authentificate to
     /// the NATS server with.
     #[arg(short = 'f', long = "nats-password-file", default_value = None)]
     pub password_file: Option<String>,
+
+    /// If passed, the extractor/tool requires a TLS connection to the NATS server.
+    #[arg(long = "nats-tls", default_value_t = false)]
+    pub tls: bool,
+
+    /// A path to a PEM encoded CA certificate used to verify the certificate of the NATS server.
+    #[arg(long = "nats-tls-ca-file", default_value = None)]
+    pub tls_ca_file: Option<String>,
+
+    /// A path to a PEM encoded client certificate used to authenticate to the NATS server.
+    #[arg(long = "nats-tls-client-cert", requires = "tls_client_key", default_value = None)]
+    pub tls_client_cert: Option<String>,
+
+    /// A path to a PEM encoded private key for the client certificate.
+    #[arg(long = "nats-tls-client-key", requires = "tls_client_cert", default_value = None)]
+    pub tls_client_key: Option<String>,
+
+    /// A path to a file containing an NKey seed the extractor/tool should try to authentificate to
+    /// the NATS server with.
+    #[arg(long = "nats-nkey-seed-file", default_value = None)]
+    pub nkey_seed_file: Option<String>,
+
+    /// A path to a .creds file containing a JWT and an NKey seed the extractor/tool should try to
+    /// authentificate to the NATS server with.
+    #[arg(long = "nats-credentials-file", default_value = None)]
+    pub credentials_file: Option<String>,
+
+    /// The NATS token the extractor/tool should try to authentificate to the NATS server with.
+    #[arg(long = "nats-token", default_value = None)]
+    #[serde(serialize_with = "util::serialize_redacted")]
+    pub token: Option<String>,
+
+    /// A path to a file containing a token the extractor/tool should try to authentificate to
+    /// the NATS server with.
+    #[arg(long = "nats-token-file", default_value = None)]
+    pub token_file: Option<String>,
+
+    /// The maximum number of attempts to reconnect to the NATS server after the connection
+    /// was lost. A value of 0 disables reconnecting. If not set, the extractor/tool tries to
+    /// reconnect indefinitely.
+    #[arg(long = "nats-max-reconnects", default_value = None)]
+    pub nats_max_reconnects: Option<usize>,
+
+    /// The delay (in milliseconds) between attempts to reconnect to the NATS server. If not set,
+    /// the delay increases with each failed attempt.
+    #[arg(long = "nats-reconnect-delay-ms", default_value = None)]
+    pub nats_reconnect_delay_ms: Option<u64>,
+
+    /// Timeout (in seconds) for establishing a connection to the NATS server.
+    #[arg(long = "nats-connect-timeout-secs", default_value_t = 10)]
+    pub nats_connect_timeout_secs: u64,
+
+    /// The client connection name reported to the NATS server. Defaults to
+    /// peer-observer-<binary-name>.
+    #[arg(long = "nats-connection-name", default_value = None)]
+    pub connection_name: Option<String>,
+
+    /// If passed, the NATS server addresses are DNS SRV names (e.g. _nats._tcp.example.com)
+    /// that are resolved into the NATS servers to connect to.
+    #[arg(long = "nats-srv", default_value_t = false)]
+    pub srv: bool,
+
+    /// The maximum number of bytes of messages published while disconnected from the NATS
+    /// server that are buffered until the client reconnects. Further messages are dropped and
+    /// counted in `nats_dropped_total`. If not set, the buffered messages aren't limited.
+    #[arg(long = "nats-pending-buffer-bytes", default_value = None)]
+    pub nats_pending_buffer_bytes: Option<usize>,
 }
 
-/// Populates ConnectOptions with a username and password, if the passed
-/// NATS argument has one set.
-pub fn prepare_connection(args: &NatsArgs) -> Result<async_nats::ConnectOptions, io::Error> {
-    match &args.username {
-        Some(user) => {
-            let mut pass: Option<String> = None;
-            if let Some(password) = &args.password {
-                log::debug!("Using supplied NATS user={} and password=***", user);
-                pass = Some(password.to_string())
-            } else if let Some(pw_file) = &args.password_file {
-                let mut password = fs::read_to_string(pw_file)?;
-                password = password.trim().to_string();
-                log::info!(
-                    "Using supplied NATS user={} with password from file {}",
-                    user,
-                    pw_file
-                );
-                pass = Some(password)
-            }
+// Implemented by hand to use the same defaults as the command line, e.g. a
+// connect timeout of 10 seconds instead of 0.
+impl Default for NatsArgs {
+    fn default() -> Self {
+        Self {
+            address: "127.0.0.1:4222".to_string(),
+            username: None,
+            password: None,
+            password_file: None,
+            tls: false,
+            tls_ca_file: None,
+            tls_client_cert: None,
+            tls_client_key: None,
+            nkey_seed_file: None,
+            credentials_file: None,
+            token: None,
+            token_file: None,
+            nats_max_reconnects: None,
+            nats_reconnect_delay_ms: None,
+            nats_connect_timeout_secs: 10,
+            connection_name: None,
+            srv: false,
+            nats_pending_buffer_bytes: None,
+        }
+    }
+}
 
-            // TODO: do more than warn?
-            if pass.is_none() {
-                log::warn!(
-                    "No NATS password supplied for connection to NATS server {} with user={}",
-                    args.address,
-                    user,
-                );
-            }
+/// Populates ConnectOptions with a username and password, an NKey seed, a
+/// credentials file, or a token, if the passed NATS argument has one set, and
+/// with the TLS configuration.
+pub fn prepare_connection(args: &NatsArgs) -> Result<async_nats::ConnectOptions, io::Error> {
+    check_auth_conflicts(args)?;
 
+    let options = if let Some(user) = &args.username {
+        let pass = if let Some(password) = &args.password {
+            log::debug!("Using supplied NATS user={} and password=***", user);
+            password.to_string()
+        } else if let Some(pw_file) = &args.password_file {
+            let password = fs::read_to_string(pw_file)?.trim().to_string();
             log::info!(
-                "Connecting to NATS-server {} with user={} and password=***",
-                args.address,
-                user
+                "Using supplied NATS user={} with password from file {}",
+                user,
+                pw_file
             );
-            Ok(
-                async_nats::ConnectOptions::new()
-                    .user_and_password(user.to_string(), pass.unwrap()),
+            password
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "a NATS password or password file is required for the connection to NATS server {} with user={}",
+                    args.address, user
+                ),
+            ));
+        };
+
+        log::info!(
+            "Connecting to NATS-server {} with user={} and password=***",
+            args.address,
+            user
+        );
+        async_nats::ConnectOptions::new().user_and_password(user.to_string(), pass)
+    } else if let Some(seed_file) = &args.nkey_seed_file {
+        let seed = fs::read_to_string(seed_file)?.trim().to_string();
+        log::info!(
+            "Connecting to NATS-server {} with NKey seed from file {}",
+            args.address,
+            seed_file
+        );
+        async_nats::ConnectOptions::with_nkey(seed)
+    } else if let Some(creds_file) = &args.credentials_file {
+        let creds = fs::read_to_string(creds_file).map_err(|e| {
+            io::Error::new(e.kind(), format!("could not read {}: {}", creds_file, e))
+        })?;
+        log::info!(
+            "Connecting to NATS-server {} with credentials from file {}",
+            args.address,
+            creds_file
+        );
+        async_nats::ConnectOptions::with_credentials(&creds).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("could not parse credentials file {}: {}", creds_file, e),
             )
-        }
+        })?
+    } else if let Some(token) = &args.token {
+        log::info!("Connecting to NATS-server {} with token=***", args.address);
+        async_nats::ConnectOptions::with_token(token.to_string())
+    } else if let Some(token_file) = &args.token_file {
+        let token = fs::read_to_string(token_file)?.trim().to_string();
+        log::info!(
+            "Connecting to NATS-server {} with token from file {}",
+            args.address,
+            token_file
+        );
+        async_nats::ConnectOptions::with_token(token)
+    } else {
+        log::debug!(
+            "Connecting to NATS-server at {} without authentification",
+            args.address
+        );
+        async_nats::ConnectOptions::new()
+    };
+
+    let options = prepare_reconnect(options, args)
+        .connection_timeout(Duration::from_secs(args.nats_connect_timeout_secs))
+        .name(connection_name(args));
+    prepare_tls(options, args)
+}
+
+/// Returns the client connection name reported to the NATS server. If none is
+/// set in the passed NATS arguments, peer-observer-<binary-name> is used.
+pub fn connection_name(args: &NatsArgs) -> String {
+    match &args.connection_name {
+        Some(name) => name.to_string(),
         None => {
-            log::debug!(
-                "Connecting to NATS-server at {} without authentification",
-                args.address
+            let binary_name = env::current_exe()
+                .ok()
+                .and_then(|path| path.file_stem().map(|s| s.to_string_lossy().to_string()))
+                .unwrap_or_else(|| "unknown".to_string());
+            format!("peer-observer-{}", binary_name)
+        }
+    }
+}
+
+/// Populates ConnectOptions with the reconnect behavior from the passed NATS
+/// arguments. Unset arguments keep the async-nats defaults.
+fn prepare_reconnect(
+    mut options: async_nats::ConnectOptions,
+    args: &NatsArgs,
+) -> async_nats::ConnectOptions {
+    if let Some(max_reconnects) = args.nats_max_reconnects {
+        log::debug!(
+            "Reconnecting to the NATS server at most {} times",
+            max_reconnects
+        );
+        options = options.max_reconnects(max_reconnects);
+    }
+
+    if let Some(delay_ms) = args.nats_reconnect_delay_ms {
+        log::debug!(
+            "Waiting {}ms between attempts to reconnect to the NATS server",
+            delay_ms
+        );
+        let delay = Duration::from_millis(delay_ms);
+        options = options.reconnect_delay_callback(move |_attempts| delay);
+    }
+
+    options
+}
+
+/// Returns the buffer for messages published while disconnected from the NATS
+/// server, if its size is limited in the passed NATS arguments. Publishing
+/// with a `nats_publisher::NatsPublisher` with this buffer drops the messages
+/// that don't fit.
+pub fn pending_buffer(args: &NatsArgs) -> Option<PendingBuffer> {
+    args.nats_pending_buffer_bytes.map(PendingBuffer::new)
+}
+
+/// Parses the (comma-separated) NATS server address(es) from the passed NATS
+/// arguments. Empty entries are ignored. With `--nats-srv`, the addresses
+/// are resolved as DNS SRV names.
+pub fn server_addrs(args: &NatsArgs) -> Result<Vec<ServerAddr>, io::Error> {
+    server_addrs_with_resolver(args, &SystemResolver)
+}
+
+/// Like [server_addrs], but resolves SRV names with the `resolver`. The
+/// targets of each name are ordered by priority and, within a priority, by
+/// descending weight.
+pub fn server_addrs_with_resolver(
+    args: &NatsArgs,
+    resolver: &impl SrvResolver,
+) -> Result<Vec<ServerAddr>, io::Error> {
+    let mut entries: Vec<String> = args
+        .address
+        .split(',')
+        .map(|addr| addr.trim())
+        .filter(|addr| !addr.is_empty())
+        .map(|addr| addr.to_string())
+        .collect();
+    if args.srv {
+        let mut resolved = vec![];
+        for name in entries.iter() {
+            let mut targets = resolver.resolve(name)?;
+            targets.sort_by(|a, b| a.priority.cmp(&b.priority).then(b.weight.cmp(&a.weight)));
+            let addrs: Vec<String> = targets
+                .iter()
+                .map(|target| format!("{}:{}", target.host, target.port))
+                .collect();
+            log::info!(
+                "Resolved the NATS SRV name {} into {}",
+                name,
+                addrs.join(", ")
             );
-            Ok(async_nats::ConnectOptions::new())
+            resolved.extend(addrs);
         }
+        entries = resolved;
     }
+
+    let addrs = entries
+        .iter()
+        .map(|addr| {
+            addr.parse::<ServerAddr>().map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid NATS server address '{}': {}", addr, e),
+                )
+            })
+        })
+        .collect::<Result<Vec<ServerAddr>, io::Error>>()?;
+
+    if addrs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("no NATS server address in '{}'", args.address),
+        ));
+    }
+    Ok(addrs)
+}
+
+/// Checks that at most one NATS authentification method is configured.
+fn check_auth_conflicts(args: &NatsArgs) -> Result<(), io::Error> {
+    let methods: Vec<&str> = [
+        (args.username.is_some(), "a username"),
+        (args.nkey_seed_file.is_some(), "an NKey seed file"),
+        (args.credentials_file.is_some(), "a credentials file"),
+        (args.token.is_some() || args.token_file.is_some(), "a token"),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, method)| *method)
+    .collect();
+
+    if methods.len() > 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "only one NATS authentification method can be used, but {} are set",
+                methods.join(" and ")
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Populates ConnectOptions with the TLS configuration from the passed NATS
+/// arguments. Certificate and key files are checked to exist and to be PEM
+/// encoded before being handed to the NATS client.
+fn prepare_tls(
+    mut options: async_nats::ConnectOptions,
+    args: &NatsArgs,
+) -> Result<async_nats::ConnectOptions, io::Error> {
+    if args.tls {
+        log::debug!("Requiring TLS for the connection to {}", args.address);
+        options = options.require_tls(true);
+    }
+
+    if let Some(ca_file) = &args.tls_ca_file {
+        check_pem_file(ca_file)?;
+        log::info!("Using NATS TLS root certificate from file {}", ca_file);
+        options = options.add_root_certificates(PathBuf::from(ca_file));
+    }
+
+    match (&args.tls_client_cert, &args.tls_client_key) {
+        (Some(cert), Some(key)) => {
+            check_pem_file(cert)?;
+            check_pem_file(key)?;
+            log::info!(
+                "Using NATS TLS client certificate from file {} with key from file {}",
+                cert,
+                key
+            );
+            options = options.add_client_certificate(PathBuf::from(cert), PathBuf::from(key));
+        }
+        (None, None) => (),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "both a NATS TLS client certificate and a NATS TLS client key are required",
+            ));
+        }
+    }
+
+    Ok(options)
+}
+
+/// Checks that the file at the passed path exists and contains PEM encoded data.
+fn check_pem_file(path: &str) -> Result<(), io::Error> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("could not read {}: {}", path, e)))?;
+    if !contents.contains("-----BEGIN ") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} does not contain PEM encoded data", path),
+        ));
+    }
+    Ok(())
 }
 
 #[cfg(feature = "nats_integration_tests")]
@@ -98,7 +422,7 @@ mod tests {
             address: address.clone(),
             username: Some(user.to_string()),
             password: Some("incorrect".to_string()),
-            password_file: None,
+            ..Default::default()
         })
         .unwrap()
         .connect(address)
@@ -134,7 +458,7 @@ mod tests {
             address,
             username: Some(user.to_string()),
             password: Some(pass.to_string()),
-            password_file: None,
+            ..Default::default()
         })
         .expect("using the correct user/password should work");
     }
@@ -159,9 +483,449 @@ mod tests {
         prepare_connection(&NatsArgs {
             address,
             username: Some(user.to_string()),
-            password: None,
             password_file: Some(path.display().to_string()),
+            ..Default::default()
         })
         .expect("using the correct user/password should work");
     }
+
+    #[tokio::test]
+    async fn test_integration_natsutil_tls() {
+        println!("test that a TLS connection to a TLS-enabled NATS server works");
+
+        let nats_server = NatsServerForTesting::new_with_tls().await;
+        let address = format!("127.0.0.1:{}", nats_server.port);
+
+        prepare_connection(&NatsArgs {
+            address: address.clone(),
+            tls: true,
+            tls_ca_file: nats_server.tls_ca_file.clone(),
+            ..Default::default()
+        })
+        .expect("the TLS configuration should be valid")
+        .connect(address)
+        .await
+        .expect("the TLS handshake should work");
+    }
+
+    #[tokio::test]
+    async fn test_integration_natsutil_tls_client_certificate() {
+        println!("test that a TLS connection with a client certificate works");
+
+        let cert_arg = format!("--tlscert={}", fixture_path("tls/server-cert.pem"));
+        let key_arg = format!("--tlskey={}", fixture_path("tls/server-key.pem"));
+        let ca_arg = format!("--tlscacert={}", fixture_path("tls/ca.pem"));
+        let extra_args: Vec<&str> = vec!["--tlsverify", &cert_arg, &key_arg, &ca_arg];
+        let nats_server = NatsServerForTesting::new(&extra_args).await;
+        let address = format!("127.0.0.1:{}", nats_server.port);
+
+        prepare_connection(&NatsArgs {
+            address: address.clone(),
+            tls: true,
+            tls_ca_file: Some(fixture_path("tls/ca.pem")),
+            tls_client_cert: Some(fixture_path("tls/client-cert.pem")),
+            tls_client_key: Some(fixture_path("tls/client-key.pem")),
+            ..Default::default()
+        })
+        .expect("the TLS configuration should be valid")
+        .connect(address)
+        .await
+        .expect("the TLS handshake with a client certificate should work");
+    }
+
+    #[tokio::test]
+    async fn test_integration_natsutil_nkey() {
+        println!("test that authentificating with an NKey seed works");
+
+        let nats_server =
+            NatsServerForTesting::new_with_config(&fixture_path("nats-server-nkey.conf")).await;
+        let address = format!("127.0.0.1:{}", nats_server.port);
+
+        prepare_connection(&NatsArgs {
+            address: address.clone(),
+            nkey_seed_file: Some(fixture_path("test-nkey-seed.txt")),
+            ..Default::default()
+        })
+        .expect("reading the NKey seed should work")
+        .connect(address)
+        .await
+        .expect("authentificating with the NKey should work");
+    }
+
+    #[tokio::test]
+    async fn test_integration_natsutil_token_file() {
+        println!("test that authentificating with a token read from a file works");
+
+        // the token file contains a trailing newline, which is trimmed
+        let token_arg = "--auth=password_read_from_file2";
+        let extra_args: Vec<&str> = vec![token_arg];
+        let nats_server = NatsServerForTesting::new(&extra_args).await;
+        let address = format!("127.0.0.1:{}", nats_server.port);
+
+        prepare_connection(&NatsArgs {
+            address: address.clone(),
+            token_file: Some(fixture_path("test-password-file.txt")),
+            ..Default::default()
+        })
+        .expect("reading the token file should work")
+        .connect(address)
+        .await
+        .expect("authentificating with the token should work");
+    }
+
+    /// Connects to a NATS server, restarts it on the same port, and returns
+    /// the connection state of the client after the restart.
+    async fn connection_state_after_restart(
+        max_reconnects: Option<usize>,
+    ) -> async_nats::connection::State {
+        let nats_server = NatsServerForTesting::new(&[]).await;
+        let port = nats_server.port;
+        let address = format!("127.0.0.1:{}", port);
+
+        let client = prepare_connection(&NatsArgs {
+            address: address.clone(),
+            nats_max_reconnects: max_reconnects,
+            nats_reconnect_delay_ms: Some(100),
+            ..Default::default()
+        })
+        .unwrap()
+        .connect(address)
+        .await
+        .expect("should be able to connect to the NATS server");
+
+        // kill the NATS server and give the client time to use up its reconnects
+        drop(nats_server);
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        let _nats_server = NatsServerForTesting::new_on_port(port, &[]).await;
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        client.connection_state()
+    }
+
+    #[tokio::test]
+    async fn test_integration_natsutil_reconnect_unlimited() {
+        println!("test that the client reconnects to a restarted NATS server");
+
+        assert_eq!(
+            connection_state_after_restart(None).await,
+            async_nats::connection::State::Connected
+        );
+    }
+
+    #[tokio::test]
+    async fn test_integration_natsutil_reconnect_limit() {
+        println!("test that the client gives up after the maximum number of reconnects");
+
+        assert_ne!(
+            connection_state_after_restart(Some(1)).await,
+            async_nats::connection::State::Connected
+        );
+    }
+
+    fn fixture_path(name: &str) -> String {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/fixtures")
+            .join(name)
+            .display()
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::dns_srv::SrvTarget;
+
+    fn nats_args(
+        ca_file: Option<&str>,
+        client_cert: Option<&str>,
+        client_key: Option<&str>,
+    ) -> NatsArgs {
+        NatsArgs {
+            tls: true,
+            tls_ca_file: ca_file.map(|f| f.to_string()),
+            tls_client_cert: client_cert.map(|f| f.to_string()),
+            tls_client_key: client_key.map(|f| f.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_natsutil_tls_missing_ca_file() {
+        let result = prepare_connection(&nats_args(Some("/does/not/exist.pem"), None, None));
+        match result {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+            Ok(_) => panic!("expected an error for a missing CA file"),
+        }
+    }
+
+    #[test]
+    fn test_natsutil_tls_invalid_ca_file() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/fixtures/test-password-file.txt"
+        );
+        let result = prepare_connection(&nats_args(Some(path), None, None));
+        match result {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+            Ok(_) => panic!("expected an error for a non-PEM CA file"),
+        }
+    }
+
+    #[test]
+    fn test_natsutil_tls_client_cert_without_key() {
+        let cert = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/fixtures/tls/client-cert.pem"
+        );
+        let result = prepare_connection(&nats_args(None, Some(cert), None));
+        match result {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+            Ok(_) => panic!("expected an error for a client certificate without a key"),
+        }
+    }
+
+    #[test]
+    fn test_natsutil_tls_valid_files() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/src/fixtures/tls");
+        let ca = format!("{}/ca.pem", dir);
+        let cert = format!("{}/client-cert.pem", dir);
+        let key = format!("{}/client-key.pem", dir);
+        prepare_connection(&nats_args(Some(&ca), Some(&cert), Some(&key)))
+            .expect("valid TLS files should be accepted");
+    }
+
+    #[test]
+    fn test_natsutil_username_and_nkey_conflict() {
+        let mut args = nats_args(None, None, None);
+        args.username = Some("b1tc0in".to_string());
+        args.password = Some("nakam0to".to_string());
+        args.nkey_seed_file = Some("seed.txt".to_string());
+        match prepare_connection(&args) {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+            Ok(_) => panic!("expected an error when both a username and an NKey are set"),
+        }
+    }
+
+    #[test]
+    fn test_natsutil_credentials_file() {
+        let mut args = nats_args(None, None, None);
+        args.credentials_file =
+            Some(concat!(env!("CARGO_MANIFEST_DIR"), "/src/fixtures/test-user.creds").to_string());
+        prepare_connection(&args).expect("parsing the credentials file should work");
+    }
+
+    #[test]
+    fn test_natsutil_credentials_file_missing() {
+        let mut args = nats_args(None, None, None);
+        args.credentials_file = Some("/does/not/exist.creds".to_string());
+        match prepare_connection(&args) {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+            Ok(_) => panic!("expected an error for a missing credentials file"),
+        }
+    }
+
+    #[test]
+    fn test_natsutil_credentials_file_malformed() {
+        let mut args = nats_args(None, None, None);
+        args.credentials_file = Some(
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/src/fixtures/test-password-file.txt"
+            )
+            .to_string(),
+        );
+        match prepare_connection(&args) {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+            Ok(_) => panic!("expected an error for a malformed credentials file"),
+        }
+    }
+
+    #[test]
+    fn test_natsutil_username_and_credentials_file_conflict() {
+        let mut args = nats_args(None, None, None);
+        args.username = Some("b1tc0in".to_string());
+        args.password = Some("nakam0to".to_string());
+        args.credentials_file = Some("user.creds".to_string());
+        match prepare_connection(&args) {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+            Ok(_) => {
+                panic!("expected an error when both a username and a credentials file are set")
+            }
+        }
+    }
+
+    #[test]
+    fn test_natsutil_username_and_token_conflict() {
+        let mut args = nats_args(None, None, None);
+        args.username = Some("b1tc0in".to_string());
+        args.password = Some("nakam0to".to_string());
+        args.token = Some("s3cr3t".to_string());
+        match prepare_connection(&args) {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+            Ok(_) => panic!("expected an error when both a username and a token are set"),
+        }
+    }
+
+    #[test]
+    fn test_natsutil_server_addrs() {
+        let mut args = nats_args(None, None, None);
+        args.address = "127.0.0.1:4222".to_string();
+        assert_eq!(server_addrs(&args).unwrap().len(), 1);
+
+        args.address = "nats1:4222,nats2:4222, nats3:4222".to_string();
+        let addrs = server_addrs(&args).unwrap();
+        assert_eq!(addrs.len(), 3);
+        assert_eq!(addrs[0].host(), "nats1");
+        assert_eq!(addrs[2].host(), "nats3");
+        assert_eq!(addrs[2].port(), 4222);
+
+        args.address = "nats1:4222,,nats2:4222,".to_string();
+        assert_eq!(server_addrs(&args).unwrap().len(), 2);
+    }
+
+    struct StubResolver;
+
+    impl SrvResolver for StubResolver {
+        fn resolve(&self, name: &str) -> Result<Vec<SrvTarget>, io::Error> {
+            let target = |priority, weight, port, host: &str| SrvTarget {
+                priority,
+                weight,
+                port,
+                host: host.to_string(),
+            };
+            match name {
+                "_nats._tcp.example.com" => Ok(vec![
+                    target(20, 0, 4224, "backup.example.com"),
+                    target(10, 1, 4223, "nats2.example.com"),
+                    target(10, 5, 4222, "nats1.example.com"),
+                ]),
+                "_nats._tcp.other.example.com" => Ok(vec![target(0, 0, 4222, "other.example.com")]),
+                _ => Err(io::Error::new(io::ErrorKind::NotFound, "no such name")),
+            }
+        }
+    }
+
+    #[test]
+    fn test_natsutil_server_addrs_srv() {
+        let mut args = nats_args(None, None, None);
+        args.srv = true;
+        args.address = "_nats._tcp.example.com, _nats._tcp.other.example.com".to_string();
+        let addrs: Vec<(String, u16)> = server_addrs_with_resolver(&args, &StubResolver)
+            .unwrap()
+            .iter()
+            .map(|addr| (addr.host().to_string(), addr.port()))
+            .collect();
+        assert_eq!(
+            addrs,
+            vec![
+                ("nats1.example.com".to_string(), 4222),
+                ("nats2.example.com".to_string(), 4223),
+                ("backup.example.com".to_string(), 4224),
+                ("other.example.com".to_string(), 4222),
+            ]
+        );
+
+        args.address = "_nats._tcp.example.com,_nats._tcp.missing.example.com".to_string();
+        match server_addrs_with_resolver(&args, &StubResolver) {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+            Ok(_) => panic!("expected an error for an unresolvable SRV name"),
+        }
+
+        // without --nats-srv, the address isn't resolved
+        args.srv = false;
+        args.address = "nats1.example.com:4222".to_string();
+        let addrs = server_addrs_with_resolver(&args, &StubResolver).unwrap();
+        assert_eq!(addrs[0].host(), "nats1.example.com");
+    }
+
+    #[test]
+    fn test_natsutil_server_addrs_empty() {
+        let mut args = nats_args(None, None, None);
+        for address in ["", ",", " , ,"] {
+            args.address = address.to_string();
+            match server_addrs(&args) {
+                Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+                Ok(_) => panic!("expected an error for address '{}'", address),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_natsutil_connect_timeout() {
+        // A listener that accepts TCP connections, but never sends the NATS
+        // INFO message, causes the connection attempt to hang.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut args = nats_args(None, None, None);
+        args.address = listener.local_addr().unwrap().to_string();
+        args.tls = false;
+        args.nats_connect_timeout_secs = 1;
+
+        let start = std::time::Instant::now();
+        let result = prepare_connection(&args)
+            .unwrap()
+            .connect(server_addrs(&args).unwrap())
+            .await;
+
+        match result {
+            Err(e) => assert!(
+                matches!(e.kind(), async_nats::ConnectErrorKind::TimedOut),
+                "unexpected error kind: {e:?}"
+            ),
+            Ok(_) => panic!("expected the connection attempt to time out"),
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_natsutil_default_args() {
+        let args = NatsArgs::default();
+        let parsed = NatsArgs::parse_from(["test"]);
+        assert_eq!(args.address, parsed.address);
+        assert_eq!(args.nats_connect_timeout_secs, 10);
+        assert_eq!(
+            args.nats_connect_timeout_secs,
+            parsed.nats_connect_timeout_secs
+        );
+        assert_eq!(args.nats_pending_buffer_bytes, None);
+        assert_eq!(
+            args.nats_pending_buffer_bytes,
+            parsed.nats_pending_buffer_bytes
+        );
+    }
+
+    #[test]
+    fn test_natsutil_pending_buffer() {
+        assert!(pending_buffer(&NatsArgs::default()).is_none());
+        let parsed = NatsArgs::parse_from(["test", "--nats-pending-buffer-bytes", "100"]);
+        let buffer = pending_buffer(&parsed).expect("the buffer should be limited");
+        assert!(buffer.reserve(async_nats::connection::State::Disconnected, 100));
+        assert!(!buffer.reserve(async_nats::connection::State::Disconnected, 1));
+    }
+
+    #[test]
+    fn test_natsutil_username_without_password() {
+        let mut args = nats_args(None, None, None);
+        args.username = Some("b1tc0in".to_string());
+        match prepare_connection(&args) {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+            Ok(_) => panic!("expected an error when a username is set without a password"),
+        }
+    }
+
+    #[test]
+    fn test_natsutil_connection_name() {
+        let mut args = nats_args(None, None, None);
+        let default_name = connection_name(&args);
+        assert!(
+            default_name.starts_with("peer-observer-"),
+            "unexpected default connection name: {}",
+            default_name
+        );
+        assert!(default_name.len() > "peer-observer-".len());
+
+        args.connection_name = Some("rpc-extractor-node1".to_string());
+        assert_eq!(connection_name(&args), "rpc-extractor-node1");
+    }
 }
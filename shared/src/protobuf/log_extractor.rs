@@ -29,6 +29,26 @@ impl fmt::Display for BlockCheckedLog {
     }
 }
 
+impl fmt::Display for UpdateTipLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "UpdateTip(hash={}, height={}, log2_work={}, tx_count={})",
+            self.block_hash, self.block_height, self.log2_work, self.tx_count
+        )
+    }
+}
+
+impl fmt::Display for MisbehavingLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Misbehaving(peer_id={}, message={})",
+            self.peer_id, self.message
+        )
+    }
+}
+
 impl fmt::Display for log::LogEvent {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -37,6 +57,8 @@ impl fmt::Display for log::LogEvent {
             log::LogEvent::BlockCheckedLog(block) => {
                 write!(f, "{}", block)
             }
+            log::LogEvent::UpdateTipLog(tip) => write!(f, "{}", tip),
+            log::LogEvent::MisbehavingLog(misbehaving) => write!(f, "{}", misbehaving),
         }
     }
 }
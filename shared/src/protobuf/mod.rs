@@ -15,3 +15,6 @@ pub mod rpc_extractor;
 
 /// Protobuf types for log-extractor events.
 pub mod log_extractor;
+
+/// Protobuf types for zmq-extractor events.
+pub mod zmq_extractor;
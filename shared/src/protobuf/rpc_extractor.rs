@@ -1,14 +1,30 @@
 use corepc_client::types::v17::{
+    Banned as RPCBanned, ChainTips as RPCChainTip, ChainTipsStatus as RPCChainTipsStatus,
+    EstimateSmartFee as RPCEstimateSmartFee, GetChainTips as RPCGetChainTips,
     GetMemoryInfoStats as RPCGetMemoryInfoStats, GetNetTotals as RPCGetNetTotals,
+    GetRawMempool as RPCGetRawMempool, ListBanned as RPCListBanned,
     UploadTarget as RPCUploadTarget,
 };
 use corepc_client::types::v19::GetChainTxStats as RPCGetChainTxStats;
+use corepc_client::types::v21::{
+    GetIndexInfo as RPCGetIndexInfo, GetIndexInfoName as RPCGetIndexInfoName,
+};
+use corepc_client::types::v22::{
+    GetNodeAddresses as RPCGetNodeAddresses, NodeAddress as RPCNodeAddress,
+};
+use corepc_client::types::v23::{
+    DeploymentInfo as RPCDeploymentInfo, GetDeploymentInfo as RPCGetDeploymentInfo,
+};
+use corepc_client::types::v24::{
+    GetRawMempoolVerbose as RPCGetRawMempoolVerbose, MempoolEntry as RPCMempoolEntry,
+};
 use corepc_client::types::v26::{
     AddrManInfoNetwork as RPCAddrManInfoNetwork, GetAddrManInfo as RPCGetAddrManInfo,
-    GetMempoolInfo, GetPeerInfo as RPCGetPeerInfo, PeerInfo as RPCPeerInfo,
+    GetMempoolInfo, GetPeerInfo as RPCGetPeerInfo, GetTxOutSetInfo as RPCGetTxOutSetInfo,
+    PeerInfo as RPCPeerInfo,
 };
 use corepc_client::types::v28::{GetNetworkInfo, GetNetworkInfoAddress, GetNetworkInfoNetwork};
-use corepc_client::types::v29::GetBlockchainInfo;
+use corepc_client::types::v29::{GetBlockchainInfo, GetMiningInfo};
 use std::fmt;
 
 // structs are generated via the rpc_extractor.proto file
@@ -47,6 +63,17 @@ impl fmt::Display for rpc::RpcEvent {
             rpc::RpcEvent::ChainTxStats(stats) => write!(f, "{}", stats),
             rpc::RpcEvent::NetworkInfo(info) => write!(f, "{}", info),
             rpc::RpcEvent::BlockchainInfo(info) => write!(f, "{}", info),
+            rpc::RpcEvent::BlockCount(count) => write!(f, "BlockCount({})", count),
+            rpc::RpcEvent::MiningInfo(info) => write!(f, "{}", info),
+            rpc::RpcEvent::RawMempool(mempool) => write!(f, "{}", mempool),
+            rpc::RpcEvent::ChainTips(tips) => write!(f, "{}", tips),
+            rpc::RpcEvent::NodeAddresses(addresses) => write!(f, "{}", addresses),
+            rpc::RpcEvent::Banned(banned) => write!(f, "{}", banned),
+            rpc::RpcEvent::FeeEstimates(estimates) => write!(f, "{}", estimates),
+            rpc::RpcEvent::IndexInfo(info) => write!(f, "{}", info),
+            rpc::RpcEvent::DeploymentInfo(info) => write!(f, "{}", info),
+            rpc::RpcEvent::TxOutSetInfo(info) => write!(f, "{}", info),
+            rpc::RpcEvent::Reorg(reorg) => write!(f, "{}", reorg),
         }
     }
 }
@@ -375,3 +402,317 @@ impl fmt::Display for BlockchainInfo {
         )
     }
 }
+
+impl From<GetMiningInfo> for MiningInfo {
+    fn from(info: GetMiningInfo) -> Self {
+        MiningInfo {
+            blocks: info.blocks as u64,
+            current_block_weight: info.current_block_weight.map(|w| w as u64),
+            current_block_tx: info.current_block_tx.map(|t| t as u64),
+            difficulty: info.difficulty,
+            network_hash_ps: info.network_hash_ps,
+            pooled_tx: info.pooled_tx as u64,
+            chain: info.chain,
+            warnings: info.warnings,
+        }
+    }
+}
+
+impl fmt::Display for MiningInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MiningInfo(chain={}, blocks={}, difficulty={}, networkhashps={})",
+            self.chain, self.blocks, self.difficulty, self.network_hash_ps
+        )
+    }
+}
+
+impl From<RPCGetRawMempool> for RawMempool {
+    fn from(mempool: RPCGetRawMempool) -> Self {
+        RawMempool {
+            txids: mempool.0,
+            entries: Default::default(),
+        }
+    }
+}
+
+impl From<RPCGetRawMempoolVerbose> for RawMempool {
+    fn from(mempool: RPCGetRawMempoolVerbose) -> Self {
+        RawMempool {
+            txids: vec![],
+            entries: mempool.0.into_iter().map(|(k, v)| (k, v.into())).collect(),
+        }
+    }
+}
+
+impl From<RPCMempoolEntry> for MempoolEntry {
+    fn from(entry: RPCMempoolEntry) -> Self {
+        MempoolEntry {
+            vsize: entry.vsize as u64,
+            weight: entry.weight as u64,
+            time: entry.time,
+            height: entry.height as u64,
+            descendant_count: entry.descendant_count as u64,
+            descendant_size: entry.descendant_size as u64,
+            ancestor_count: entry.ancestor_count as u64,
+            ancestor_size: entry.ancestor_size as u64,
+            wtxid: entry.wtxid,
+            fee_base: entry.fees.base,
+            fee_modified: entry.fees.modified,
+            fee_ancestor: entry.fees.ancestor,
+            fee_descendant: entry.fees.descendant,
+            depends: entry.depends,
+            spent_by: entry.spent_by,
+            bip125_replaceable: entry.bip125_replaceable,
+            unbroadcast: entry.unbroadcast,
+        }
+    }
+}
+
+impl fmt::Display for RawMempool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RawMempool(txids={}, entries={})",
+            self.txids.len(),
+            self.entries.len()
+        )
+    }
+}
+
+impl From<RPCGetChainTips> for ChainTips {
+    fn from(tips: RPCGetChainTips) -> Self {
+        ChainTips {
+            tips: tips.0.into_iter().map(|t| t.into()).collect(),
+        }
+    }
+}
+
+impl From<RPCChainTip> for ChainTip {
+    fn from(tip: RPCChainTip) -> Self {
+        let status = match tip.status {
+            RPCChainTipsStatus::Invalid => "invalid",
+            RPCChainTipsStatus::HeadersOnly => "headers-only",
+            RPCChainTipsStatus::ValidHeaders => "valid-headers",
+            RPCChainTipsStatus::ValidFork => "valid-fork",
+            RPCChainTipsStatus::Active => "active",
+        };
+        ChainTip {
+            height: tip.height as u64,
+            hash: tip.hash,
+            branch_length: tip.branch_length as u64,
+            status: status.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ChainTips {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tip_strs: Vec<String> = self.tips.iter().map(|t| t.to_string()).collect();
+        write!(f, "ChainTips([{}])", tip_strs.join(", "))
+    }
+}
+
+impl fmt::Display for ChainTip {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ChainTip(height={}, status={}, branch_length={})",
+            self.height, self.status, self.branch_length
+        )
+    }
+}
+
+impl From<RPCGetNodeAddresses> for NodeAddresses {
+    fn from(addresses: RPCGetNodeAddresses) -> Self {
+        NodeAddresses {
+            addresses: addresses.0.into_iter().map(|a| a.into()).collect(),
+        }
+    }
+}
+
+impl From<RPCNodeAddress> for NodeAddress {
+    fn from(address: RPCNodeAddress) -> Self {
+        NodeAddress {
+            time: address.time as u64,
+            services: address.services as u64,
+            address: address.address,
+            port: address.port as u32,
+            network: address.network,
+        }
+    }
+}
+
+impl fmt::Display for NodeAddresses {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NodeAddresses(count={})", self.addresses.len())
+    }
+}
+
+impl From<RPCListBanned> for Banned {
+    fn from(banned: RPCListBanned) -> Self {
+        Banned {
+            subnets: banned.0.into_iter().map(|b| b.into()).collect(),
+        }
+    }
+}
+
+impl From<RPCBanned> for BannedSubnet {
+    fn from(banned: RPCBanned) -> Self {
+        BannedSubnet {
+            address: banned.address,
+            ban_created: banned.ban_created as u64,
+            banned_until: banned.banned_until as u64,
+        }
+    }
+}
+
+impl fmt::Display for Banned {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Banned(count={})", self.subnets.len())
+    }
+}
+
+impl FeeEstimate {
+    /// Creates a fee estimate for the confirmation `target` from an
+    /// estimatesmartfee response, which doesn't include the target itself.
+    pub fn new(target: u32, estimate: RPCEstimateSmartFee) -> Self {
+        FeeEstimate {
+            target,
+            fee_rate: estimate.fee_rate,
+            errors: estimate.errors.unwrap_or_default(),
+            blocks: estimate.blocks as u32,
+        }
+    }
+}
+
+impl fmt::Display for FeeEstimates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let estimate_strs: Vec<String> = self.estimates.iter().map(|e| e.to_string()).collect();
+        write!(f, "FeeEstimates([{}])", estimate_strs.join(", "))
+    }
+}
+
+impl fmt::Display for FeeEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.fee_rate {
+            Some(fee_rate) => write!(
+                f,
+                "FeeEstimate(target={}, fee_rate={}BTC/kvB)",
+                self.target, fee_rate
+            ),
+            None => write!(f, "FeeEstimate(target={}, fee_rate=none)", self.target),
+        }
+    }
+}
+
+impl From<RPCGetIndexInfo> for IndexInfo {
+    fn from(info: RPCGetIndexInfo) -> Self {
+        let indices = info.0.into_iter().map(|(k, v)| (k, v.into())).collect();
+
+        IndexInfo { indices }
+    }
+}
+
+impl From<RPCGetIndexInfoName> for IndexInfoIndex {
+    fn from(index: RPCGetIndexInfoName) -> Self {
+        IndexInfoIndex {
+            synced: index.synced,
+            best_block_height: index.best_block_height as u32,
+        }
+    }
+}
+
+impl fmt::Display for IndexInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let index_strs: Vec<String> = self
+            .indices
+            .iter()
+            .map(|(name, index)| {
+                format!(
+                    "{}(synced={}, height={})",
+                    name, index.synced, index.best_block_height
+                )
+            })
+            .collect();
+        write!(f, "IndexInfo([{}])", index_strs.join(", "))
+    }
+}
+
+impl From<RPCGetDeploymentInfo> for DeploymentInfo {
+    fn from(info: RPCGetDeploymentInfo) -> Self {
+        DeploymentInfo {
+            hash: info.hash,
+            height: info.height as u32,
+            deployments: info
+                .deployments
+                .into_iter()
+                .map(|(k, v)| (k, v.into()))
+                .collect(),
+        }
+    }
+}
+
+impl From<RPCDeploymentInfo> for Deployment {
+    fn from(deployment: RPCDeploymentInfo) -> Self {
+        Deployment {
+            r#type: deployment.deployment_type,
+            active: deployment.active,
+            height: deployment.height.map(|h| h as u32),
+            bip9_status: deployment.bip9.as_ref().map(|b| b.status.clone()),
+            bip9_since: deployment.bip9.as_ref().map(|b| b.since as u32),
+        }
+    }
+}
+
+impl fmt::Display for DeploymentInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let active: Vec<&str> = self
+            .deployments
+            .iter()
+            .filter(|(_, d)| d.active)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        write!(
+            f,
+            "DeploymentInfo(height={}, active=[{}])",
+            self.height,
+            active.join(", ")
+        )
+    }
+}
+
+impl From<RPCGetTxOutSetInfo> for TxOutSetInfo {
+    fn from(info: RPCGetTxOutSetInfo) -> Self {
+        TxOutSetInfo {
+            height: info.height as u64,
+            bestblock: info.best_block,
+            txouts: info.tx_outs as u64,
+            bogosize: info.bogo_size as u64,
+            transactions: info.transactions.map(|t| t as u64),
+            disk_size: info.disk_size.map(|s| s as u64),
+            total_amount: info.total_amount,
+        }
+    }
+}
+
+impl fmt::Display for TxOutSetInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TxOutSetInfo(height={}, txouts={}, total_amount={}BTC)",
+            self.height, self.txouts, self.total_amount
+        )
+    }
+}
+
+impl fmt::Display for Reorg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Reorg(old={} at {}, new={} at {})",
+            self.old_best_block_hash, self.old_height, self.new_best_block_hash, self.new_height
+        )
+    }
+}
@@ -0,0 +1,60 @@
+use bitcoin::hex::DisplayHex;
+use std::fmt;
+
+// structs are generated via the zmq_extractor.proto file
+include!(concat!(env!("OUT_DIR"), "/zmq_extractor.rs"));
+
+impl fmt::Display for RawTx {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RawTx(size={}, sequence={})",
+            self.tx.len(),
+            self.sequence
+        )
+    }
+}
+
+impl fmt::Display for RawBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "RawBlock(size={}, sequence={})",
+            self.block.len(),
+            self.sequence
+        )
+    }
+}
+
+impl fmt::Display for HashBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "HashBlock(hash={}, sequence={})",
+            self.hash.to_lower_hex_string(),
+            self.sequence
+        )
+    }
+}
+
+impl fmt::Display for HashTx {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "HashTx(txid={}, sequence={})",
+            self.txid.to_lower_hex_string(),
+            self.sequence
+        )
+    }
+}
+
+impl fmt::Display for zmq::ZmqEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            zmq::ZmqEvent::RawTx(tx) => write!(f, "{}", tx),
+            zmq::ZmqEvent::RawBlock(block) => write!(f, "{}", block),
+            zmq::ZmqEvent::HashBlock(hash) => write!(f, "{}", hash),
+            zmq::ZmqEvent::HashTx(txid) => write!(f, "{}", txid),
+        }
+    }
+}
@@ -0,0 +1,721 @@
+use flate2::read::GzDecoder;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Timeout for connecting to, writing to, and reading from the metrics server.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Interval in which [fetch_metrics_until] scrapes the metrics.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An HTTP response of a metrics server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MetricsResponse {
+    /// Returns the value of the first header with the `name`, compared
+    /// case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Fetches the Prometheus metrics served on the `port` at `path` by a
+/// metrics server on localhost. Returns only the response body, with a
+/// `Transfer-Encoding: chunked` body decoded.
+pub fn fetch_metrics(port: u16, path: &str) -> Result<String, io::Error> {
+    fetch_metrics_with_timeout(port, path, DEFAULT_TIMEOUT)
+}
+
+/// Like [fetch_metrics], but fails if connecting to the metrics server or
+/// writing and reading the request and response take longer than `timeout`.
+pub fn fetch_metrics_with_timeout(
+    port: u16,
+    path: &str,
+    timeout: Duration,
+) -> Result<String, io::Error> {
+    Ok(parse_response(&read_response(port, path, timeout, &[])?)?.body)
+}
+
+/// Scrapes the metrics every 100 milliseconds until the `predicate` holds for
+/// the metrics body and returns that body. Failed scrapes, e.g. while the
+/// metrics server is still starting, are retried. Returns a `TimedOut` error
+/// if the predicate doesn't hold within `max_wait`.
+pub async fn fetch_metrics_until<F>(
+    port: u16,
+    path: &str,
+    predicate: F,
+    max_wait: Duration,
+) -> Result<String, io::Error>
+where
+    F: Fn(&str) -> bool,
+{
+    let deadline = Instant::now() + max_wait;
+    loop {
+        let last = match fetch_metrics(port, path) {
+            Ok(metrics) if predicate(&metrics) => return Ok(metrics),
+            Ok(_) => "the condition didn't hold".to_string(),
+            Err(e) => format!("the last scrape failed: {}", e),
+        };
+        if Instant::now() >= deadline {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "metrics on port {} at '{}' not as expected within {:?}: {}",
+                    port, path, max_wait, last
+                ),
+            ));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Fetches the `path` from a metrics server on localhost at the `port` and
+/// returns the status, headers, and decoded body of the HTTP response.
+pub fn fetch_metrics_response(port: u16, path: &str) -> Result<MetricsResponse, io::Error> {
+    fetch_metrics_response_with_headers(port, path, &[])
+}
+
+/// Like [fetch_metrics_response], but sends an `Accept-Encoding: gzip`
+/// header. A `Content-Encoding: gzip` body is decompressed, while the
+/// response headers are kept as received.
+pub fn fetch_metrics_response_gzip(port: u16, path: &str) -> Result<MetricsResponse, io::Error> {
+    fetch_metrics_response_with_headers(port, path, &[("Accept-Encoding", "gzip")])
+}
+
+/// Like [fetch_metrics_response], but sends the additional request
+/// `headers`, e.g. an `Authorization` header.
+pub fn fetch_metrics_response_with_headers(
+    port: u16,
+    path: &str,
+    headers: &[(&str, &str)],
+) -> Result<MetricsResponse, io::Error> {
+    parse_response(&read_response(port, path, DEFAULT_TIMEOUT, headers)?)
+}
+
+fn read_response(
+    port: u16,
+    path: &str,
+    timeout: Duration,
+    headers: &[(&str, &str)],
+) -> Result<Vec<u8>, io::Error> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    log::debug!("fetching metrics from {}{}", addr, path);
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}\r\n", name, value))
+        .collect();
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\n{}Connection: close\r\n\r\n",
+        path, addr, headers
+    );
+    stream.write_all(request.as_bytes())?;
+    stream.flush()?;
+
+    // Read the full response until EOF (server closes the connection).
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    Ok(response)
+}
+
+/// Parses the raw HTTP `response` into its status, headers, and body. A
+/// chunked body is de-framed and a gzip-encoded body is decompressed.
+fn parse_response(response: &[u8]) -> Result<MetricsResponse, io::Error> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let header_end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| invalid("incomplete HTTP response headers"))?;
+    let head = String::from_utf8_lossy(&response[..header_end]);
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| invalid("invalid HTTP status line"))?;
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    let body = &response[header_end + 4..];
+    let chunked = headers.iter().any(|(name, value)| {
+        name.eq_ignore_ascii_case("transfer-encoding")
+            && value.to_ascii_lowercase().contains("chunked")
+    });
+    let body = if chunked {
+        decode_chunked(body).ok_or_else(|| invalid("invalid chunked HTTP response body"))?
+    } else {
+        body.to_vec()
+    };
+    let gzipped = headers.iter().any(|(name, value)| {
+        name.eq_ignore_ascii_case("content-encoding") && value.eq_ignore_ascii_case("gzip")
+    });
+    let body = if gzipped {
+        let mut decoded = Vec::new();
+        GzDecoder::new(&body[..])
+            .read_to_end(&mut decoded)
+            .map_err(|e| invalid(&format!("invalid gzip HTTP response body: {}", e)))?;
+        decoded
+    } else {
+        body
+    };
+    Ok(MetricsResponse {
+        status,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+/// Decodes a chunked HTTP body: each chunk is prefixed with its size in hex
+/// and the body ends with a zero-sized chunk.
+fn decode_chunked(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    loop {
+        let line_end = body.windows(2).position(|w| w == b"\r\n")?;
+        let size_line = std::str::from_utf8(&body[..line_end]).ok()?;
+        // chunk extensions after a ';' are ignored
+        let size = usize::from_str_radix(size_line.split(';').next()?.trim(), 16).ok()?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Some(decoded);
+        }
+        decoded.extend_from_slice(body.get(..size)?);
+        body = body.get(size..)?.strip_prefix(b"\r\n")?;
+    }
+}
+
+/// Returns the value of the counter `metric_name` with the `rpc_method` label
+/// from the raw Prometheus metrics. Panics if there is no such counter.
+pub fn get_counter_value(metrics_raw: &str, metric_name: &str, rpc_method: &str) -> u64 {
+    get_counter_value_labeled(metrics_raw, metric_name, "rpc_method", rpc_method)
+}
+
+/// Returns the value of the counter `metric_name` with the single label
+/// `label_name` set to `label_value` from the raw Prometheus metrics. Panics
+/// if there is no such counter.
+pub fn get_counter_value_labeled(
+    metrics_raw: &str,
+    metric_name: &str,
+    label_name: &str,
+    label_value: &str,
+) -> u64 {
+    let pattern = format!("{}{{{}=\"{}\"}} ", metric_name, label_name, label_value);
+    parse_value(metrics_raw, &pattern)
+}
+
+/// Returns the sum of the counter `metric_name` over all samples with the
+/// `labels`, e.g. over all `error_kind`s of an `rpc_method`. Returns 0 if
+/// there is no such sample.
+pub fn get_counter_sum(metrics_raw: &str, metric_name: &str, labels: &[(&str, &str)]) -> u64 {
+    parse_metrics(metrics_raw)
+        .iter()
+        .filter(|sample| {
+            sample.name == metric_name
+                && labels.iter().all(|(name, value)| {
+                    sample.labels.get(*name).map(String::as_str) == Some(*value)
+                })
+        })
+        .map(|sample| sample.value as u64)
+        .sum()
+}
+
+/// Returns the number of observations of the histogram `metric_name` with the
+/// `rpc_method` label from the raw Prometheus metrics. Panics if there is no
+/// such histogram.
+pub fn get_histogram_count(metrics_raw: &str, metric_name: &str, rpc_method: &str) -> u64 {
+    get_histogram_count_labeled(metrics_raw, metric_name, "rpc_method", rpc_method)
+}
+
+/// Returns the number of observations of the histogram `metric_name` with the
+/// single label `label_name` set to `label_value` from the raw Prometheus
+/// metrics. Panics if there is no such histogram.
+pub fn get_histogram_count_labeled(
+    metrics_raw: &str,
+    metric_name: &str,
+    label_name: &str,
+    label_value: &str,
+) -> u64 {
+    let pattern = format!(
+        "{}_count{{{}=\"{}\"}} ",
+        metric_name, label_name, label_value
+    );
+    parse_value(metrics_raw, &pattern)
+}
+
+/// Returns the sum of the observations of the histogram (or summary)
+/// `metric_name` with the `rpc_method` label from the raw Prometheus
+/// metrics. Panics if there is no such histogram.
+pub fn get_histogram_sum(metrics_raw: &str, metric_name: &str, rpc_method: &str) -> f64 {
+    let pattern = format!("{}_sum{{rpc_method=\"{}\"}} ", metric_name, rpc_method);
+    parse_value(metrics_raw, &pattern)
+}
+
+/// Returns the value of the gauge `metric_name` with a label set to
+/// `label_value` (e.g. an `rpc_method` or a `node_id`) from the raw Prometheus
+/// metrics. The value is the last whitespace-separated token of the line.
+/// Panics if there is no such gauge.
+pub fn get_gauge_value(metrics_raw: &str, metric_name: &str, label_value: &str) -> f64 {
+    let prefix = format!("{}{{", metric_name);
+    let label = format!("=\"{}\"", label_value);
+    let line = metrics_raw
+        .lines()
+        .find(|line| {
+            line.strip_prefix(&prefix)
+                .and_then(|rest| rest.split_once('}'))
+                .is_some_and(|(labels, _)| labels.contains(&label))
+        })
+        .unwrap_or_else(|| {
+            panic!(
+                "could not find the gauge '{}' with the label value '{}'",
+                metric_name, label_value
+            )
+        });
+    let value = line.split_whitespace().last().unwrap_or_default();
+    value
+        .parse()
+        .unwrap_or_else(|e| panic!("could not parse the value '{}' of '{}': {}", value, line, e))
+}
+
+/// A single sample of a metric in the Prometheus text exposition format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricSample {
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    pub value: f64,
+}
+
+/// Parses the raw Prometheus metrics into their samples. `# HELP` and
+/// `# TYPE` comments and empty lines are skipped, as are the status line and
+/// headers if the raw metrics are a full HTTP response. Panics on a malformed
+/// sample line.
+pub fn parse_metrics(metrics_raw: &str) -> Vec<MetricSample> {
+    let body = match metrics_raw.split_once("\r\n\r\n") {
+        Some((_, body)) if metrics_raw.starts_with("HTTP/") => body,
+        _ => metrics_raw,
+    };
+    body.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            parse_sample(line)
+                .unwrap_or_else(|| panic!("could not parse the metric sample '{}'", line))
+        })
+        .collect()
+}
+
+/// Parses a sample line `name{label="value",...} value [timestamp]`.
+fn parse_sample(line: &str) -> Option<MetricSample> {
+    let name_end = line.find(['{', ' ']).unwrap_or(line.len());
+    let name = &line[..name_end];
+    if name.is_empty() {
+        return None;
+    }
+    let mut rest = &line[name_end..];
+    let mut labels = BTreeMap::new();
+    if let Some(mut label_rest) = rest.strip_prefix('{') {
+        loop {
+            label_rest = label_rest.trim_start_matches([',', ' ']);
+            if let Some(after) = label_rest.strip_prefix('}') {
+                rest = after;
+                break;
+            }
+            let (label_name, after) = label_rest.split_once("=\"")?;
+            let (label_value, after) = parse_label_value(after)?;
+            labels.insert(label_name.trim().to_string(), label_value);
+            label_rest = after;
+        }
+    }
+    let value = rest.split_whitespace().next()?.parse().ok()?;
+    Some(MetricSample {
+        name: name.to_string(),
+        labels,
+        value,
+    })
+}
+
+/// Parses an escaped label value up to its closing quote. Returns the value
+/// and the remaining input after the quote.
+fn parse_label_value(input: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &input[i + 1..])),
+            '\\' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                escaped => value.push(escaped),
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+fn parse_value<T: FromStr>(metrics_raw: &str, pattern: &str) -> T
+where
+    T::Err: fmt::Display,
+{
+    metrics_raw
+        .lines()
+        .find_map(|line| line.strip_prefix(pattern))
+        .unwrap_or_else(|| panic!("could not find the metric '{}'", pattern.trim_end()))
+        .trim()
+        .parse()
+        .unwrap_or_else(|e| panic!("could not parse the value of '{}': {}", pattern, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    const METRICS: &str = "# HELP rpcextractor_rpc_fetch_errors Number of failed RPC calls.
+# TYPE rpcextractor_rpc_fetch_errors counter
+rpcextractor_rpc_fetch_errors{rpc_method=\"getpeerinfo\"} 3
+rpcextractor_rpc_fetch_errors{rpc_method=\"uptime\"} 12
+# HELP rpcextractor_rpc_fetch_duration_seconds Duration of RPC calls.
+# TYPE rpcextractor_rpc_fetch_duration_seconds histogram
+rpcextractor_rpc_fetch_duration_seconds_bucket{rpc_method=\"uptime\",le=\"0.01\"} 4
+rpcextractor_rpc_fetch_duration_seconds_bucket{rpc_method=\"uptime\",le=\"+Inf\"} 5
+rpcextractor_rpc_fetch_duration_seconds_sum{rpc_method=\"uptime\"} 0.042
+rpcextractor_rpc_fetch_duration_seconds_count{rpc_method=\"uptime\"} 5
+# HELP rpcextractor_rpc_last_success_timestamp_seconds UNIX epoch timestamp of the last successful RPC call.
+# TYPE rpcextractor_rpc_last_success_timestamp_seconds gauge
+rpcextractor_rpc_last_success_timestamp_seconds{rpc_method=\"uptime\"} 1700000000.5
+rpcextractor_rpc_last_success_timestamp_seconds{node_id=\"node2\",rpc_method=\"getpeerinfo\"} 1700000001
+# HELP rpcextractor_mempool_size Number of transactions in the mempool.
+# TYPE rpcextractor_mempool_size gauge
+rpcextractor_mempool_size{node_id=\"node1\"} -1e3
+# HELP p2pextractor_messages Number of P2P messages.
+# TYPE p2pextractor_messages counter
+p2pextractor_messages{direction=\"inbound\"} 7
+p2pextractor_messages{direction=\"outbound\"} 9
+# HELP rpcextractor_index_sync_duration_seconds Duration of index syncs.
+# TYPE rpcextractor_index_sync_duration_seconds histogram
+rpcextractor_index_sync_duration_seconds_sum{index=\"txindex\"} 1.5
+rpcextractor_index_sync_duration_seconds_count{index=\"txindex\"} 2
+";
+
+    #[test]
+    fn test_fetch_metrics_with_timeout_not_listening() {
+        // bind and drop a listener to get a port nothing listens on
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let start = Instant::now();
+        assert!(fetch_metrics_with_timeout(port, "/metrics", Duration::from_millis(200)).is_err());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_fetch_metrics_with_timeout_no_response() {
+        // the connection is accepted by the OS, but never answered
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let start = Instant::now();
+        let result = fetch_metrics_with_timeout(port, "/metrics", Duration::from_millis(200));
+        assert!(result.is_err());
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_metrics_until_deadline() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let start = Instant::now();
+        let e = fetch_metrics_until(port, "/metrics", |_| true, Duration::from_millis(300))
+            .await
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+        assert!(start.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_parse_response_ok() {
+        let response =
+            b"HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\ncontent-length: 5\r\n\r\nup 1\n";
+        let response = parse_response(response).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.headers,
+            vec![
+                ("content-type".to_string(), "text/plain".to_string()),
+                ("content-length".to_string(), "5".to_string()),
+            ]
+        );
+        assert_eq!(response.header("Content-Type"), Some("text/plain"));
+        assert_eq!(response.body, "up 1\n");
+
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\n").is_err());
+        assert!(parse_response(b"garbage\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_response_not_found() {
+        let response = b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n";
+        let response = parse_response(response).unwrap();
+        assert_eq!(response.status, 404);
+        assert_eq!(response.header("content-length"), Some("0"));
+        assert_eq!(response.header("content-type"), None);
+        assert!(response.body.is_empty());
+    }
+
+    #[test]
+    fn test_parse_response_chunked() {
+        let response = b"HTTP/1.1 200 OK\r\n\
+            Content-Type: text/plain\r\n\
+            Transfer-Encoding: chunked\r\n\r\n\
+            1c\r\nrpcextractor_nats_connected \r\n\
+            3;ext=1\r\n1\nu\r\n\
+            4\r\np 1\n\r\n\
+            0\r\n\r\n";
+        let body = parse_response(response).unwrap().body;
+        assert_eq!(body, "rpcextractor_nats_connected 1\nup 1\n");
+        let samples = parse_metrics(&body);
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].value, 1.0);
+
+        // a chunk shorter than its announced size
+        let truncated =
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\na\r\nup 1\r\n0\r\n\r\n";
+        assert!(parse_response(truncated).is_err());
+    }
+
+    #[test]
+    fn test_parse_response_gzip() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"up 1\n").unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut response =
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Encoding: gzip\r\n\r\n"
+                .to_vec();
+        response.extend_from_slice(&compressed);
+        let response = parse_response(&response).unwrap();
+        assert_eq!(response.header("content-encoding"), Some("gzip"));
+        assert_eq!(response.body, "up 1\n");
+
+        assert!(
+            parse_response(b"HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\n\r\nup 1\n").is_err()
+        );
+    }
+
+    #[test]
+    fn test_get_counter_value() {
+        assert_eq!(
+            get_counter_value(METRICS, "rpcextractor_rpc_fetch_errors", "getpeerinfo"),
+            3
+        );
+        assert_eq!(
+            get_counter_value(METRICS, "rpcextractor_rpc_fetch_errors", "uptime"),
+            12
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "could not find the metric")]
+    fn test_get_counter_value_missing() {
+        get_counter_value(METRICS, "rpcextractor_rpc_fetch_errors", "getnettotals");
+    }
+
+    #[test]
+    fn test_get_counter_sum() {
+        let metrics = "rpcextractor_rpc_fetch_errors{error_kind=\"auth\",rpc_method=\"uptime\"} 2
+rpcextractor_rpc_fetch_errors{error_kind=\"timeout\",rpc_method=\"uptime\"} 3
+rpcextractor_rpc_fetch_errors{error_kind=\"timeout\",rpc_method=\"getpeerinfo\"} 4
+rpcextractor_rpc_fetch_errors_other{rpc_method=\"uptime\"} 100
+";
+        let errors = "rpcextractor_rpc_fetch_errors";
+        assert_eq!(get_counter_sum(metrics, errors, &[]), 9);
+        assert_eq!(
+            get_counter_sum(metrics, errors, &[("rpc_method", "uptime")]),
+            5
+        );
+        assert_eq!(
+            get_counter_sum(
+                metrics,
+                errors,
+                &[("rpc_method", "uptime"), ("error_kind", "timeout")]
+            ),
+            3
+        );
+        assert_eq!(
+            get_counter_sum(metrics, errors, &[("rpc_method", "getnettotals")]),
+            0
+        );
+    }
+
+    #[test]
+    fn test_get_histogram_count() {
+        assert_eq!(
+            get_histogram_count(METRICS, "rpcextractor_rpc_fetch_duration_seconds", "uptime"),
+            5
+        );
+    }
+
+    #[test]
+    fn test_get_histogram_sum() {
+        assert_eq!(
+            get_histogram_sum(METRICS, "rpcextractor_rpc_fetch_duration_seconds", "uptime"),
+            0.042
+        );
+    }
+
+    #[test]
+    fn test_get_counter_value_labeled() {
+        assert_eq!(
+            get_counter_value_labeled(METRICS, "p2pextractor_messages", "direction", "inbound"),
+            7
+        );
+        assert_eq!(
+            get_counter_value_labeled(METRICS, "p2pextractor_messages", "direction", "outbound"),
+            9
+        );
+        assert_eq!(
+            get_counter_value_labeled(
+                METRICS,
+                "rpcextractor_rpc_fetch_errors",
+                "rpc_method",
+                "uptime"
+            ),
+            get_counter_value(METRICS, "rpcextractor_rpc_fetch_errors", "uptime")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "could not find the metric")]
+    fn test_get_counter_value_labeled_wrong_label_name() {
+        get_counter_value_labeled(METRICS, "p2pextractor_messages", "rpc_method", "inbound");
+    }
+
+    #[test]
+    fn test_get_histogram_count_labeled() {
+        assert_eq!(
+            get_histogram_count_labeled(
+                METRICS,
+                "rpcextractor_index_sync_duration_seconds",
+                "index",
+                "txindex"
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn test_parse_metrics() {
+        let samples = parse_metrics(METRICS);
+        // all lines except the comments are samples
+        assert_eq!(samples.len(), 13);
+        assert!(samples.iter().all(|sample| !sample.name.starts_with('#')));
+        assert_eq!(
+            samples[0],
+            MetricSample {
+                name: "rpcextractor_rpc_fetch_errors".to_string(),
+                labels: BTreeMap::from([("rpc_method".to_string(), "getpeerinfo".to_string())]),
+                value: 3.0,
+            }
+        );
+
+        let bucket = samples
+            .iter()
+            .find(|sample| sample.labels.get("le").map(String::as_str) == Some("+Inf"))
+            .unwrap();
+        assert_eq!(
+            bucket.name,
+            "rpcextractor_rpc_fetch_duration_seconds_bucket"
+        );
+        assert_eq!(bucket.labels["rpc_method"], "uptime");
+        assert_eq!(bucket.value, 5.0);
+
+        let node2: Vec<&MetricSample> = samples
+            .iter()
+            .filter(|sample| sample.labels.get("node_id").map(String::as_str) == Some("node2"))
+            .collect();
+        assert_eq!(node2.len(), 1);
+        assert_eq!(node2[0].labels["rpc_method"], "getpeerinfo");
+        assert_eq!(node2[0].value, 1_700_000_001.0);
+
+        assert!(!samples
+            .iter()
+            .any(|sample| sample.name == "rpcextractor_rpc_fetch_errors"
+                && sample.labels["rpc_method"] == "getnettotals"));
+    }
+
+    #[test]
+    fn test_parse_metrics_edge_cases() {
+        let raw = "HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\n\r\n\
+            up 1\n\
+            \n\
+            escaped{path=\"a \\\"b\\\" \\\\ c\",line=\"x\\ny\"} 2.5 1700000000000\n\
+            nan_value{kind=\"a,b\"} NaN\n";
+        let samples = parse_metrics(raw);
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[0].name, "up");
+        assert!(samples[0].labels.is_empty());
+        assert_eq!(samples[0].value, 1.0);
+        assert_eq!(samples[1].labels["path"], "a \"b\" \\ c");
+        assert_eq!(samples[1].labels["line"], "x\ny");
+        assert_eq!(samples[1].value, 2.5);
+        assert_eq!(samples[2].labels["kind"], "a,b");
+        assert!(samples[2].value.is_nan());
+    }
+
+    #[test]
+    #[should_panic(expected = "could not parse the metric sample")]
+    fn test_parse_metrics_malformed() {
+        parse_metrics("broken{label=\"value} 1\n");
+    }
+
+    #[test]
+    fn test_get_gauge_value() {
+        let name = "rpcextractor_rpc_last_success_timestamp_seconds";
+        assert_eq!(get_gauge_value(METRICS, name, "uptime"), 1_700_000_000.5);
+        assert_eq!(
+            get_gauge_value(METRICS, name, "getpeerinfo"),
+            1_700_000_001.0
+        );
+        assert_eq!(get_gauge_value(METRICS, name, "node2"), 1_700_000_001.0);
+        assert_eq!(
+            get_gauge_value(METRICS, "rpcextractor_mempool_size", "node1"),
+            -1000.0
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "could not find the gauge")]
+    fn test_get_gauge_value_missing() {
+        get_gauge_value(
+            METRICS,
+            "rpcextractor_rpc_last_success_timestamp_seconds",
+            "getnettotals",
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "could not find the gauge")]
+    fn test_get_gauge_value_requires_full_metric_name() {
+        // only a prefix of the histogram's metric names
+        get_gauge_value(METRICS, "rpcextractor_rpc_fetch_duration", "uptime");
+    }
+}
@@ -1,4 +1,8 @@
+/// Helpers to fetch and parse Prometheus metrics in integration tests.
+pub mod metrics_fetcher;
 /// A NATS publisher to be used in integration tests.
 pub mod nats_publisher;
 /// A NATS server runnner to be used in integration tests.
 pub mod nats_server;
+/// A NATS subscriber collecting messages in integration tests.
+pub mod nats_subscriber;
@@ -7,9 +7,18 @@
 // - Renamed NATS_PATH_ENV to ENV_NATS_SERVER_BINARY
 // - Renamed NatsServer to NatsServerForTesting
 // - Changed the function new() to attempt to find a working port for NATS and hardcode all other nats-server args
+// - Added a JetStream-enabled variant with a temporary storage directory
+// - Added a TLS-enabled variant using the certificates in the fixtures
+// - Added a variant loading a nats-server config file
 
 use rand::Rng;
-use std::{env, process::Stdio, time::Duration};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process,
+    process::Stdio,
+    time::Duration,
+};
 use tokio::{
     io::{AsyncBufReadExt, BufReader},
     process::Command,
@@ -23,13 +32,61 @@ const ENV_NATS_SERVER_BINARY: &str = "NATS_SERVER_BINARY";
 const NATS_READY_MESSAGE: &str = "Server is ready";
 const NATS_PORT_IN_USE_MESSAGE: &str = "address already in use";
 
+// Test certificates for 127.0.0.1 and localhost, signed by the test CA.
+const TLS_FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/fixtures/tls");
+
 pub struct NatsServerForTesting {
     kill: Option<Sender<()>>,
     pub port: u16,
+    /// The CA certificate clients need to trust to connect to a TLS-enabled
+    /// server. None if TLS isn't enabled.
+    pub tls_ca_file: Option<String>,
+    // Temporary files and directories used by the server. Removed on drop.
+    temp_paths: Vec<PathBuf>,
 }
 
 impl NatsServerForTesting {
     pub async fn new(extra_args: &[&str]) -> Self {
+        Self::spawn(extra_args, None).await
+    }
+
+    /// Starts a NATS server with JetStream enabled. The JetStream storage is
+    /// placed in a temporary directory that's removed when the server is
+    /// dropped.
+    pub async fn new_with_jetstream() -> Self {
+        let store_dir = temp_path("jetstream");
+        fs::create_dir_all(&store_dir).expect("to create the JetStream storage directory");
+        let store_arg = format!("--store_dir={}", store_dir.display());
+        let mut server = Self::spawn(&["--jetstream", &store_arg], None).await;
+        server.temp_paths.push(store_dir);
+        server
+    }
+
+    /// Starts a NATS server that requires TLS. The server certificate is
+    /// signed by the CA in `tls_ca_file`.
+    pub async fn new_with_tls() -> Self {
+        let cert_arg = format!("--tlscert={}/server-cert.pem", TLS_FIXTURES_DIR);
+        let key_arg = format!("--tlskey={}/server-key.pem", TLS_FIXTURES_DIR);
+        let mut server = Self::spawn(&["--tls", &cert_arg, &key_arg], None).await;
+        server.tls_ca_file = Some(format!("{}/ca.pem", TLS_FIXTURES_DIR));
+        server
+    }
+
+    /// Starts a NATS server with the nats-server config file at `path`, for
+    /// example to configure permissions or limits. The server listens on a
+    /// free port, regardless of a port set in the config file.
+    pub async fn new_with_config(path: &str) -> Self {
+        let config_arg = format!("--config={}", path);
+        Self::spawn(&[&config_arg], None).await
+    }
+
+    /// Starts a NATS server on the passed port. This can be used to restart a
+    /// NATS server on the port of a previously dropped one.
+    pub async fn new_on_port(port: u16, extra_args: &[&str]) -> Self {
+        Self::spawn(extra_args, Some(port)).await
+    }
+
+    async fn spawn(extra_args: &[&str], fixed_port: Option<u16>) -> Self {
         let nats_server_binary_path: String = match env::var(ENV_NATS_SERVER_BINARY) {
             Ok(b) => b,
             Err(e) => {
@@ -41,8 +98,10 @@ impl NatsServerForTesting {
         };
 
         for attempt in 1..=PORT_ATTEMPTS {
-            let mut rng = rand::rng();
-            let nats_port = rng.random_range(49152..65500);
+            let nats_port = match fixed_port {
+                Some(port) => port,
+                None => rand::rng().random_range(49152..65500),
+            };
 
             log::debug!(
                 "attempting to use port={} for the testing NATS server (attempt={})",
@@ -138,9 +197,11 @@ impl NatsServerForTesting {
                         return Self {
                             kill: Some(kill_tx),
                             port: nats_port,
+                            tls_ca_file: None,
+                            temp_paths: vec![],
                         };
                     } else {
-                        log::warn!("NATS port already in use - trying again with another one");
+                        log::warn!("NATS port already in use - trying again");
                         continue;
                     }
                 }
@@ -156,10 +217,130 @@ impl NatsServerForTesting {
     }
 }
 
+/// Returns a unique path in the system's temporary directory.
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!(
+        "nats-server-for-testing-{}-{}-{}",
+        name,
+        process::id(),
+        rand::rng().random::<u64>()
+    ))
+}
+
+fn remove_temp_path(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
 impl Drop for NatsServerForTesting {
     fn drop(&mut self) {
         if let Some(kill) = self.kill.take() {
             kill.send(()).expect("to send kill oneshot")
         }
+        for path in self.temp_paths.iter() {
+            if let Err(e) = remove_temp_path(path) {
+                log::warn!("could not remove {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "nats_integration_tests")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_nats::jetstream;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_integration_natsserver_jetstream() {
+        println!("test that the JetStream-enabled NATS server persists published messages");
+
+        let nats_server = NatsServerForTesting::new_with_jetstream().await;
+        let store_dir = nats_server.temp_paths[0].clone();
+        assert!(store_dir.is_dir());
+
+        let client = async_nats::connect(format!("127.0.0.1:{}", nats_server.port))
+            .await
+            .unwrap();
+        let js = jetstream::new(client);
+        let mut stream = js
+            .create_stream(jetstream::stream::Config {
+                name: "EVENTS".to_string(),
+                subjects: vec!["events".to_string()],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        // the publish is only acknowledged once the message is stored
+        js.publish("events", "hello".into())
+            .await
+            .unwrap()
+            .await
+            .unwrap();
+
+        assert_eq!(stream.info().await.unwrap().state.messages, 1);
+        let message = stream.get_raw_message(1).await.unwrap();
+        assert_eq!(message.payload.as_ref(), b"hello");
+
+        drop(nats_server);
+        assert!(!store_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_integration_natsserver_config() {
+        println!("test that the NATS server uses the permissions from the config file");
+
+        let config = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/fixtures/nats-server-permissions.conf"
+        );
+        let nats_server = NatsServerForTesting::new_with_config(config).await;
+        let address = format!("127.0.0.1:{}", nats_server.port);
+
+        assert!(async_nats::connect(&address).await.is_err());
+        let client = async_nats::ConnectOptions::with_user_and_password(
+            "publisher".to_string(),
+            "publisher".to_string(),
+        )
+        .connect(&address)
+        .await
+        .unwrap();
+        let mut subscriber = client.subscribe("*").await.unwrap();
+        client.publish("restricted", "denied".into()).await.unwrap();
+        client.publish("allowed", "allowed".into()).await.unwrap();
+        client.flush().await.unwrap();
+
+        // the publish to the restricted subject is dropped by the server
+        let message = timeout(Duration::from_secs(5), subscriber.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.subject.as_str(), "allowed");
+    }
+
+    #[tokio::test]
+    async fn test_integration_natsserver_tls() {
+        println!("test that the TLS-enabled NATS server completes a TLS handshake");
+
+        let nats_server = NatsServerForTesting::new_with_tls().await;
+        let address = format!("127.0.0.1:{}", nats_server.port);
+        let ca_file = nats_server.tls_ca_file.clone().unwrap();
+
+        let client = async_nats::ConnectOptions::new()
+            .require_tls(true)
+            .add_root_certificates(PathBuf::from(&ca_file))
+            .connect(&address)
+            .await
+            .expect("the TLS handshake should work");
+        client.publish("test", "hello".into()).await.unwrap();
+        client.flush().await.unwrap();
+
+        // the test CA isn't trusted by default
+        assert!(async_nats::connect(&address).await.is_err());
     }
 }
@@ -0,0 +1,107 @@
+use async_nats::{Message, Subscriber};
+use futures::StreamExt;
+use std::time::Duration;
+use tokio::time::{self, Instant};
+
+pub struct NatsSubscriberForTesting {
+    subscriber: Subscriber,
+}
+
+impl NatsSubscriberForTesting {
+    /// Connects to the NATS server on the `port` and subscribes to the
+    /// `subject`. Messages published after this returns are collected.
+    pub async fn new(port: u16, subject: &str) -> Self {
+        let addr = format!("127.0.0.1:{}", port);
+        log::debug!("The testing NATS subscriber is connecting to {}..", addr);
+        let client = async_nats::connect(addr)
+            .await
+            .expect("should be able to connect to NATS server");
+        Self {
+            subscriber: client
+                .subscribe(subject.to_string())
+                .await
+                .expect("should be able to subscribe"),
+        }
+    }
+
+    /// Waits for up to `count` messages, but no longer than `timeout`, and
+    /// returns the received messages.
+    pub async fn collect_messages(&mut self, count: usize, timeout: Duration) -> Vec<Message> {
+        let deadline = Instant::now() + timeout;
+        let mut messages = Vec::with_capacity(count);
+        while messages.len() < count {
+            match time::timeout_at(deadline, self.subscriber.next()).await {
+                Ok(Some(message)) => messages.push(message),
+                Ok(None) | Err(_) => break,
+            }
+        }
+        messages
+    }
+}
+
+/// Subscribes to the `subject` on the NATS server on the `port` and returns
+/// up to `count` messages received within the `timeout`. Only messages
+/// published after subscribing are received, so this suits continuously
+/// publishing sources like the extractors. Use a [NatsSubscriberForTesting]
+/// to subscribe before publishing.
+pub async fn collect_messages(
+    port: u16,
+    subject: &str,
+    count: usize,
+    timeout: Duration,
+) -> Vec<Message> {
+    NatsSubscriberForTesting::new(port, subject)
+        .await
+        .collect_messages(count, timeout)
+        .await
+}
+
+#[cfg(feature = "nats_integration_tests")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::nats_publisher::NatsPublisherForTesting;
+    use crate::testing::nats_server::NatsServerForTesting;
+
+    #[tokio::test]
+    async fn test_integration_natssubscriber_collect_messages() {
+        println!("test that the published messages are collected");
+
+        let nats_server = NatsServerForTesting::new(&[]).await;
+        let mut subscriber = NatsSubscriberForTesting::new(nats_server.port, "test.*").await;
+        let publisher = NatsPublisherForTesting::new(nats_server.port).await;
+        for i in 0..5u8 {
+            publisher.publish(format!("test.{}", i), vec![i]).await;
+        }
+        // not matching the subscribed subject
+        publisher.publish("other".to_string(), vec![]).await;
+
+        let messages = subscriber.collect_messages(5, Duration::from_secs(5)).await;
+        assert_eq!(messages.len(), 5);
+        for (i, message) in messages.iter().enumerate() {
+            assert_eq!(message.subject.as_str(), format!("test.{}", i));
+            assert_eq!(message.payload.as_ref(), [i as u8]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_integration_natssubscriber_collect_messages_timeout() {
+        println!("test that collecting messages stops after the timeout");
+
+        let nats_server = NatsServerForTesting::new(&[]).await;
+        let mut subscriber = NatsSubscriberForTesting::new(nats_server.port, "test").await;
+        let publisher = NatsPublisherForTesting::new(nats_server.port).await;
+        publisher.publish("test".to_string(), vec![1]).await;
+
+        let start = Instant::now();
+        let messages = subscriber
+            .collect_messages(3, Duration::from_millis(500))
+            .await;
+        assert_eq!(messages.len(), 1);
+        assert!(start.elapsed() >= Duration::from_millis(500));
+
+        let messages =
+            collect_messages(nats_server.port, "test", 1, Duration::from_millis(200)).await;
+        assert!(messages.is_empty());
+    }
+}
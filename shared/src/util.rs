@@ -74,6 +74,18 @@ pub fn is_on_linkinglion_banlist(ip: &str) -> bool {
         || ip.starts_with("2602:f5c0:")
 }
 
+/// Serializes a secret, e.g. a password, as `"<redacted>"`, or as `null` if
+/// it isn't set. For use with `#[serde(serialize_with = "...")]`.
+pub fn serialize_redacted<S: serde::Serializer>(
+    secret: &Option<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match secret {
+        Some(_) => serializer.serialize_str("<redacted>"),
+        None => serializer.serialize_none(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -226,4 +238,23 @@ mod tests {
         assert!(belongs_to_bitprojects("66.163.223.129"));
         assert!(belongs_to_bitprojects("173.46.87.56"));
     }
+
+    #[test]
+    fn test_serialize_redacted() {
+        #[derive(serde::Serialize)]
+        struct Secrets {
+            #[serde(serialize_with = "serialize_redacted")]
+            set: Option<String>,
+            #[serde(serialize_with = "serialize_redacted")]
+            unset: Option<String>,
+        }
+        let secrets = Secrets {
+            set: Some("hunter2".to_string()),
+            unset: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&secrets).unwrap(),
+            r#"{"set":"<redacted>","unset":null}"#
+        );
+    }
 }
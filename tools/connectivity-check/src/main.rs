@@ -330,7 +330,9 @@ async fn main() {
 
     let nc = nats_util::prepare_connection(&args.nats)
         .expect("should be able to open a password file")
-        .connect(&args.nats.address)
+        .connect(
+            nats_util::server_addrs(&args.nats).expect("should be able to parse the NATS address"),
+        )
         .await
         .expect("should be able to connect to NATS server");
 
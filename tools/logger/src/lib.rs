@@ -125,7 +125,7 @@ pub async fn run(args: Args, mut shutdown_rx: watch::Receiver<bool>) -> Result<(
     }
 
     let nc = nats_util::prepare_connection(&args.nats)?
-        .connect(&args.nats.address)
+        .connect(nats_util::server_addrs(&args.nats)?)
         .await?;
 
     let mut sub = nc.subscribe("*").await?;
@@ -214,5 +214,10 @@ fn log_event(event: Event, args: Args) {
                 );
             }
         }
+        PeerObserverEvent::ZmqExtractor(z) => {
+            if log_all {
+                log::info!("zmq event: {}", z.zmq_event.unwrap());
+            }
+        }
     }
 }
@@ -81,9 +81,7 @@ fn make_test_args(
     Args::new(
         nats_util::NatsArgs {
             address: format!("127.0.0.1:{}", nats_port),
-            username: None,
-            password: None,
-            password_file: None,
+            ..Default::default()
         },
         log::Level::Trace,
         messages,
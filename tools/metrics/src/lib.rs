@@ -77,7 +77,7 @@ pub async fn run(
     metricserver::start(&args.metrics_address, Some(metrics.registry.clone()))?;
 
     let nc = nats_util::prepare_connection(&args.nats)?
-        .connect(&args.nats.address)
+        .connect(nats_util::server_addrs(&args.nats)?)
         .await?;
     info!("Connected to NATS-server at {}", args.nats.address);
     let mut sub = nc.subscribe("*").await?;
@@ -153,6 +153,8 @@ fn handle_event(
             PeerObserverEvent::LogExtractor(l) => {
                 handle_log_event(&l, metrics);
             }
+            // The zmq-extractor events aren't turned into metrics (yet).
+            PeerObserverEvent::ZmqExtractor(_) => (),
         }
     }
 
@@ -668,6 +670,18 @@ fn handle_rpc_event(e: &rpc::RpcEvent, metrics: metrics::Metrics) {
                 metrics.rpc_chaintxstats_tx_rate.set(tx_rate);
             }
         }
+        // not (yet) turned into metrics
+        rpc::RpcEvent::BlockCount(_)
+        | rpc::RpcEvent::MiningInfo(_)
+        | rpc::RpcEvent::RawMempool(_)
+        | rpc::RpcEvent::ChainTips(_)
+        | rpc::RpcEvent::NodeAddresses(_)
+        | rpc::RpcEvent::Banned(_)
+        | rpc::RpcEvent::FeeEstimates(_)
+        | rpc::RpcEvent::IndexInfo(_)
+        | rpc::RpcEvent::DeploymentInfo(_)
+        | rpc::RpcEvent::TxOutSetInfo(_)
+        | rpc::RpcEvent::Reorg(_) => (),
     }
 }
 
@@ -1130,7 +1144,9 @@ fn handle_log_event(log: &Log, metrics: metrics::Metrics) {
 
     let Some(e) = &log.log_event else { return };
     match e {
-        log::LogEvent::UnknownLogMessage(_) => {}
+        log::LogEvent::UnknownLogMessage(_)
+        | log::LogEvent::UpdateTipLog(_)
+        | log::LogEvent::MisbehavingLog(_) => {}
         log::LogEvent::BlockConnectedLog(_) => {
             metrics.log_block_connected_events.inc();
         }
@@ -59,7 +59,7 @@ pub async fn run(
     mut shutdown_rx: watch::Receiver<bool>,
 ) -> Result<(), error::RuntimeError> {
     let nc = nats_util::prepare_connection(&args.nats)?
-        .connect(&args.nats.address)
+        .connect(nats_util::server_addrs(&args.nats)?)
         .await?;
     log::info!("Connected to NATS-server at {}", args.nats.address);
     let mut sub = nc.subscribe("*").await?;
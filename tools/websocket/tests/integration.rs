@@ -66,9 +66,7 @@ fn make_test_args(nats_port: u16, websocket_port: u16) -> Args {
     Args::new(
         NatsArgs {
             address: format!("127.0.0.1:{}", nats_port),
-            username: None,
-            password: None,
-            password_file: None,
+            ..Default::default()
         },
         format!("127.0.0.1:{}", websocket_port),
         log::Level::Trace,